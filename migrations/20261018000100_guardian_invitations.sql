@@ -0,0 +1,33 @@
+-- Códigos de invitación para vincular apoderados con alumnos
+CREATE TABLE IF NOT EXISTS guardian_invitations (
+    id SERIAL PRIMARY KEY,
+    code VARCHAR(16) NOT NULL UNIQUE,
+    student_id INTEGER REFERENCES students(id) ON DELETE SET NULL,
+    student_user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    relationship_type VARCHAR(50),
+    created_by_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP NOT NULL,
+    redeemed_at TIMESTAMP,
+    redeemed_by_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    revoked_at TIMESTAMP,
+    revoked_by_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    revoke_reason TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_guardian_invitations_student_user
+    ON guardian_invitations (student_user_id);
+
+-- Auditoría de canjes (exitosos y fallidos)
+CREATE TABLE IF NOT EXISTS guardian_invitation_redemptions (
+    id SERIAL PRIMARY KEY,
+    invitation_id INTEGER REFERENCES guardian_invitations(id) ON DELETE CASCADE,
+    code VARCHAR(16) NOT NULL,
+    guardian_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    success BOOLEAN NOT NULL,
+    reason VARCHAR(30) NOT NULL,
+    attempted_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_guardian_invitation_redemptions_invitation
+    ON guardian_invitation_redemptions (invitation_id);
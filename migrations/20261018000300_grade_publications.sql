@@ -0,0 +1,9 @@
+-- Publicación de notas por (sesión, competencia).
+-- Alumnos y apoderados solo ven valores de pares publicados.
+CREATE TABLE IF NOT EXISTS grade_publications (
+    session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    competency_id INTEGER NOT NULL REFERENCES competencies(id) ON DELETE CASCADE,
+    published_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    published_by_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    PRIMARY KEY (session_id, competency_id)
+);
@@ -0,0 +1,64 @@
+-- Docentes asignados a cada sección (a quién se enrutan los reclamos)
+CREATE TABLE IF NOT EXISTS section_teachers (
+    section_id INTEGER NOT NULL REFERENCES sections(id) ON DELETE CASCADE,
+    teacher_user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    is_tutor BOOLEAN NOT NULL DEFAULT FALSE,
+    assigned_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (section_id, teacher_user_id)
+);
+
+-- Notificaciones internas por usuario
+CREATE TABLE IF NOT EXISTS notifications (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind VARCHAR(50) NOT NULL,
+    title TEXT NOT NULL,
+    body TEXT,
+    payload JSONB NOT NULL DEFAULT '{}'::jsonb,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    read_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_notifications_user_unread
+    ON notifications (user_id) WHERE read_at IS NULL;
+
+-- Reclamos sobre una celda de evaluation_items
+CREATE TABLE IF NOT EXISTS grade_appeals (
+    id SERIAL PRIMARY KEY,
+    evaluation_item_id INTEGER NOT NULL REFERENCES evaluation_items(id) ON DELETE CASCADE,
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    submitted_by_user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    assigned_teacher_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    reason TEXT NOT NULL,
+    requested_value VARCHAR(4),
+    original_value VARCHAR(4) NOT NULL,
+    resolved_value VARCHAR(4),
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'accepted', 'rejected', 'withdrawn')),
+    response TEXT,
+    resolved_by_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    resolved_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Un solo reclamo pendiente por celda
+CREATE UNIQUE INDEX IF NOT EXISTS uq_grade_appeals_pending_item
+    ON grade_appeals (evaluation_item_id) WHERE status = 'pending';
+
+CREATE INDEX IF NOT EXISTS idx_grade_appeals_teacher
+    ON grade_appeals (assigned_teacher_user_id, status);
+
+-- Historial de cambios de valor en evaluation_items
+CREATE TABLE IF NOT EXISTS evaluation_item_history (
+    id SERIAL PRIMARY KEY,
+    evaluation_item_id INTEGER NOT NULL REFERENCES evaluation_items(id) ON DELETE CASCADE,
+    old_value VARCHAR(4),
+    new_value VARCHAR(4) NOT NULL,
+    changed_by_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    appeal_id INTEGER REFERENCES grade_appeals(id) ON DELETE SET NULL,
+    reason TEXT,
+    changed_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_evaluation_item_history_item
+    ON evaluation_item_history (evaluation_item_id);
@@ -0,0 +1,18 @@
+-- Conclusiones descriptivas por alumno, competencia y bimestre.
+-- La competencia se identifica por su nombre visible, porque cada sesión
+-- tiene sus propias filas en competencies.
+CREATE TABLE IF NOT EXISTS descriptive_conclusions (
+    id SERIAL PRIMARY KEY,
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    bimester_id INTEGER NOT NULL REFERENCES bimesters(id) ON DELETE CASCADE,
+    competency_name TEXT NOT NULL,
+    level VARCHAR(4) NOT NULL,
+    draft_text TEXT NOT NULL,
+    text TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'draft' CHECK (status IN ('draft', 'approved')),
+    generated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    approved_by_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    approved_at TIMESTAMP,
+    UNIQUE (student_id, bimester_id, competency_name)
+);
@@ -0,0 +1,11 @@
+-- Código oficial SIAGIE de cada competencia, identificada por su nombre visible
+CREATE TABLE IF NOT EXISTS siagie_competency_mappings (
+    id SERIAL PRIMARY KEY,
+    competency_name TEXT NOT NULL,
+    siagie_code VARCHAR(20) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_siagie_mappings_name
+    ON siagie_competency_mappings (LOWER(TRIM(competency_name)));
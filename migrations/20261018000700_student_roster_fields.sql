@@ -0,0 +1,3 @@
+-- Datos adicionales del padrón importado
+ALTER TABLE students ADD COLUMN IF NOT EXISTS birth_date DATE;
+ALTER TABLE students ADD COLUMN IF NOT EXISTS gender VARCHAR(1) CHECK (gender IN ('M', 'F'));
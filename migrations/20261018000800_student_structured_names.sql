@@ -0,0 +1,12 @@
+-- Nombre del estudiante por partes; full_name se deriva de estas columnas
+ALTER TABLE students ADD COLUMN IF NOT EXISTS paternal_surname VARCHAR(100);
+ALTER TABLE students ADD COLUMN IF NOT EXISTS maternal_surname VARCHAR(100);
+ALTER TABLE students ADD COLUMN IF NOT EXISTS given_names VARCHAR(150);
+
+-- Motivo por el que la división automática del nombre necesita revisión manual
+ALTER TABLE students ADD COLUMN IF NOT EXISTS name_review_reason TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_students_name_review
+    ON students (section_id) WHERE name_review_reason IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_students_surnames
+    ON students (section_id, paternal_surname, maternal_surname, given_names);
@@ -0,0 +1,10 @@
+-- Candidatos descartados en la revisión de vínculos cuenta-padrón: no se vuelven a proponer
+CREATE TABLE IF NOT EXISTS link_review_rejections (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    rejected_by_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    reason TEXT,
+    rejected_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, student_id)
+);
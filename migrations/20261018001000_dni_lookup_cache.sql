@@ -0,0 +1,6 @@
+-- Respuestas exitosas de RENIEC por DNI; se consideran vigentes mientras no venza el TTL
+CREATE TABLE IF NOT EXISTS dni_lookup_cache (
+    dni VARCHAR(8) PRIMARY KEY,
+    data JSONB NOT NULL,
+    fetched_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
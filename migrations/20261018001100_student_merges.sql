@@ -0,0 +1,17 @@
+-- Auditoría de fusiones de alumnos duplicados: se guarda el registro eliminado
+-- tal como estaba y el resumen de lo que se movió
+CREATE TABLE IF NOT EXISTS student_merges (
+    id SERIAL PRIMARY KEY,
+    surviving_student_id INTEGER REFERENCES students(id) ON DELETE SET NULL,
+    duplicate_student_id INTEGER NOT NULL,
+    duplicate_snapshot JSONB NOT NULL,
+    conflict_policy VARCHAR(20) NOT NULL
+        CHECK (conflict_policy IN ('keep_surviving', 'keep_duplicate', 'keep_latest')),
+    summary JSONB NOT NULL,
+    reason TEXT,
+    merged_by_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    merged_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_student_merges_surviving
+    ON student_merges (surviving_student_id);
@@ -0,0 +1,48 @@
+-- Año académico con sus fechas y estado; cada año agrupa cuatro bimestres
+CREATE TABLE IF NOT EXISTS academic_years (
+    id SERIAL PRIMARY KEY,
+    year INTEGER NOT NULL UNIQUE,
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'planning'
+        CHECK (status IN ('planning', 'active', 'closed')),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK (end_date > start_date)
+);
+
+-- Un solo año activo a la vez
+CREATE UNIQUE INDEX IF NOT EXISTS uq_academic_years_active
+    ON academic_years (status) WHERE status = 'active';
+
+ALTER TABLE bimesters
+    ADD COLUMN IF NOT EXISTS academic_year_id INTEGER REFERENCES academic_years(id) ON DELETE CASCADE,
+    ADD COLUMN IF NOT EXISTS number INTEGER CHECK (number BETWEEN 1 AND 4),
+    ADD COLUMN IF NOT EXISTS start_date DATE,
+    ADD COLUMN IF NOT EXISTS end_date DATE;
+
+-- Bimestres creados sin año: se asignan al año en curso
+UPDATE bimesters SET year = EXTRACT(YEAR FROM NOW())::int WHERE year IS NULL;
+ALTER TABLE bimesters ALTER COLUMN year SET NOT NULL;
+
+UPDATE bimesters
+SET number = CASE UPPER(TRIM(name))
+    WHEN 'I' THEN 1 WHEN 'II' THEN 2 WHEN 'III' THEN 3 WHEN 'IV' THEN 4
+END
+WHERE number IS NULL;
+
+-- Un año por cada año que ya tenía bimestres: los anteriores quedan cerrados
+INSERT INTO academic_years (year, start_date, end_date, status)
+SELECT DISTINCT b.year, make_date(b.year, 3, 1), make_date(b.year, 12, 31),
+       CASE
+           WHEN b.year < EXTRACT(YEAR FROM NOW()) THEN 'closed'
+           WHEN b.year = EXTRACT(YEAR FROM NOW()) THEN 'active'
+           ELSE 'planning'
+       END
+FROM bimesters b
+ON CONFLICT (year) DO NOTHING;
+
+UPDATE bimesters b SET academic_year_id = ay.id
+FROM academic_years ay
+WHERE ay.year = b.year AND b.academic_year_id IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_bimesters_academic_year ON bimesters (academic_year_id);
@@ -0,0 +1,17 @@
+-- Decisiones de fin de año por alumno: a qué matrícula del año siguiente pasó,
+-- o si repite, se retira o egresa. Evita procesar dos veces al mismo alumno.
+CREATE TABLE IF NOT EXISTS student_promotions (
+    id SERIAL PRIMARY KEY,
+    source_bimester_id INTEGER NOT NULL REFERENCES bimesters(id) ON DELETE CASCADE,
+    target_bimester_id INTEGER NOT NULL REFERENCES bimesters(id) ON DELETE CASCADE,
+    source_student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    target_student_id INTEGER REFERENCES students(id) ON DELETE SET NULL,
+    decision VARCHAR(20) NOT NULL
+        CHECK (decision IN ('promote', 'repeat', 'leave', 'graduate')),
+    decided_by_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (source_student_id, target_bimester_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_student_promotions_target
+    ON student_promotions (target_bimester_id);
@@ -0,0 +1,126 @@
+-- Persona que estudia en el colegio, independiente de la sección y el bimestre.
+-- Cada fila de students pasa a ser una matrícula de un learner en una sección.
+CREATE TABLE IF NOT EXISTS learners (
+    id SERIAL PRIMARY KEY,
+    dni VARCHAR(8) UNIQUE,
+    user_id INTEGER UNIQUE REFERENCES users(id) ON DELETE SET NULL,
+    full_name VARCHAR(255) NOT NULL,
+    paternal_surname VARCHAR(100),
+    maternal_surname VARCHAR(100),
+    given_names VARCHAR(150),
+    birth_date DATE,
+    gender VARCHAR(1) CHECK (gender IN ('M', 'F')),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- student_id es la fila por sección que sigue guardando notas y asistencia
+CREATE TABLE IF NOT EXISTS enrollments (
+    id SERIAL PRIMARY KEY,
+    learner_id INTEGER NOT NULL REFERENCES learners(id) ON DELETE CASCADE,
+    section_id INTEGER NOT NULL REFERENCES sections(id) ON DELETE CASCADE,
+    student_id INTEGER NOT NULL UNIQUE REFERENCES students(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'active'
+        CHECK (status IN ('active', 'completed', 'withdrawn', 'transferred')),
+    start_date DATE,
+    end_date DATE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_enrollments_learner ON enrollments (learner_id);
+CREATE INDEX IF NOT EXISTS idx_enrollments_section ON enrollments (section_id);
+
+-- Asigna la fila de students a su learner: primero por DNI, luego por cuenta; si no
+-- hay coincidencia conserva el learner actual o crea uno nuevo. Completa el DNI y la
+-- cuenta del learner cuando no chocan con otro y borra el learner que quede sin
+-- matrículas al reasignar.
+CREATE OR REPLACE FUNCTION public.sync_student_learner(p_student_id INTEGER)
+RETURNS INTEGER AS $$
+DECLARE
+    s students%ROWTYPE;
+    v_learner INTEGER;
+    v_previous INTEGER;
+BEGIN
+    SELECT * INTO s FROM students WHERE id = p_student_id;
+    IF NOT FOUND THEN
+        RETURN NULL;
+    END IF;
+    SELECT learner_id INTO v_previous FROM enrollments WHERE student_id = s.id;
+
+    IF s.dni IS NOT NULL THEN
+        SELECT id INTO v_learner FROM learners WHERE dni = s.dni;
+    END IF;
+    IF v_learner IS NULL AND s.user_id IS NOT NULL THEN
+        SELECT id INTO v_learner FROM learners WHERE user_id = s.user_id;
+    END IF;
+    v_learner := COALESCE(v_learner, v_previous);
+    IF v_learner IS NULL THEN
+        INSERT INTO learners (full_name) VALUES (s.full_name) RETURNING id INTO v_learner;
+    END IF;
+
+    INSERT INTO enrollments (learner_id, section_id, student_id, start_date, end_date)
+    SELECT v_learner, s.section_id, s.id, b.start_date, b.end_date
+    FROM sections sec
+    JOIN grades g ON g.id = sec.grade_id
+    JOIN bimesters b ON b.id = g.bimester_id
+    WHERE sec.id = s.section_id
+    ON CONFLICT (student_id) DO UPDATE
+        SET learner_id = EXCLUDED.learner_id, section_id = EXCLUDED.section_id;
+
+    IF v_previous IS NOT NULL AND v_previous <> v_learner THEN
+        DELETE FROM learners l
+        WHERE l.id = v_previous
+          AND NOT EXISTS (SELECT 1 FROM enrollments e WHERE e.learner_id = l.id);
+    END IF;
+
+    UPDATE learners l SET
+        dni = COALESCE(l.dni, s.dni),
+        user_id = COALESCE(l.user_id, CASE
+            WHEN NOT EXISTS (SELECT 1 FROM learners o WHERE o.user_id = s.user_id)
+            THEN s.user_id
+        END),
+        full_name = s.full_name,
+        paternal_surname = COALESCE(s.paternal_surname, l.paternal_surname),
+        maternal_surname = COALESCE(s.maternal_surname, l.maternal_surname),
+        given_names = COALESCE(s.given_names, l.given_names),
+        birth_date = COALESCE(s.birth_date, l.birth_date),
+        gender = COALESCE(s.gender, l.gender),
+        updated_at = NOW()
+    WHERE l.id = v_learner;
+
+    RETURN v_learner;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION public.students_sync_learner_trigger()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM public.sync_student_learner(NEW.id);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_students_sync_learner ON students;
+CREATE TRIGGER trg_students_sync_learner
+    AFTER INSERT OR UPDATE OF dni, user_id, section_id, full_name ON students
+    FOR EACH ROW EXECUTE FUNCTION public.students_sync_learner_trigger();
+
+-- Agrupa las filas existentes en orden de creación: las que comparten DNI o cuenta
+-- quedan en el mismo learner
+DO $$
+DECLARE
+    r RECORD;
+BEGIN
+    FOR r IN SELECT id FROM students ORDER BY id LOOP
+        PERFORM public.sync_student_learner(r.id);
+    END LOOP;
+END;
+$$;
+
+-- Las matrículas de años ya cerrados quedan como completadas
+UPDATE enrollments e SET status = 'completed'
+FROM sections sec
+JOIN grades g ON g.id = sec.grade_id
+JOIN bimesters b ON b.id = g.bimester_id
+JOIN academic_years ay ON ay.id = b.academic_year_id
+WHERE sec.id = e.section_id AND ay.status = 'closed' AND e.status = 'active';
@@ -0,0 +1,13 @@
+-- Un número de bimestre por año académico. Si ya hay repetidos, el más antiguo
+-- conserva el número y los demás quedan sin número para corregirlos a mano.
+UPDATE bimesters b SET number = NULL
+WHERE b.number IS NOT NULL
+  AND EXISTS (
+      SELECT 1 FROM bimesters o
+      WHERE o.academic_year_id = b.academic_year_id
+        AND o.number = b.number
+        AND o.id < b.id
+  );
+
+CREATE UNIQUE INDEX IF NOT EXISTS uq_bimesters_year_number
+    ON bimesters (academic_year_id, number);
@@ -0,0 +1,20 @@
+-- Al borrar una fila de students su matrícula se va en cascada; el learner que se
+-- queda sin matrículas se borra también
+CREATE OR REPLACE FUNCTION public.enrollments_delete_orphan_learner_trigger()
+RETURNS TRIGGER AS $$
+BEGIN
+    DELETE FROM learners l
+    WHERE l.id = OLD.learner_id
+      AND NOT EXISTS (SELECT 1 FROM enrollments e WHERE e.learner_id = l.id);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_enrollments_delete_orphan_learner ON enrollments;
+CREATE TRIGGER trg_enrollments_delete_orphan_learner
+    AFTER DELETE ON enrollments
+    FOR EACH ROW EXECUTE FUNCTION public.enrollments_delete_orphan_learner_trigger();
+
+-- Learners que ya quedaron sin matrículas antes de este trigger
+DELETE FROM learners l
+WHERE NOT EXISTS (SELECT 1 FROM enrollments e WHERE e.learner_id = l.id);
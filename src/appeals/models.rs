@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Serialize, FromRow)]
+pub struct GradeAppeal {
+    pub id: i32,
+    pub evaluation_item_id: i32,
+    pub student_id: i32,
+    pub student_name: String,
+    pub section_id: i32,
+    pub session_id: i32,
+    pub session_title: Option<String>,
+    pub competency_name: Option<String>,
+    pub ability_name: Option<String>,
+    pub criterion_name: Option<String>,
+    pub submitted_by_user_id: i32,
+    pub assigned_teacher_user_id: Option<i32>,
+    pub reason: String,
+    pub requested_value: Option<String>,
+    pub original_value: String,
+    pub current_value: String,
+    pub resolved_value: Option<String>,
+    pub status: String,
+    pub response: Option<String>,
+    pub resolved_by_user_id: Option<i32>,
+    pub resolved_at: Option<chrono::NaiveDateTime>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct NewAppealIn {
+    pub evaluation_item_id: i32,
+    pub reason: String,
+    pub requested_value: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ResolveAppealIn {
+    pub decision: String,
+    pub new_value: Option<String>,
+    pub response: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AppealFilter {
+    pub status: Option<String>,
+}
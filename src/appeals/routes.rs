@@ -0,0 +1,595 @@
+use crate::appeals::models::*;
+use crate::auth::guard::{current_user, require_role};
+use crate::auth::models::{User, UserRole};
+use crate::basic::session::evaluation::models::EVAL_LEVELS;
+use crate::notifications::routes::notify;
+use crate::AppState;
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use sqlx::{PgConnection, PgPool, Row};
+
+const APPEAL_SELECT: &str = r#"
+    SELECT
+        a.id, a.evaluation_item_id, a.student_id, s.full_name AS student_name,
+        s.section_id, ei.session_id, sess.title AS session_title,
+        comp.name AS competency_name, abl.name AS ability_name, crt.name AS criterion_name,
+        a.submitted_by_user_id, a.assigned_teacher_user_id, a.reason,
+        a.requested_value, a.original_value, ei.value::text AS current_value,
+        a.resolved_value, a.status, a.response, a.resolved_by_user_id,
+        a.resolved_at, a.created_at
+    FROM grade_appeals a
+    JOIN evaluation_items ei ON ei.id = a.evaluation_item_id
+    JOIN students s ON s.id = a.student_id
+    JOIN sessions sess ON sess.id = ei.session_id
+    JOIN competencies comp ON comp.id = ei.competency_id
+    JOIN abilities abl ON abl.id = ei.ability_id
+    JOIN criteria crt ON crt.id = ei.criterion_id
+"#;
+
+// Cada rol solo ve los reclamos que le corresponden:
+// alumno los propios, apoderado los de sus hijos, docente los de sus secciones
+async fn fetch_visible_appeals(
+    pool: &PgPool,
+    user: &User,
+    status: Option<&str>,
+    appeal_id: Option<i32>,
+) -> Result<Vec<GradeAppeal>, sqlx::Error> {
+    let sql = format!(
+        r#"
+        {}
+        WHERE (
+            $1 = 'ADMIN'
+            OR ($1 = 'ALUMNO' AND s.user_id = $2)
+            OR ($1 = 'APODERADO' AND s.user_id IN (
+                SELECT student_user_id FROM guardian_student_relationships WHERE guardian_user_id = $2
+            ))
+            OR ($1 = 'DOCENTE' AND (
+                a.assigned_teacher_user_id = $2
+                OR EXISTS (
+                    SELECT 1 FROM section_teachers st
+                    WHERE st.section_id = s.section_id AND st.teacher_user_id = $2
+                )
+            ))
+        )
+          AND ($3::text IS NULL OR a.status = $3)
+          AND ($4::int IS NULL OR a.id = $4)
+        ORDER BY a.created_at DESC
+        "#,
+        APPEAL_SELECT
+    );
+
+    sqlx::query_as::<_, GradeAppeal>(&sql)
+        .bind(user.role.to_string())
+        .bind(user.id)
+        .bind(status)
+        .bind(appeal_id)
+        .fetch_all(pool)
+        .await
+}
+
+// Cambia el valor de una celda dejando el cambio en evaluation_item_history
+pub async fn change_evaluation_value(
+    conn: &mut PgConnection,
+    evaluation_item_id: i32,
+    new_value: &str,
+    changed_by_user_id: i32,
+    appeal_id: Option<i32>,
+    reason: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let old_value = sqlx::query_scalar::<_, String>(
+        "SELECT value::text FROM evaluation_items WHERE id = $1 FOR UPDATE",
+    )
+    .bind(evaluation_item_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        "UPDATE evaluation_items SET value = $1::eval_level, updated_at = NOW() WHERE id = $2",
+    )
+    .bind(new_value)
+    .bind(evaluation_item_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO evaluation_item_history
+        (evaluation_item_id, old_value, new_value, changed_by_user_id, appeal_id, reason)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(evaluation_item_id)
+    .bind(old_value)
+    .bind(new_value)
+    .bind(changed_by_user_id)
+    .bind(appeal_id)
+    .bind(reason)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[post("/api/appeals")]
+pub async fn create_appeal(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    body: web::Json<NewAppealIn>,
+) -> impl Responder {
+    let user = match require_role(&data.pool, &req, &[UserRole::Alumno, UserRole::Apoderado]).await
+    {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let reason = body.reason.trim();
+    if reason.is_empty() {
+        return HttpResponse::BadRequest().body("Debe indicar el motivo del reclamo");
+    }
+    if let Some(v) = &body.requested_value {
+        if !EVAL_LEVELS.contains(&v.as_str()) {
+            return HttpResponse::BadRequest().body("Valor solicitado inválido");
+        }
+    }
+
+    let item = match sqlx::query(
+        r#"
+        SELECT ei.id, ei.student_id, ei.value::text AS value, s.user_id AS student_user_id,
+               s.section_id,
+               EXISTS (
+                   SELECT 1 FROM grade_publications gp
+                   WHERE gp.session_id = ei.session_id AND gp.competency_id = ei.competency_id
+               ) AS published,
+               EXISTS (
+                   SELECT 1 FROM guardian_student_relationships gsr
+                   WHERE gsr.guardian_user_id = $2 AND gsr.student_user_id = s.user_id
+               ) AS is_guardian
+        FROM evaluation_items ei
+        JOIN students s ON s.id = ei.student_id
+        WHERE ei.id = $1
+        "#,
+    )
+    .bind(body.evaluation_item_id)
+    .bind(user.id)
+    .fetch_optional(&data.pool)
+    .await
+    {
+        Ok(Some(row)) => row,
+        Ok(None) => return HttpResponse::NotFound().body("Evaluación no encontrada"),
+        Err(e) => {
+            eprintln!("Error fetching evaluation item: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+
+    let student_id: i32 = item.try_get("student_id").unwrap();
+    let student_user_id: Option<i32> = item.try_get("student_user_id").unwrap();
+    let section_id: i32 = item.try_get("section_id").unwrap();
+    let original_value: String = item.try_get("value").unwrap();
+
+    let allowed = match user.role {
+        UserRole::Alumno => student_user_id == Some(user.id),
+        _ => item.try_get::<bool, _>("is_guardian").unwrap(),
+    };
+    if !allowed || !item.try_get::<bool, _>("published").unwrap() {
+        // Las notas no publicadas se tratan como inexistentes para alumnos y apoderados
+        return HttpResponse::NotFound().body("Evaluación no encontrada");
+    }
+    if body.requested_value.as_deref() == Some(original_value.as_str()) {
+        return HttpResponse::BadRequest().body("El valor solicitado es igual al actual");
+    }
+
+    let teacher_user_id = match sqlx::query_scalar::<_, i32>(
+        "SELECT teacher_user_id FROM section_teachers WHERE section_id = $1
+         ORDER BY is_tutor DESC, assigned_at LIMIT 1",
+    )
+    .bind(section_id)
+    .fetch_optional(&data.pool)
+    .await
+    {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error fetching section teacher: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+
+    let mut tx = match data.pool.begin().await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+
+    let appeal_id = match sqlx::query_scalar::<_, i32>(
+        r#"
+        INSERT INTO grade_appeals
+        (evaluation_item_id, student_id, submitted_by_user_id, assigned_teacher_user_id,
+         reason, requested_value, original_value)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id
+        "#,
+    )
+    .bind(body.evaluation_item_id)
+    .bind(student_id)
+    .bind(user.id)
+    .bind(teacher_user_id)
+    .bind(reason)
+    .bind(&body.requested_value)
+    .bind(&original_value)
+    .fetch_one(&mut *tx)
+    .await
+    {
+        Ok(id) => id,
+        Err(sqlx::Error::Database(db_err)) if db_err.code().as_deref() == Some("23505") => {
+            let _ = tx.rollback().await;
+            return HttpResponse::Conflict()
+                .body("Ya existe un reclamo pendiente para esta evaluación");
+        }
+        Err(e) => {
+            let _ = tx.rollback().await;
+            eprintln!("Error creating appeal: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error al registrar el reclamo");
+        }
+    };
+
+    if let Some(teacher_id) = teacher_user_id {
+        if let Err(e) = notify(
+            &mut *tx,
+            teacher_id,
+            "appeal_submitted",
+            "Nuevo reclamo de nota",
+            Some(reason),
+            serde_json::json!({"appeal_id": appeal_id, "student_id": student_id}),
+        )
+        .await
+        {
+            let _ = tx.rollback().await;
+            eprintln!("Error notifying teacher: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error al registrar el reclamo");
+        }
+    }
+
+    if let Err(e) = tx.commit().await {
+        eprintln!("Error committing appeal: {:?}", e);
+        return HttpResponse::InternalServerError().body("Error al registrar el reclamo");
+    }
+
+    match fetch_visible_appeals(&data.pool, &user, None, Some(appeal_id)).await {
+        Ok(mut rows) if !rows.is_empty() => HttpResponse::Created().json(rows.remove(0)),
+        Ok(_) => HttpResponse::Created().json(serde_json::json!({"id": appeal_id})),
+        Err(e) => {
+            eprintln!("Error fetching appeal: {:?}", e);
+            HttpResponse::Created().json(serde_json::json!({"id": appeal_id}))
+        }
+    }
+}
+
+#[get("/api/appeals")]
+pub async fn list_appeals(
+    req: HttpRequest,
+    query: web::Query<AppealFilter>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user = match current_user(&data.pool, &req).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    match fetch_visible_appeals(&data.pool, &user, query.status.as_deref(), None).await {
+        Ok(rows) => HttpResponse::Ok().json(rows),
+        Err(e) => {
+            eprintln!("Error listing appeals: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al obtener reclamos")
+        }
+    }
+}
+
+#[get("/api/appeals/{id}")]
+pub async fn get_appeal(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let user = match current_user(&data.pool, &req).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    match fetch_visible_appeals(&data.pool, &user, None, Some(id)).await {
+        Ok(mut rows) if !rows.is_empty() => {
+            let appeal = rows.remove(0);
+            let history = match sqlx::query(
+                r#"
+                SELECT old_value, new_value, changed_by_user_id, appeal_id, reason, changed_at
+                FROM evaluation_item_history
+                WHERE evaluation_item_id = $1
+                ORDER BY changed_at
+                "#,
+            )
+            .bind(appeal.evaluation_item_id)
+            .fetch_all(&data.pool)
+            .await
+            {
+                Ok(h) => h
+                    .iter()
+                    .map(|r| {
+                        serde_json::json!({
+                            "old_value": r.try_get::<Option<String>, _>("old_value").unwrap(),
+                            "new_value": r.try_get::<String, _>("new_value").unwrap(),
+                            "changed_by_user_id": r.try_get::<Option<i32>, _>("changed_by_user_id").unwrap(),
+                            "appeal_id": r.try_get::<Option<i32>, _>("appeal_id").unwrap(),
+                            "reason": r.try_get::<Option<String>, _>("reason").unwrap(),
+                            "changed_at": r.try_get::<chrono::NaiveDateTime, _>("changed_at").unwrap(),
+                        })
+                    })
+                    .collect::<Vec<_>>(),
+                Err(e) => {
+                    eprintln!("Error fetching history: {:?}", e);
+                    return HttpResponse::InternalServerError().body("Error al obtener historial");
+                }
+            };
+            HttpResponse::Ok().json(serde_json::json!({
+                "appeal": appeal,
+                "history": history,
+            }))
+        }
+        Ok(_) => HttpResponse::NotFound().body("Reclamo no encontrado"),
+        Err(e) => {
+            eprintln!("Error fetching appeal: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al obtener reclamo")
+        }
+    }
+}
+
+#[post("/api/appeals/{id}/resolve")]
+pub async fn resolve_appeal(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    body: web::Json<ResolveAppealIn>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let user = match require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let accepted = match body.decision.as_str() {
+        "accepted" => true,
+        "rejected" => false,
+        _ => {
+            return HttpResponse::BadRequest().body("La decisión debe ser 'accepted' o 'rejected'")
+        }
+    };
+    let response = body
+        .response
+        .as_deref()
+        .map(str::trim)
+        .filter(|r| !r.is_empty());
+    if !accepted && response.is_none() {
+        return HttpResponse::BadRequest().body("Debe indicar una respuesta al rechazar");
+    }
+
+    let mut tx = match data.pool.begin().await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+
+    let appeal = match sqlx::query(
+        r#"
+        SELECT a.id, a.evaluation_item_id, a.student_id, a.status, a.requested_value,
+               a.submitted_by_user_id, a.assigned_teacher_user_id,
+               s.user_id AS student_user_id,
+               EXISTS (
+                   SELECT 1 FROM section_teachers st
+                   WHERE st.section_id = s.section_id AND st.teacher_user_id = $2
+               ) AS teaches_section
+        FROM grade_appeals a
+        JOIN students s ON s.id = a.student_id
+        WHERE a.id = $1
+        FOR UPDATE OF a
+        "#,
+    )
+    .bind(id)
+    .bind(user.id)
+    .fetch_optional(&mut *tx)
+    .await
+    {
+        Ok(Some(row)) => row,
+        Ok(None) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::NotFound().body("Reclamo no encontrado");
+        }
+        Err(e) => {
+            let _ = tx.rollback().await;
+            eprintln!("Error fetching appeal: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+
+    let assigned: Option<i32> = appeal.try_get("assigned_teacher_user_id").unwrap();
+    let may_resolve = user.role == UserRole::Admin
+        || assigned == Some(user.id)
+        || appeal.try_get::<bool, _>("teaches_section").unwrap();
+    if !may_resolve {
+        let _ = tx.rollback().await;
+        return HttpResponse::Forbidden().body("El reclamo no corresponde a sus secciones");
+    }
+    if appeal.try_get::<String, _>("status").unwrap() != "pending" {
+        let _ = tx.rollback().await;
+        return HttpResponse::Conflict().body("El reclamo ya fue resuelto");
+    }
+
+    let evaluation_item_id: i32 = appeal.try_get("evaluation_item_id").unwrap();
+    let submitted_by: i32 = appeal.try_get("submitted_by_user_id").unwrap();
+    let student_user_id: Option<i32> = appeal.try_get("student_user_id").unwrap();
+
+    let resolved_value = if accepted {
+        let requested: Option<String> = appeal.try_get("requested_value").unwrap();
+        let Some(new_value) = body.new_value.clone().or(requested) else {
+            let _ = tx.rollback().await;
+            return HttpResponse::BadRequest().body("Debe indicar el nuevo valor");
+        };
+        if !EVAL_LEVELS.contains(&new_value.as_str()) {
+            let _ = tx.rollback().await;
+            return HttpResponse::BadRequest().body("Valor inválido");
+        }
+
+        // Una competencia bloqueada no se modifica ni por reclamo: hay que desbloquearla antes
+        let locked = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM evaluation_items ei
+                JOIN evaluation_locks l
+                  ON l.session_id = ei.session_id AND l.competency_id = ei.competency_id
+                WHERE ei.id = $1
+            )
+            "#,
+        )
+        .bind(evaluation_item_id)
+        .fetch_one(&mut *tx)
+        .await;
+        match locked {
+            Ok(false) => {}
+            Ok(true) => {
+                let _ = tx.rollback().await;
+                return HttpResponse::Conflict()
+                    .body("La competencia está bloqueada; desbloquéela para aceptar el reclamo");
+            }
+            Err(e) => {
+                let _ = tx.rollback().await;
+                eprintln!("Error checking evaluation lock: {:?}", e);
+                return HttpResponse::InternalServerError().body("Error en la base de datos");
+            }
+        }
+
+        let changed = change_evaluation_value(
+            &mut tx,
+            evaluation_item_id,
+            &new_value,
+            user.id,
+            Some(id),
+            response,
+        )
+        .await;
+
+        if let Err(e) = changed {
+            let _ = tx.rollback().await;
+            eprintln!("Error updating evaluation value: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error al actualizar la nota");
+        }
+        Some(new_value)
+    } else {
+        None
+    };
+
+    let status = if accepted { "accepted" } else { "rejected" };
+    if let Err(e) = sqlx::query(
+        r#"
+        UPDATE grade_appeals
+        SET status = $2, resolved_value = $3, response = $4,
+            resolved_by_user_id = $5, resolved_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(status)
+    .bind(&resolved_value)
+    .bind(response)
+    .bind(user.id)
+    .execute(&mut *tx)
+    .await
+    {
+        let _ = tx.rollback().await;
+        eprintln!("Error resolving appeal: {:?}", e);
+        return HttpResponse::InternalServerError().body("Error al resolver el reclamo");
+    }
+
+    let title = if accepted {
+        "Tu reclamo de nota fue aceptado"
+    } else {
+        "Tu reclamo de nota fue rechazado"
+    };
+    let mut recipients = vec![submitted_by];
+    if let Some(student_user) = student_user_id {
+        if student_user != submitted_by {
+            recipients.push(student_user);
+        }
+    }
+    for recipient in recipients {
+        if let Err(e) = notify(
+            &mut *tx,
+            recipient,
+            "appeal_resolved",
+            title,
+            response,
+            serde_json::json!({
+                "appeal_id": id,
+                "status": status,
+                "resolved_value": resolved_value,
+            }),
+        )
+        .await
+        {
+            let _ = tx.rollback().await;
+            eprintln!("Error notifying appeal resolution: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error al resolver el reclamo");
+        }
+    }
+
+    if let Err(e) = tx.commit().await {
+        eprintln!("Error committing resolution: {:?}", e);
+        return HttpResponse::InternalServerError().body("Error al resolver el reclamo");
+    }
+
+    match fetch_visible_appeals(&data.pool, &user, None, Some(id)).await {
+        Ok(mut rows) if !rows.is_empty() => HttpResponse::Ok().json(rows.remove(0)),
+        _ => HttpResponse::Ok().json(serde_json::json!({"id": id, "status": status})),
+    }
+}
+
+#[post("/api/appeals/{id}/withdraw")]
+pub async fn withdraw_appeal(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let user = match current_user(&data.pool, &req).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let result = sqlx::query(
+        r#"
+        UPDATE grade_appeals SET status = 'withdrawn', resolved_at = NOW()
+        WHERE id = $1 AND submitted_by_user_id = $2 AND status = 'pending'
+        "#,
+    )
+    .bind(id)
+    .bind(user.id)
+    .execute(&data.pool)
+    .await;
+
+    match result {
+        Ok(r) if r.rows_affected() > 0 => HttpResponse::NoContent().finish(),
+        Ok(_) => HttpResponse::NotFound().body("No hay un reclamo pendiente propio con ese id"),
+        Err(e) => {
+            eprintln!("Error withdrawing appeal: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al retirar el reclamo")
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_appeal)
+        .service(list_appeals)
+        .service(get_appeal)
+        .service(resolve_appeal)
+        .service(withdraw_appeal);
+}
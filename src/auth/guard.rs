@@ -0,0 +1,72 @@
+use crate::auth::models::{ErrorResponse, User, UserRole};
+use actix_web::{HttpRequest, HttpResponse};
+use sqlx::PgPool;
+
+// Resuelve el usuario autenticado a partir del header X-Firebase-UID
+pub async fn current_user(pool: &PgPool, req: &HttpRequest) -> Result<User, HttpResponse> {
+    let firebase_uid = match req.headers().get("X-Firebase-UID") {
+        Some(header) => match header.to_str() {
+            Ok(uid) => uid.to_string(),
+            Err(_) => {
+                return Err(HttpResponse::BadRequest().json(ErrorResponse {
+                    error: "Header inválido".to_string(),
+                    details: None,
+                }));
+            }
+        },
+        None => {
+            return Err(HttpResponse::Unauthorized().json(ErrorResponse {
+                error: "No autenticado".to_string(),
+                details: Some("Falta header X-Firebase-UID".to_string()),
+            }));
+        }
+    };
+
+    match sqlx::query_as::<_, User>(
+        r#"
+        SELECT id, firebase_uid, email, role, status,
+               created_at, updated_at, last_login,
+               profile_photo_url, phone
+        FROM users
+        WHERE firebase_uid = $1
+        "#,
+    )
+    .bind(&firebase_uid)
+    .fetch_optional(pool)
+    .await
+    {
+        Ok(Some(user)) => Ok(user),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(ErrorResponse {
+            error: "Usuario no encontrado".to_string(),
+            details: None,
+        })),
+        Err(e) => {
+            eprintln!("Error buscando usuario: {:?}", e);
+            Err(HttpResponse::InternalServerError().json(ErrorResponse {
+                error: "Error buscando usuario".to_string(),
+                details: Some(e.to_string()),
+            }))
+        }
+    }
+}
+
+// Igual que current_user, pero exige que el usuario tenga alguno de los roles indicados
+pub async fn require_role(
+    pool: &PgPool,
+    req: &HttpRequest,
+    roles: &[UserRole],
+) -> Result<User, HttpResponse> {
+    let user = current_user(pool, req).await?;
+
+    if !roles.contains(&user.role) {
+        return Err(HttpResponse::Forbidden().json(ErrorResponse {
+            error: "Acceso denegado".to_string(),
+            details: Some(format!(
+                "El rol {} no tiene permiso para esta acción",
+                user.role
+            )),
+        }));
+    }
+
+    Ok(user)
+}
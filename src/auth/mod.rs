@@ -1,2 +1,4 @@
+pub mod guard;
 pub mod models;
+pub mod name_verification;
 pub mod routes;
\ No newline at end of file
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "user_role", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum UserRole {
     Docente,
@@ -112,6 +112,7 @@ pub struct RegisterApoderadoRequest {
     pub firebase_uid: String,
     pub occupation: Option<String>,
     pub workplace: Option<String>,
+    pub invitation_code: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
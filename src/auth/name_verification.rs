@@ -0,0 +1,114 @@
+use crate::auth::models::{ErrorResponse, UserRole};
+use crate::basic::students::names::compose_full_name;
+use crate::links::dni::{DniLookup, DniLookupError};
+use crate::links::matching::name_similarity;
+use crate::links::models::ReniecData;
+use actix_web::HttpResponse;
+use std::env;
+
+// Similitud mínima para aceptar que el nombre ingresado es el de RENIEC
+const NAME_MATCH_THRESHOLD: f64 = 0.95;
+
+// Qué hacer con el nombre ingresado en el registro frente al de RENIEC
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NamePolicy {
+    // No se consulta RENIEC
+    Off,
+    // Se guarda siempre el nombre oficial
+    Normalize,
+    // Si el nombre no coincide se rechaza el registro; si coincide se guarda el oficial
+    Reject,
+}
+
+impl NamePolicy {
+    // RENIEC_NAME_POLICY_ALUMNO / _APODERADO / _DOCENTE = off | normalize | reject
+    pub fn for_role(role: &UserRole) -> Self {
+        let default = match role {
+            UserRole::Alumno => NamePolicy::Normalize,
+            _ => NamePolicy::Off,
+        };
+        match env::var(format!("RENIEC_NAME_POLICY_{}", role))
+            .map(|v| v.trim().to_lowercase())
+            .as_deref()
+        {
+            Ok("off") => NamePolicy::Off,
+            Ok("normalize") => NamePolicy::Normalize,
+            Ok("reject") => NamePolicy::Reject,
+            _ => default,
+        }
+    }
+}
+
+// Nombre oficial con el formato de las nóminas; si faltan las partes, el texto de RENIEC
+fn official_name(data: &ReniecData) -> Option<String> {
+    match (&data.apellido_paterno, &data.nombres) {
+        (Some(paternal), Some(given)) => Some(compose_full_name(
+            paternal,
+            data.apellido_materno.as_deref().unwrap_or(""),
+            given,
+        )),
+        _ => data.nombre_completo.clone(),
+    }
+    .filter(|n| !n.trim().is_empty())
+}
+
+// Devuelve el nombre que debe guardarse para la cuenta, o la respuesta de rechazo.
+// Si RENIEC no responde no se bloquea el registro: se conserva el nombre ingresado.
+pub async fn verify_registration_name(
+    lookup: &dyn DniLookup,
+    role: &UserRole,
+    dni: &str,
+    full_name: &str,
+) -> Result<String, HttpResponse> {
+    let policy = NamePolicy::for_role(role);
+    if policy == NamePolicy::Off {
+        return Ok(full_name.to_string());
+    }
+
+    let data = match lookup.lookup(dni).await {
+        Ok(data) => data,
+        Err(DniLookupError::NotFound) => {
+            return Err(HttpResponse::BadRequest().json(ErrorResponse {
+                error: "DNI no encontrado".to_string(),
+                details: Some("El DNI no figura en RENIEC".to_string()),
+            }));
+        }
+        Err(e) => {
+            tracing::warn!("⚠️ No se pudo verificar el DNI {} en RENIEC: {}", dni, e);
+            return Ok(full_name.to_string());
+        }
+    };
+    let Some(official) = official_name(&data) else {
+        return Ok(full_name.to_string());
+    };
+
+    let score = name_similarity(full_name, &official);
+    if score >= NAME_MATCH_THRESHOLD {
+        return Ok(official);
+    }
+    match policy {
+        NamePolicy::Reject => {
+            tracing::warn!(
+                "⚠️ Nombre '{}' no coincide con RENIEC para DNI {} (similitud {:.2})",
+                full_name,
+                dni,
+                score
+            );
+            Err(HttpResponse::UnprocessableEntity().json(ErrorResponse {
+                error: "El nombre no coincide con RENIEC".to_string(),
+                details: Some(
+                    "El nombre ingresado no coincide con el registrado en RENIEC para este DNI"
+                        .to_string(),
+                ),
+            }))
+        }
+        _ => {
+            tracing::info!(
+                "✏️ Nombre '{}' reemplazado por el oficial de RENIEC para DNI {}",
+                full_name,
+                dni
+            );
+            Ok(official)
+        }
+    }
+}
@@ -1,8 +1,13 @@
 use crate::auth::models::*;
+use crate::auth::name_verification::verify_registration_name;
+use crate::links::invitations::models::RedeemOutcome;
+use crate::links::invitations::routes::{
+    normalize_code, record_redemption_attempt, redeem_invitation,
+};
+use crate::links::linking::{link_registered_student, RegistrationLink};
 use crate::AppState;
 use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
-use sqlx::PgPool;
-use sqlx::Row;
+use sqlx::Acquire;
 use tracing;
 
 #[post("/api/auth/register/alumno")]
@@ -43,6 +48,20 @@ pub async fn register_alumno(
         });
     }
 
+    // El DNI debe existir en RENIEC y, según la política del rol, el nombre guardado
+    // es el oficial o el registro se rechaza si no coincide
+    let full_name = match verify_registration_name(
+        data.dni_lookup.as_ref(),
+        &UserRole::Alumno,
+        &body.dni,
+        &body.full_name,
+    )
+    .await
+    {
+        Ok(name) => name,
+        Err(resp) => return resp,
+    };
+
     let mut tx = match data.pool.begin().await {
         Ok(t) => t,
         Err(e) => {
@@ -96,7 +115,7 @@ pub async fn register_alumno(
     )
     .bind(user.id)
     .bind(&body.dni)
-    .bind(&body.full_name)
+    .bind(&full_name)
     .fetch_one(&mut *tx)
     .await
     {
@@ -119,6 +138,35 @@ pub async fn register_alumno(
         }
     };
 
+    // ============================================
+    // 3. VINCULACIÓN (DENTRO DE LA TRANSACCIÓN)
+    // ============================================
+
+    // Punto de guardado: si la vinculación falla se descarta completa, sin dejar
+    // registros a medio vincular, y el registro de la cuenta sigue adelante
+    let link_result = match (&mut tx).begin().await {
+        Ok(mut savepoint) => {
+            match link_registered_student(&mut savepoint, user.id, &full_name, &body.dni).await {
+                Ok(result) => match savepoint.commit().await {
+                    Ok(()) => result,
+                    Err(e) => {
+                        tracing::error!("❌ Error confirmando vinculación: {:?}", e);
+                        RegistrationLink::None
+                    }
+                },
+                Err(e) => {
+                    tracing::error!("❌ Error en vinculación: {:?}", e);
+                    let _ = savepoint.rollback().await;
+                    RegistrationLink::None
+                }
+            }
+        }
+        Err(e) => {
+            tracing::error!("❌ Error iniciando vinculación: {:?}", e);
+            RegistrationLink::None
+        }
+    };
+
     // Commit de la transacción
     if let Err(e) = tx.commit().await {
         tracing::error!("❌ Error confirmando transacción: {:?}", e);
@@ -128,96 +176,42 @@ pub async fn register_alumno(
         });
     }
 
-    tracing::info!("✅ Transacción confirmada exitosamente");
-
-    // ============================================
-    // 3. VERIFICAR VINCULACIÓN (POST-COMMIT)
-    // ============================================
-
-    // Esperar un momento para que el trigger se ejecute
-    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-
-    // Verificar si el trigger vinculó automáticamente
-    let linked_result = sqlx::query(
-        r#"
-        SELECT 
-            s.id AS student_id,
-            s.full_name AS student_name,
-            spl.linked_by_method,
-            spl.linked_at
-        FROM students s
-        LEFT JOIN student_profile_links spl ON spl.student_id = s.id AND spl.user_id = s.user_id
-        WHERE s.user_id = $1
-        LIMIT 1
-        "#,
-    )
-    .bind(user.id)
-    .fetch_optional(&data.pool)
-    .await;
-
-    let linking_info = match linked_result {
-        Ok(Some(row)) => {
-            let student_id: i32 = row.try_get("student_id").unwrap();
-            let student_name: String = row.try_get("student_name").unwrap();
-            let method: Option<String> = row.try_get("linked_by_method").ok();
-
-            tracing::info!(
-                "✅ Trigger vinculó automáticamente: user_id={}, student_id={}, nombre='{}', método={:?}",
-                user.id, student_id, student_name, method
-            );
+    tracing::info!(
+        "✅ Transacción confirmada exitosamente: user_id={}, vinculación={}",
+        user.id,
+        link_result.as_str()
+    );
 
-            Some(LinkingInfo {
-                student_id,
-                student_name,
-                linked_by: method.unwrap_or_else(|| "auto".to_string()),
+    let linking_info = match &link_result {
+        RegistrationLink::LinkedByDni(rows) | RegistrationLink::LinkedByName(rows) => {
+            let linked_by = if matches!(link_result, RegistrationLink::LinkedByDni(_)) {
+                "dni_auto"
+            } else {
+                "full_name_auto"
+            };
+            rows.first().map(|row| LinkingInfo {
+                student_id: row.student_id,
+                student_name: row.full_name.clone(),
+                linked_by: linked_by.to_string(),
                 success: true,
             })
         }
-        Ok(None) => {
+        RegistrationLink::Ambiguous(candidates) => {
             tracing::warn!(
-                "⚠️ Trigger NO vinculó automáticamente, intentando vinculación manual..."
+                "⚠️ Varios estudiantes coinciden con '{}', queda para revisión: {:?}",
+                full_name,
+                candidates
+                    .iter()
+                    .map(|c| (c.student_id, c.score))
+                    .collect::<Vec<_>>()
             );
-
-            // Intentar vinculación manual como fallback
-            match try_link_student_by_name(&data.pool, user.id, &body.full_name, &body.dni).await {
-                Ok(Some(student_id)) => {
-                    tracing::info!(
-                        "✅ Vinculación manual exitosa: user_id={}, student_id={}",
-                        user.id,
-                        student_id
-                    );
-
-                    // Obtener nombre del estudiante vinculado
-                    let student_name = sqlx::query_scalar::<_, String>(
-                        "SELECT full_name FROM students WHERE id = $1",
-                    )
-                    .bind(student_id)
-                    .fetch_one(&data.pool)
-                    .await
-                    .unwrap_or_else(|_| body.full_name.clone());
-
-                    Some(LinkingInfo {
-                        student_id,
-                        student_name,
-                        linked_by: "manual".to_string(),
-                        success: true,
-                    })
-                }
-                Ok(None) => {
-                    tracing::warn!(
-                        "⚠️ No se encontró estudiante para vincular: '{}'",
-                        body.full_name
-                    );
-                    None
-                }
-                Err(e) => {
-                    tracing::error!("❌ Error en vinculación manual: {:?}", e);
-                    None
-                }
-            }
+            None
         }
-        Err(e) => {
-            tracing::error!("❌ Error verificando vinculación: {:?}", e);
+        RegistrationLink::None => {
+            tracing::warn!(
+                "⚠️ No se encontró estudiante para vincular: '{}'",
+                full_name
+            );
             None
         }
     };
@@ -230,6 +224,7 @@ pub async fn register_alumno(
         "dni": student_profile.dni,
         "full_name": student_profile.full_name,
         "enrollment_date": student_profile.enrollment_date,
+        "link_result": link_result.as_str(),
     });
 
     let (message, is_linked) = if let Some(info) = linking_info {
@@ -252,9 +247,14 @@ pub async fn register_alumno(
         )
     } else {
         profile_data["auto_linked"] = serde_json::json!(false);
-        profile_data["linking_note"] = serde_json::json!(
-            "No se encontró un registro previo para vincular. El alumno podrá ser vinculado manualmente más tarde."
-        );
+        profile_data["linking_note"] = serde_json::json!(match link_result {
+            RegistrationLink::Ambiguous(_) => {
+                "Hay varios registros posibles para este alumno. Un administrador revisará la vinculación."
+            }
+            _ => {
+                "No se encontró un registro previo para vincular. El alumno podrá ser vinculado manualmente más tarde."
+            }
+        });
 
         (
             "Alumno registrado exitosamente (sin vinculación automática)".to_string(),
@@ -311,6 +311,18 @@ pub async fn register_apoderado(
         });
     }
 
+    let full_name = match verify_registration_name(
+        data.dni_lookup.as_ref(),
+        &UserRole::Apoderado,
+        &body.dni,
+        &body.full_name,
+    )
+    .await
+    {
+        Ok(name) => name,
+        Err(resp) => return resp,
+    };
+
     let mut tx = match data.pool.begin().await {
         Ok(t) => t,
         Err(e) => {
@@ -361,7 +373,7 @@ pub async fn register_apoderado(
         "#,
     )
     .bind(user.id)
-    .bind(&body.full_name)
+    .bind(&full_name)
     .bind(Some(&body.dni))
     .bind(Some(&body.relationship_type))
     .bind(body.occupation.as_ref())
@@ -381,6 +393,44 @@ pub async fn register_apoderado(
         }
     };
 
+    // 3. Canjear código de invitación (opcional)
+    let invitation_code = body
+        .invitation_code
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty());
+
+    let redeemed = match invitation_code {
+        Some(code) => {
+            match redeem_invitation(&mut tx, code, user.id, Some(&body.relationship_type)).await {
+                Ok(outcome) if outcome.is_success() => Some(outcome),
+                Ok(outcome) => {
+                    let _ = tx.rollback().await;
+                    // El intento se registra fuera de la transacción revertida
+                    if let Err(e) =
+                        record_redemption_attempt(&data.pool, &normalize_code(code), None, &outcome)
+                            .await
+                    {
+                        eprintln!("Error registrando intento de canje: {:?}", e);
+                    }
+                    return HttpResponse::BadRequest().json(ErrorResponse {
+                        error: "Código de invitación inválido".to_string(),
+                        details: Some(outcome.message().to_string()),
+                    });
+                }
+                Err(e) => {
+                    let _ = tx.rollback().await;
+                    eprintln!("Error canjeando invitación: {:?}", e);
+                    return HttpResponse::InternalServerError().json(ErrorResponse {
+                        error: "Error canjeando invitación".to_string(),
+                        details: Some(e.to_string()),
+                    });
+                }
+            }
+        }
+        None => None,
+    };
+
     if let Err(e) = tx.commit().await {
         return HttpResponse::InternalServerError().json(ErrorResponse {
             error: "Error confirmando registro".to_string(),
@@ -388,20 +438,37 @@ pub async fn register_apoderado(
         });
     }
 
+    let mut profile_data = serde_json::json!({
+        "dni": guardian_profile.dni,
+        "full_name": guardian_profile.full_name,
+        "relationship_type": guardian_profile.relationship_type,
+        "phone": guardian_profile.emergency_phone,
+    });
+
+    let message = match redeemed {
+        Some(
+            RedeemOutcome::Linked {
+                student_user_id, ..
+            }
+            | RedeemOutcome::AlreadyLinked {
+                student_user_id, ..
+            },
+        ) => {
+            profile_data["linked_student_user_id"] = serde_json::json!(student_user_id);
+            "Apoderado registrado y vinculado con el alumno".to_string()
+        }
+        _ => "Apoderado registrado exitosamente".to_string(),
+    };
+
     HttpResponse::Created().json(ApiResponse {
         success: true,
-        message: "Apoderado registrado exitosamente".to_string(),
+        message,
         data: Some(UserResponse {
             id: user.id,
             email: user.email.clone(),
             role: user.role.to_string(),
             status: user.status.to_string(),
-            profile_data: serde_json::json!({
-                "dni": guardian_profile.dni,
-                "full_name": guardian_profile.full_name,
-                "relationship_type": guardian_profile.relationship_type,
-                "phone": guardian_profile.emergency_phone,
-            }),
+            profile_data,
         }),
     })
 }
@@ -435,6 +502,18 @@ pub async fn register_docente(
         });
     }
 
+    let full_name = match verify_registration_name(
+        data.dni_lookup.as_ref(),
+        &UserRole::Docente,
+        &body.dni,
+        &body.full_name,
+    )
+    .await
+    {
+        Ok(name) => name,
+        Err(resp) => return resp,
+    };
+
     // Verificar área
     let area_id = if let Some(id) = body.area_id {
         println!("✅ Usando area_id proporcionado: {}", id);
@@ -555,7 +634,7 @@ pub async fn register_docente(
     )
     .bind(user.id)
     .bind(Some(area_id))
-    .bind(&body.full_name)
+    .bind(&full_name)
     .bind(body.employee_code.as_ref())
     .bind(body.specialization.as_ref())
     .fetch_one(&mut *tx)
@@ -713,84 +792,6 @@ pub async fn get_current_user(data: web::Data<AppState>, req: HttpRequest) -> im
     }
 }
 
-async fn try_link_student_by_name(
-    pool: &PgPool,
-    user_id: i32,
-    full_name: &str,
-    dni: &str,
-) -> Result<Option<i32>, sqlx::Error> {
-    tracing::info!("🔍 Buscando estudiante para vincular: '{}'", full_name);
-
-    // ✅ AHORA usa la función normalize_name de SQL directamente
-    let student = sqlx::query(
-        r#"
-        SELECT id, full_name, section_id
-        FROM students
-        WHERE user_id IS NULL
-          AND public.normalize_name(full_name) = public.normalize_name($1)
-        LIMIT 1
-        "#,
-    )
-    .bind(full_name) // ✅ Ya no necesitas normalizar en Rust
-    .fetch_optional(pool)
-    .await?;
-
-    if let Some(student) = student {
-        let student_id: i32 = student.try_get("id")?;
-        let student_full_name: String = student.try_get("full_name")?;
-
-        tracing::info!(
-            "✅ Encontrado estudiante ID {} con nombre: '{}'",
-            student_id,
-            student_full_name
-        );
-
-        // Actualizar student con user_id y dni
-        sqlx::query(
-            r#"
-            UPDATE students
-            SET user_id = $1, dni = $2
-            WHERE id = $3
-            "#,
-        )
-        .bind(user_id)
-        .bind(dni)
-        .bind(student_id)
-        .execute(pool)
-        .await?;
-
-        // Registrar en auditoría
-        sqlx::query(
-            r#"
-            INSERT INTO student_profile_links (student_id, user_id, linked_by_method)
-            VALUES ($1, $2, 'full_name_manual')
-            ON CONFLICT (student_id, user_id) DO UPDATE
-            SET linked_by_method = 'full_name_manual',
-                linked_at = NOW()
-            "#,
-        )
-        .bind(student_id)
-        .bind(user_id)
-        .execute(pool)
-        .await?;
-
-        tracing::info!(
-            "✅ Estudiante {} vinculado exitosamente: user_id={}, dni={}",
-            student_full_name,
-            user_id,
-            dni
-        );
-
-        Ok(Some(student_id))
-    } else {
-        tracing::warn!(
-            "⚠️ No se encontró estudiante sin vincular con nombre: '{}'",
-            full_name
-        );
-        Ok(None)
-    }
-}
-
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(register_alumno)
         .service(register_apoderado)
@@ -0,0 +1,239 @@
+use crate::basic::models::*;
+use crate::imports::table::normalize_text;
+use sqlx::{PgConnection, Row};
+use std::collections::{HashMap, HashSet};
+
+pub enum CloneError {
+    NotFound(&'static str),
+    Conflict(String),
+    Db(sqlx::Error),
+}
+
+impl From<sqlx::Error> for CloneError {
+    fn from(e: sqlx::Error) -> Self {
+        CloneError::Db(e)
+    }
+}
+
+// Matricula al alumno en otra sección como un registro nuevo, conservando DNI,
+// datos personales y la cuenta vinculada
+pub async fn copy_student_row(
+    conn: &mut PgConnection,
+    student_id: i32,
+    target_section_id: i32,
+) -> Result<i32, sqlx::Error> {
+    let new_id = sqlx::query_scalar::<_, i32>(
+        r#"
+        INSERT INTO students
+        (section_id, full_name, user_id, dni, birth_date, gender,
+         paternal_surname, maternal_surname, given_names, name_review_reason)
+        SELECT $1, full_name, user_id, dni, birth_date, gender,
+               paternal_surname, maternal_surname, given_names, name_review_reason
+        FROM students WHERE id = $2
+        RETURNING id
+        "#,
+    )
+    .bind(target_section_id)
+    .bind(student_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO student_profile_links (student_id, user_id, linked_by_method)
+        SELECT $1, user_id, linked_by_method FROM student_profile_links WHERE student_id = $2
+        ON CONFLICT (student_id, user_id) DO NOTHING
+        "#,
+    )
+    .bind(new_id)
+    .bind(student_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(new_id)
+}
+
+// Copia los alumnos de una sección en otra. Se omiten los que ya estaban en el
+// destino antes de copiar: mismo DNI, o mismo nombre salvo que ambos tengan DNI
+// y sea distinto.
+async fn clone_students(
+    conn: &mut PgConnection,
+    source_section_id: i32,
+    target_section_id: i32,
+    mappings: &mut Vec<IdMapping>,
+) -> Result<usize, sqlx::Error> {
+    let existing = sqlx::query("SELECT full_name, dni FROM students WHERE section_id = $1")
+        .bind(target_section_id)
+        .fetch_all(&mut *conn)
+        .await?;
+    let dnis: HashSet<String> = existing
+        .iter()
+        .filter_map(|r| r.get::<Option<String>, _>("dni"))
+        .collect();
+    let mut names: HashMap<String, Vec<Option<String>>> = HashMap::new();
+    for r in &existing {
+        names
+            .entry(normalize_text(r.get::<&str, _>("full_name")))
+            .or_default()
+            .push(r.get("dni"));
+    }
+
+    let students =
+        sqlx::query("SELECT id, full_name, dni FROM students WHERE section_id = $1 ORDER BY id")
+            .bind(source_section_id)
+            .fetch_all(&mut *conn)
+            .await?;
+    let mut skipped = 0;
+    for student in &students {
+        let student_id: i32 = student.get("id");
+        let dni: Option<String> = student.get("dni");
+        let name = normalize_text(student.get::<&str, _>("full_name"));
+        let same_dni = dni.as_ref().is_some_and(|d| dnis.contains(d));
+        let same_name = names
+            .get(&name)
+            .is_some_and(|found| found.iter().any(|d| d.is_none() || dni.is_none()));
+        if same_dni || same_name {
+            skipped += 1;
+            continue;
+        }
+
+        let new_id = copy_student_row(conn, student_id, target_section_id).await?;
+        mappings.push(IdMapping {
+            source_id: student_id,
+            target_id: new_id,
+            created: true,
+        });
+    }
+    Ok(skipped)
+}
+
+// Copia grados y secciones (y opcionalmente las nóminas) de un bimestre a otro.
+// Quien llama decide si confirmar la transacción o deshacerla en la vista previa.
+pub async fn clone_bimester(
+    conn: &mut PgConnection,
+    source_bimester_id: i32,
+    input: &CloneBimesterIn,
+    dry_run: bool,
+) -> Result<CloneBimesterResult, CloneError> {
+    let target_bimester_id = input.target_bimester_id;
+    if source_bimester_id == target_bimester_id {
+        return Err(CloneError::Conflict(
+            "El bimestre de origen y el de destino son el mismo".to_string(),
+        ));
+    }
+    let found: Vec<i32> = sqlx::query_scalar("SELECT id FROM bimesters WHERE id = ANY($1)")
+        .bind([source_bimester_id, target_bimester_id])
+        .fetch_all(&mut *conn)
+        .await?;
+    if !found.contains(&source_bimester_id) {
+        return Err(CloneError::NotFound("Bimestre de origen no encontrado"));
+    }
+    if !found.contains(&target_bimester_id) {
+        return Err(CloneError::NotFound("Bimestre de destino no encontrado"));
+    }
+
+    let source_grades = sqlx::query_as::<_, Grade>(
+        "SELECT id, bimester_id, number FROM grades WHERE bimester_id = $1 ORDER BY number",
+    )
+    .bind(source_bimester_id)
+    .fetch_all(&mut *conn)
+    .await?;
+    let target_grades: HashMap<i32, i32> = sqlx::query_as::<_, Grade>(
+        "SELECT id, bimester_id, number FROM grades WHERE bimester_id = $1",
+    )
+    .bind(target_bimester_id)
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|g| (g.number, g.id))
+    .collect();
+
+    if input.on_conflict == CloneConflictPolicy::Fail {
+        let mut taken: Vec<i32> = source_grades
+            .iter()
+            .filter(|g| target_grades.contains_key(&g.number))
+            .map(|g| g.number)
+            .collect();
+        if !taken.is_empty() {
+            taken.sort_unstable();
+            return Err(CloneError::Conflict(format!(
+                "El bimestre de destino ya tiene los grados {:?}",
+                taken
+            )));
+        }
+    }
+
+    let mut result = CloneBimesterResult {
+        dry_run,
+        source_bimester_id,
+        target_bimester_id,
+        grades: Vec::new(),
+        sections: Vec::new(),
+        students: Vec::new(),
+        skipped_students: 0,
+    };
+
+    for grade in &source_grades {
+        let (target_grade_id, created) = match target_grades.get(&grade.number) {
+            Some(id) => (*id, false),
+            None => {
+                let id = sqlx::query_scalar::<_, i32>(
+                    "INSERT INTO grades (bimester_id, number) VALUES ($1, $2) RETURNING id",
+                )
+                .bind(target_bimester_id)
+                .bind(grade.number)
+                .fetch_one(&mut *conn)
+                .await?;
+                (id, true)
+            }
+        };
+        result.grades.push(IdMapping {
+            source_id: grade.id,
+            target_id: target_grade_id,
+            created,
+        });
+
+        let source_sections = sqlx::query_as::<_, Section>(
+            "SELECT id, grade_id, letter FROM sections WHERE grade_id = $1 ORDER BY letter",
+        )
+        .bind(grade.id)
+        .fetch_all(&mut *conn)
+        .await?;
+        let target_sections: HashMap<String, i32> = sqlx::query_as::<_, Section>(
+            "SELECT id, grade_id, letter FROM sections WHERE grade_id = $1",
+        )
+        .bind(target_grade_id)
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|s| (s.letter, s.id))
+        .collect();
+
+        for section in &source_sections {
+            let (target_section_id, created) = match target_sections.get(&section.letter) {
+                Some(id) => (*id, false),
+                None => {
+                    let id = sqlx::query_scalar::<_, i32>(
+                        "INSERT INTO sections (grade_id, letter) VALUES ($1, $2) RETURNING id",
+                    )
+                    .bind(target_grade_id)
+                    .bind(&section.letter)
+                    .fetch_one(&mut *conn)
+                    .await?;
+                    (id, true)
+                }
+            };
+            result.sections.push(IdMapping {
+                source_id: section.id,
+                target_id: target_section_id,
+                created,
+            });
+
+            if input.include_students.unwrap_or(false) {
+                result.skipped_students +=
+                    clone_students(conn, section.id, target_section_id, &mut result.students)
+                        .await?;
+            }
+        }
+    }
+
+    Ok(result)
+}
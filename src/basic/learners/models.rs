@@ -0,0 +1,65 @@
+use crate::basic::students::models::LinkedStudent;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+pub const LEARNER_COLUMNS: &str = "id, dni, user_id, full_name, paternal_surname, \
+     maternal_surname, given_names, birth_date, gender, created_at, updated_at";
+
+pub const ENROLLMENT_STATUSES: [&str; 4] = ["active", "completed", "withdrawn", "transferred"];
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct Learner {
+    pub id: i32,
+    pub dni: Option<String>,
+    pub user_id: Option<i32>,
+    pub full_name: String,
+    pub paternal_surname: Option<String>,
+    pub maternal_surname: Option<String>,
+    pub given_names: Option<String>,
+    pub birth_date: Option<NaiveDate>,
+    pub gender: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+// Cómo se identifica a la persona: por su cuenta de usuario o por el learner
+#[derive(Clone, Copy)]
+pub enum LearnerRef {
+    User(i32),
+    Learner(i32),
+}
+
+impl LearnerRef {
+    pub fn user_id(&self) -> Option<i32> {
+        match self {
+            LearnerRef::User(id) => Some(*id),
+            LearnerRef::Learner(_) => None,
+        }
+    }
+
+    pub fn learner_id(&self) -> Option<i32> {
+        match self {
+            LearnerRef::User(_) => None,
+            LearnerRef::Learner(id) => Some(*id),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LearnerSearch {
+    pub dni: Option<String>,
+    pub q: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct LearnerWithEnrollments {
+    #[serde(flatten)]
+    pub learner: Learner,
+    pub enrollments: Vec<LinkedStudent>,
+}
+
+#[derive(Deserialize)]
+pub struct EnrollmentStatusIn {
+    pub status: String,
+    pub end_date: Option<NaiveDate>,
+}
@@ -0,0 +1,159 @@
+use crate::auth::guard::require_role;
+use crate::auth::models::UserRole;
+use crate::basic::learners::models::*;
+use crate::basic::students::models::StudentGradesFilter;
+use crate::basic::students::routes::{fetch_student_enrollments, fetch_student_grades};
+use crate::AppState;
+use actix_web::{get, put, web, HttpRequest, HttpResponse, Responder};
+
+// Busca personas por DNI exacto o por parte del nombre, sin importar la sección
+#[get("/learners")]
+pub async fn search_learners(
+    req: HttpRequest,
+    query: web::Query<LearnerSearch>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+    let dni = query
+        .dni
+        .as_deref()
+        .map(str::trim)
+        .filter(|d| !d.is_empty());
+    let q = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty());
+    if dni.is_none() && q.is_none() {
+        return HttpResponse::BadRequest().body("Indique 'dni' o 'q'");
+    }
+
+    match sqlx::query_as::<_, Learner>(&format!(
+        r#"
+        SELECT {} FROM learners
+        WHERE ($1::text IS NULL OR dni = $1)
+          AND ($2::text IS NULL OR full_name ILIKE '%' || $2 || '%')
+        ORDER BY full_name
+        LIMIT 50
+        "#,
+        LEARNER_COLUMNS
+    ))
+    .bind(dni)
+    .bind(q)
+    .fetch_all(&data.pool)
+    .await
+    {
+        Ok(rows) => HttpResponse::Ok().json(rows),
+        Err(e) => {
+            eprintln!("Error searching learners: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+#[get("/learners/{learner_id}")]
+pub async fn get_learner(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+    let learner_id = path.into_inner();
+
+    let learner = match sqlx::query_as::<_, Learner>(&format!(
+        "SELECT {} FROM learners WHERE id = $1",
+        LEARNER_COLUMNS
+    ))
+    .bind(learner_id)
+    .fetch_optional(&data.pool)
+    .await
+    {
+        Ok(Some(l)) => l,
+        Ok(None) => return HttpResponse::NotFound().body("Alumno no encontrado"),
+        Err(e) => {
+            eprintln!("Error fetching learner: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+    match fetch_student_enrollments(&data.pool, LearnerRef::Learner(learner_id)).await {
+        Ok(enrollments) => HttpResponse::Ok().json(LearnerWithEnrollments {
+            learner,
+            enrollments,
+        }),
+        Err(e) => {
+            eprintln!("Error fetching enrollments: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al obtener matrículas")
+        }
+    }
+}
+
+// Notas de todas las matrículas de la persona, incluidas las aún no publicadas
+#[get("/learners/{learner_id}/grades")]
+pub async fn get_learner_grades(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    query: web::Query<StudentGradesFilter>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+    let learner_id = path.into_inner();
+
+    match fetch_student_grades(&data.pool, LearnerRef::Learner(learner_id), &query, false).await {
+        Ok(sessions) => HttpResponse::Ok().json(sessions),
+        Err(e) => {
+            eprintln!("Error fetching grades: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al obtener notas")
+        }
+    }
+}
+
+// Retiros, traslados y cierres de matrícula. En un retiro o traslado sin fecha de
+// fin se toma la de hoy; en los demás casos se conserva la del bimestre.
+#[put("/enrollments/{enrollment_id}/status")]
+pub async fn update_enrollment_status(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    body: web::Json<EnrollmentStatusIn>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Admin]).await {
+        return resp;
+    }
+    if !ENROLLMENT_STATUSES.contains(&body.status.as_str()) {
+        return HttpResponse::BadRequest()
+            .body("Estado inválido: active, completed, withdrawn o transferred");
+    }
+
+    match sqlx::query(
+        r#"
+        UPDATE enrollments
+        SET status = $1,
+            end_date = CASE WHEN $1 IN ('withdrawn', 'transferred') THEN COALESCE($2, CURRENT_DATE)
+                            ELSE COALESCE($2, end_date) END
+        WHERE id = $3
+        RETURNING id
+        "#,
+    )
+    .bind(&body.status)
+    .bind(body.end_date)
+    .bind(path.into_inner())
+    .fetch_optional(&data.pool)
+    .await
+    {
+        Ok(Some(_)) => HttpResponse::Ok().json(serde_json::json!({ "status": body.status })),
+        Ok(None) => HttpResponse::NotFound().body("Matrícula no encontrada"),
+        Err(e) => {
+            eprintln!("Error updating enrollment: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(search_learners)
+        .service(get_learner)
+        .service(get_learner_grades)
+        .service(update_enrollment_status);
+}
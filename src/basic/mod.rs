@@ -1,4 +1,8 @@
+pub mod clone;
+pub mod learners;
 pub mod models;
+pub mod promotion;
 pub mod routes;
 pub mod students;
-pub mod session;
\ No newline at end of file
+pub mod session;
+pub mod years;
\ No newline at end of file
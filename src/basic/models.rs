@@ -1,9 +1,32 @@
 use serde::{Deserialize, Serialize};
 
+pub const BIMESTER_COLUMNS: &str = "id, name, year, academic_year_id, number, start_date, end_date";
+
 #[derive(Serialize, sqlx::FromRow)]
 pub struct Bimester {
     pub id: i32,
     pub name: String,
+    pub year: i32,
+    pub academic_year_id: Option<i32>,
+    pub number: Option<i32>,
+    pub start_date: Option<chrono::NaiveDate>,
+    pub end_date: Option<chrono::NaiveDate>,
+}
+
+#[derive(Deserialize)]
+pub struct BimesterIn {
+    pub name: Option<String>,
+    pub year: Option<i32>,
+    pub academic_year_id: Option<i32>,
+    pub start_date: Option<chrono::NaiveDate>,
+    pub end_date: Option<chrono::NaiveDate>,
+}
+
+// Filtro por año para los listados de la jerarquía bimestre/grado/sección
+#[derive(Deserialize)]
+pub struct YearFilter {
+    pub year: Option<i32>,
+    pub academic_year_id: Option<i32>,
 }
 
 #[derive(Serialize, sqlx::FromRow)]
@@ -20,6 +43,21 @@ pub struct Section {
     pub letter: String,
 }
 
+#[derive(Serialize, sqlx::FromRow)]
+pub struct SectionTeacher {
+    pub section_id: i32,
+    pub teacher_user_id: i32,
+    pub full_name: Option<String>,
+    pub is_tutor: bool,
+    pub assigned_at: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct AssignSectionTeacherIn {
+    pub teacher_user_id: i32,
+    pub is_tutor: Option<bool>,
+}
+
 #[derive(Serialize, sqlx::FromRow)]
 #[allow(dead_code)]
 pub struct StudentGradeItem {
@@ -136,3 +174,153 @@ pub struct ReniecResponse {
     pub data: Option<ReniecData>,
     pub message: Option<String>,
 }
+
+// Qué hacer si el grado o la sección ya existe en el bimestre destino
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CloneConflictPolicy {
+    // Se usa la existente y se completa con lo que falte
+    #[default]
+    Reuse,
+    // No se copia nada si ya existe alguno
+    Fail,
+}
+
+#[derive(Deserialize)]
+pub struct CloneBimesterQuery {
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct CloneBimesterIn {
+    pub target_bimester_id: i32,
+    pub include_students: Option<bool>,
+    #[serde(default)]
+    pub on_conflict: CloneConflictPolicy,
+}
+
+#[derive(Serialize)]
+pub struct IdMapping {
+    pub source_id: i32,
+    pub target_id: i32,
+    // false si ya existía en el destino y se reutilizó
+    pub created: bool,
+}
+
+#[derive(Serialize)]
+pub struct CloneBimesterResult {
+    pub dry_run: bool,
+    pub source_bimester_id: i32,
+    pub target_bimester_id: i32,
+    pub grades: Vec<IdMapping>,
+    pub sections: Vec<IdMapping>,
+    pub students: Vec<IdMapping>,
+    // Alumnos que ya estaban en la sección destino (mismo DNI o nombre)
+    pub skipped_students: usize,
+}
+
+// Qué pasa con el alumno al cerrar el año
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PromotionDecision {
+    // Pasa al grado siguiente
+    Promote,
+    // Se matricula otra vez en el mismo grado
+    Repeat,
+    // Se retira del colegio; no se matricula
+    Leave,
+    // Terminó el último grado; no se matricula
+    Graduate,
+}
+
+impl PromotionDecision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PromotionDecision::Promote => "promote",
+            PromotionDecision::Repeat => "repeat",
+            PromotionDecision::Leave => "leave",
+            PromotionDecision::Graduate => "graduate",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PromotionQuery {
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct PromotionOverride {
+    pub student_id: i32,
+    pub decision: Option<PromotionDecision>,
+    // Sección del bimestre destino en lugar de la propuesta
+    pub target_section_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct PromotionIn {
+    pub target_bimester_id: i32,
+    #[serde(default)]
+    pub overrides: Vec<PromotionOverride>,
+    // Crea en el destino los grados y secciones propuestos que falten (por defecto sí)
+    pub create_missing_sections: Option<bool>,
+}
+
+// Resultado de la matrícula de cada alumno en el bimestre de destino
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PromotionStatus {
+    Enrolled,
+    // Se retira o egresa: no le corresponde matrícula
+    NotEnrolled,
+    AlreadyEnrolled,
+    // Le corresponde matrícula pero falta su sección en el destino
+    NoSection,
+}
+
+impl PromotionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PromotionStatus::Enrolled => "enrolled",
+            PromotionStatus::NotEnrolled => "not_enrolled",
+            PromotionStatus::AlreadyEnrolled => "already_enrolled",
+            PromotionStatus::NoSection => "no_section",
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PromotionItem {
+    pub student_id: i32,
+    pub full_name: String,
+    pub dni: Option<String>,
+    pub user_id: Option<i32>,
+    pub grade_number: i32,
+    pub section_letter: String,
+    pub decision: PromotionDecision,
+    pub target_grade_number: Option<i32>,
+    pub target_section_letter: Option<String>,
+    pub target_section_id: Option<i32>,
+    pub new_student_id: Option<i32>,
+    pub status: PromotionStatus,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CreatedSection {
+    pub grade_number: i32,
+    pub letter: String,
+    pub section_id: i32,
+}
+
+#[derive(Serialize)]
+pub struct PromotionResult {
+    pub dry_run: bool,
+    pub source_bimester_id: i32,
+    pub target_bimester_id: i32,
+    // Alumnos por decisión y por resultado de la matrícula
+    pub counts: std::collections::BTreeMap<&'static str, usize>,
+    pub status_counts: std::collections::BTreeMap<&'static str, usize>,
+    pub sections_created: Vec<CreatedSection>,
+    pub students: Vec<PromotionItem>,
+}
@@ -0,0 +1,434 @@
+use crate::basic::clone::copy_student_row;
+use crate::basic::models::*;
+use sqlx::{PgConnection, Row};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+// Último grado de primaria: quienes lo terminan egresan
+pub const LAST_GRADE: i32 = 6;
+
+pub enum PromotionError {
+    NotFound(&'static str),
+    Invalid(String),
+    Conflict(String),
+    Db(sqlx::Error),
+}
+
+impl From<sqlx::Error> for PromotionError {
+    fn from(e: sqlx::Error) -> Self {
+        PromotionError::Db(e)
+    }
+}
+
+// Grados y secciones del bimestre destino; crea los que falten si se permite
+struct TargetSections {
+    bimester_id: i32,
+    create_missing: bool,
+    grades: HashMap<i32, i32>,
+    sections: HashMap<(i32, String), i32>,
+    created: Vec<CreatedSection>,
+}
+
+impl TargetSections {
+    async fn load(
+        conn: &mut PgConnection,
+        bimester_id: i32,
+        create_missing: bool,
+    ) -> Result<Self, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT g.id AS grade_id, g.number, sec.id AS section_id, sec.letter
+            FROM grades g
+            LEFT JOIN sections sec ON sec.grade_id = g.id
+            WHERE g.bimester_id = $1
+            "#,
+        )
+        .bind(bimester_id)
+        .fetch_all(&mut *conn)
+        .await?;
+        let mut target = TargetSections {
+            bimester_id,
+            create_missing,
+            grades: HashMap::new(),
+            sections: HashMap::new(),
+            created: Vec::new(),
+        };
+        for r in &rows {
+            let number: i32 = r.get("number");
+            target.grades.insert(number, r.get("grade_id"));
+            if let Some(section_id) = r.get::<Option<i32>, _>("section_id") {
+                let letter: String = r.get("letter");
+                target
+                    .sections
+                    .insert((number, letter.to_uppercase()), section_id);
+            }
+        }
+        Ok(target)
+    }
+
+    fn by_id(&self, section_id: i32) -> Option<(i32, String)> {
+        self.sections
+            .iter()
+            .find(|(_, id)| **id == section_id)
+            .map(|((number, letter), _)| (*number, letter.clone()))
+    }
+
+    async fn resolve(
+        &mut self,
+        conn: &mut PgConnection,
+        grade: i32,
+        letter: &str,
+    ) -> Result<Option<i32>, sqlx::Error> {
+        let key = (grade, letter.to_uppercase());
+        if let Some(id) = self.sections.get(&key) {
+            return Ok(Some(*id));
+        }
+        if !self.create_missing {
+            return Ok(None);
+        }
+        let grade_id = match self.grades.get(&grade) {
+            Some(id) => *id,
+            None => {
+                let id = sqlx::query_scalar::<_, i32>(
+                    "INSERT INTO grades (bimester_id, number) VALUES ($1, $2) RETURNING id",
+                )
+                .bind(self.bimester_id)
+                .bind(grade)
+                .fetch_one(&mut *conn)
+                .await?;
+                self.grades.insert(grade, id);
+                id
+            }
+        };
+        let section_id = sqlx::query_scalar::<_, i32>(
+            "INSERT INTO sections (grade_id, letter) VALUES ($1, $2) RETURNING id",
+        )
+        .bind(grade_id)
+        .bind(letter)
+        .fetch_one(&mut *conn)
+        .await?;
+        self.sections.insert(key, section_id);
+        self.created.push(CreatedSection {
+            grade_number: grade,
+            letter: letter.to_string(),
+            section_id,
+        });
+        Ok(Some(section_id))
+    }
+}
+
+// Propone para cada alumno del bimestre de origen el grado siguiente en la misma
+// letra de sección, aplica las decisiones del personal (repite, se retira) y crea
+// las matrículas en el bimestre destino conservando DNI y cuenta vinculada.
+// Quien llama decide si confirmar la transacción o deshacerla en la vista previa.
+pub async fn promote_students(
+    conn: &mut PgConnection,
+    source_bimester_id: i32,
+    input: &PromotionIn,
+    admin_id: i32,
+    dry_run: bool,
+) -> Result<PromotionResult, PromotionError> {
+    let target_bimester_id = input.target_bimester_id;
+    let bimesters = sqlx::query("SELECT id, year FROM bimesters WHERE id = ANY($1)")
+        .bind([source_bimester_id, target_bimester_id])
+        .fetch_all(&mut *conn)
+        .await?;
+    let years: HashMap<i32, i32> = bimesters
+        .iter()
+        .map(|r| (r.get("id"), r.get("year")))
+        .collect();
+    let Some(source_year) = years.get(&source_bimester_id) else {
+        return Err(PromotionError::NotFound("Bimestre de origen no encontrado"));
+    };
+    let Some(target_year) = years.get(&target_bimester_id) else {
+        return Err(PromotionError::NotFound(
+            "Bimestre de destino no encontrado",
+        ));
+    };
+    if target_year <= source_year {
+        return Err(PromotionError::Conflict(format!(
+            "El bimestre de destino ({}) debe ser de un año posterior al de origen ({})",
+            target_year, source_year
+        )));
+    }
+
+    let students = sqlx::query(
+        r#"
+        SELECT s.id, s.full_name, s.dni, s.user_id, g.number AS grade_number,
+               sec.letter AS section_letter
+        FROM students s
+        JOIN sections sec ON sec.id = s.section_id
+        JOIN grades g ON g.id = sec.grade_id
+        WHERE g.bimester_id = $1
+        ORDER BY g.number, sec.letter, s.full_name
+        "#,
+    )
+    .bind(source_bimester_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut overrides: HashMap<i32, &PromotionOverride> = HashMap::new();
+    for o in &input.overrides {
+        if overrides.insert(o.student_id, o).is_some() {
+            return Err(PromotionError::Invalid(format!(
+                "El alumno {} aparece más de una vez",
+                o.student_id
+            )));
+        }
+    }
+    let source_ids: HashSet<i32> = students.iter().map(|r| r.get("id")).collect();
+    if let Some(o) = input
+        .overrides
+        .iter()
+        .find(|o| !source_ids.contains(&o.student_id))
+    {
+        return Err(PromotionError::Invalid(format!(
+            "El alumno {} no pertenece al bimestre de origen",
+            o.student_id
+        )));
+    }
+
+    let processed: HashSet<i32> = sqlx::query_scalar(
+        "SELECT source_student_id FROM student_promotions WHERE target_bimester_id = $1",
+    )
+    .bind(target_bimester_id)
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .collect();
+    let enrolled = sqlx::query(
+        r#"
+        SELECT s.user_id, s.dni
+        FROM students s
+        JOIN sections sec ON sec.id = s.section_id
+        JOIN grades g ON g.id = sec.grade_id
+        WHERE g.bimester_id = $1
+        "#,
+    )
+    .bind(target_bimester_id)
+    .fetch_all(&mut *conn)
+    .await?;
+    let mut enrolled_users: HashSet<i32> = enrolled
+        .iter()
+        .filter_map(|r| r.get::<Option<i32>, _>("user_id"))
+        .collect();
+    let mut enrolled_dnis: HashSet<String> = enrolled
+        .iter()
+        .filter_map(|r| r.get::<Option<String>, _>("dni"))
+        .collect();
+
+    let mut targets = TargetSections::load(
+        conn,
+        target_bimester_id,
+        input.create_missing_sections.unwrap_or(true),
+    )
+    .await?;
+    let mut items = Vec::with_capacity(students.len());
+    for r in &students {
+        let student_id: i32 = r.get("id");
+        let grade: i32 = r.get("grade_number");
+        let letter: String = r.get("section_letter");
+        let o = overrides.get(&student_id);
+        let decision = match o.and_then(|o| o.decision) {
+            Some(d) => d,
+            None if grade >= LAST_GRADE => PromotionDecision::Graduate,
+            None => PromotionDecision::Promote,
+        };
+        match decision {
+            PromotionDecision::Promote if grade >= LAST_GRADE => {
+                return Err(PromotionError::Invalid(format!(
+                    "El alumno {} está en el último grado; no puede pasar a {}°",
+                    student_id,
+                    grade + 1
+                )));
+            }
+            PromotionDecision::Graduate if grade < LAST_GRADE => {
+                return Err(PromotionError::Invalid(format!(
+                    "El alumno {} está en {}°; solo egresan los de {}°",
+                    student_id, grade, LAST_GRADE
+                )));
+            }
+            PromotionDecision::Leave | PromotionDecision::Graduate
+                if o.is_some_and(|o| o.target_section_id.is_some()) =>
+            {
+                return Err(PromotionError::Invalid(format!(
+                    "El alumno {} no se matricula ({}); no indique sección de destino",
+                    student_id,
+                    decision.as_str()
+                )));
+            }
+            _ => {}
+        }
+
+        let mut item = PromotionItem {
+            student_id,
+            full_name: r.get("full_name"),
+            dni: r.get("dni"),
+            user_id: r.get("user_id"),
+            grade_number: grade,
+            section_letter: letter.clone(),
+            decision,
+            target_grade_number: None,
+            target_section_letter: None,
+            target_section_id: None,
+            new_student_id: None,
+            status: PromotionStatus::NotEnrolled,
+            message: None,
+        };
+        if processed.contains(&student_id) {
+            item.status = PromotionStatus::AlreadyEnrolled;
+            item.message = Some("Ya fue procesado en una promoción anterior".to_string());
+            items.push(item);
+            continue;
+        }
+
+        // La matrícula del año que termina se cierra: retirada o completada
+        sqlx::query(
+            "UPDATE enrollments SET status = $1 WHERE student_id = $2 AND status = 'active'",
+        )
+        .bind(if decision == PromotionDecision::Leave {
+            "withdrawn"
+        } else {
+            "completed"
+        })
+        .bind(student_id)
+        .execute(&mut *conn)
+        .await?;
+
+        let target_grade = match decision {
+            PromotionDecision::Promote => grade + 1,
+            PromotionDecision::Repeat => grade,
+            PromotionDecision::Leave | PromotionDecision::Graduate => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO student_promotions
+                    (source_bimester_id, target_bimester_id, source_student_id, decision,
+                     decided_by_user_id)
+                    VALUES ($1, $2, $3, $4, $5)
+                    "#,
+                )
+                .bind(source_bimester_id)
+                .bind(target_bimester_id)
+                .bind(student_id)
+                .bind(decision.as_str())
+                .bind(admin_id)
+                .execute(&mut *conn)
+                .await?;
+                items.push(item);
+                continue;
+            }
+        };
+
+        let section_id = match o.and_then(|o| o.target_section_id) {
+            Some(id) => {
+                let Some((number, l)) = targets.by_id(id) else {
+                    return Err(PromotionError::Invalid(format!(
+                        "La sección {} no pertenece al bimestre de destino",
+                        id
+                    )));
+                };
+                if number != target_grade {
+                    return Err(PromotionError::Invalid(format!(
+                        "El alumno {} va a {}° ({}), pero la sección {} es de {}°",
+                        student_id,
+                        target_grade,
+                        decision.as_str(),
+                        id,
+                        number
+                    )));
+                }
+                item.target_grade_number = Some(number);
+                item.target_section_letter = Some(l);
+                Some(id)
+            }
+            None => {
+                item.target_grade_number = Some(target_grade);
+                item.target_section_letter = Some(letter.clone());
+                targets.resolve(conn, target_grade, &letter).await?
+            }
+        };
+        item.target_section_id = section_id;
+
+        if item.user_id.is_some_and(|u| enrolled_users.contains(&u))
+            || item.dni.as_ref().is_some_and(|d| enrolled_dnis.contains(d))
+        {
+            item.status = PromotionStatus::AlreadyEnrolled;
+            item.message = Some("Ya tiene matrícula en el bimestre de destino".to_string());
+            items.push(item);
+            continue;
+        }
+        let Some(section_id) = section_id else {
+            item.status = PromotionStatus::NoSection;
+            item.message = Some(format!(
+                "No existe la sección {}° {} en el destino",
+                target_grade, letter
+            ));
+            items.push(item);
+            continue;
+        };
+
+        let new_id = copy_student_row(conn, student_id, section_id).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO student_promotions
+            (source_bimester_id, target_bimester_id, source_student_id, target_student_id,
+             decision, decided_by_user_id)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(source_bimester_id)
+        .bind(target_bimester_id)
+        .bind(student_id)
+        .bind(new_id)
+        .bind(decision.as_str())
+        .bind(admin_id)
+        .execute(&mut *conn)
+        .await?;
+        if let Some(u) = item.user_id {
+            enrolled_users.insert(u);
+        }
+        if let Some(d) = &item.dni {
+            enrolled_dnis.insert(d.clone());
+        }
+        item.new_student_id = Some(new_id);
+        item.status = PromotionStatus::Enrolled;
+        items.push(item);
+    }
+
+    if !dry_run {
+        let missing: Vec<String> = items
+            .iter()
+            .filter(|i| i.status == PromotionStatus::NoSection)
+            .map(|i| {
+                format!(
+                    "{}° {}",
+                    i.target_grade_number.unwrap_or_default(),
+                    i.section_letter
+                )
+            })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        if !missing.is_empty() {
+            return Err(PromotionError::Conflict(format!(
+                "Faltan secciones en el destino: {}",
+                missing.join(", ")
+            )));
+        }
+    }
+
+    let mut counts = BTreeMap::new();
+    let mut status_counts = BTreeMap::new();
+    for item in &items {
+        *counts.entry(item.decision.as_str()).or_insert(0) += 1;
+        *status_counts.entry(item.status.as_str()).or_insert(0) += 1;
+    }
+    Ok(PromotionResult {
+        dry_run,
+        source_bimester_id,
+        target_bimester_id,
+        counts,
+        status_counts,
+        sections_created: targets.created,
+        students: items,
+    })
+}
@@ -1,48 +1,170 @@
+use crate::auth::guard::require_role;
+use crate::auth::models::UserRole;
+use crate::basic::clone::{clone_bimester, CloneError};
 use crate::basic::models::*;
+use crate::basic::promotion::{promote_students, PromotionError};
+use crate::basic::years::routes::BIMESTER_NAMES;
 use crate::AppState;
-use actix_web::{delete, get, post, web, HttpResponse, Responder};
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder};
+use chrono::Datelike;
 use serde_json::json;
 use sqlx::Row;
 
+// El año sale del año académico indicado, del año explícito o del año activo; el
+// nombre por defecto es el siguiente numeral romano libre de ese año
 #[post("/bimesters")]
 pub async fn create_bimester(
     data: web::Data<AppState>,
-    body: web::Json<serde_json::Value>,
+    body: web::Json<BimesterIn>,
 ) -> impl Responder {
-    let name = body.get("name").and_then(|v| v.as_str()).unwrap_or("I");
-    let rec = sqlx::query_as::<_, Bimester>(
-        "INSERT INTO bimesters (name) VALUES ($1) RETURNING id, name",
+    let year_row = if let Some(id) = body.academic_year_id {
+        sqlx::query("SELECT id, year FROM academic_years WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&data.pool)
+            .await
+    } else if let Some(year) = body.year {
+        sqlx::query("SELECT id, year FROM academic_years WHERE year = $1")
+            .bind(year)
+            .fetch_optional(&data.pool)
+            .await
+    } else {
+        sqlx::query("SELECT id, year FROM academic_years WHERE status = 'active'")
+            .fetch_optional(&data.pool)
+            .await
+    };
+    let year_row = match year_row {
+        Ok(None) if body.academic_year_id.is_some() => {
+            return HttpResponse::NotFound().body("Año académico no encontrado");
+        }
+        // Todo bimestre pertenece a un año académico, para que la unicidad de su número
+        // se cumpla: si el año no existe se crea en planificación con las fechas por
+        // defecto (1 de marzo al 31 de diciembre)
+        Ok(None) => {
+            sqlx::query(
+                r#"
+                INSERT INTO academic_years (year, start_date, end_date)
+                VALUES ($1, make_date($1, 3, 1), make_date($1, 12, 31))
+                ON CONFLICT (year) DO UPDATE SET year = EXCLUDED.year
+                RETURNING id, year
+                "#,
+            )
+            .bind(body.year.unwrap_or_else(|| chrono::Local::now().year()))
+            .fetch_one(&data.pool)
+            .await
+        }
+        Ok(Some(r)) => Ok(r),
+        Err(e) => Err(e),
+    };
+    let (academic_year_id, year) = match year_row {
+        Ok(r) => (r.get::<i32, _>("id"), r.get::<i32, _>("year")),
+        Err(e) => {
+            eprintln!("Error fetching academic year: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+
+    // Se toma el primer número libre: si se borró un bimestre, su hueco se reutiliza
+    let used = match sqlx::query_scalar::<_, Option<i32>>(
+        "SELECT number FROM bimesters WHERE academic_year_id = $1",
     )
+    .bind(academic_year_id)
+    .fetch_all(&data.pool)
+    .await
+    {
+        Ok(n) => n,
+        Err(e) => {
+            eprintln!("Error fetching bimester numbers: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+    let free = (1..=BIMESTER_NAMES.len() as i32).find(|n| !used.contains(&Some(*n)));
+    let Some(number) = free.filter(|_| used.len() < BIMESTER_NAMES.len()) else {
+        return HttpResponse::Conflict().json(json!({
+            "error": format!("El año {} ya tiene cuatro bimestres", year)
+        }));
+    };
+    let name = body
+        .name
+        .clone()
+        .unwrap_or_else(|| BIMESTER_NAMES[number as usize - 1].to_string());
+
+    let rec = sqlx::query_as::<_, Bimester>(&format!(
+        r#"
+        INSERT INTO bimesters (name, year, academic_year_id, number, start_date, end_date)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING {}
+        "#,
+        BIMESTER_COLUMNS
+    ))
     .bind(name)
+    .bind(year)
+    .bind(academic_year_id)
+    .bind(number)
+    .bind(body.start_date)
+    .bind(body.end_date)
     .fetch_one(&data.pool)
+    .await;
+    match rec {
+        Ok(b) => HttpResponse::Ok().json(b),
+        // Otra petición tomó el mismo número a la vez
+        Err(sqlx::Error::Database(db_err)) if db_err.code().as_deref() == Some("23505") => {
+            HttpResponse::Conflict().json(json!({
+                "error": format!("El bimestre {} del año {} ya existe", number, year)
+            }))
+        }
+        Err(e) => {
+            eprintln!("Error creating bimester: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+async fn fetch_bimesters(
+    pool: &sqlx::PgPool,
+    filter: &YearFilter,
+) -> Result<Vec<Bimester>, sqlx::Error> {
+    sqlx::query_as::<_, Bimester>(&format!(
+        r#"
+        SELECT {} FROM bimesters
+        WHERE ($1::int IS NULL OR year = $1)
+          AND ($2::int IS NULL OR academic_year_id = $2)
+        ORDER BY year, number, id
+        "#,
+        BIMESTER_COLUMNS
+    ))
+    .bind(filter.year)
+    .bind(filter.academic_year_id)
+    .fetch_all(pool)
     .await
-    .unwrap();
-    HttpResponse::Ok().json(rec)
 }
 
 #[get("/bimesters")]
-pub async fn list_bimesters(data: web::Data<AppState>) -> impl Responder {
-    let rows = sqlx::query_as::<_, Bimester>("SELECT id, name FROM bimesters ORDER BY id")
-        .fetch_all(&data.pool)
-        .await
-        .unwrap();
-    HttpResponse::Ok().json(rows)
+pub async fn list_bimesters(
+    query: web::Query<YearFilter>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match fetch_bimesters(&data.pool, &query).await {
+        Ok(rows) => HttpResponse::Ok().json(rows),
+        Err(e) => {
+            eprintln!("Error fetching bimesters: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al obtener bimestres")
+        }
+    }
 }
 
 #[get("/bimesters/full")]
-pub async fn list_bimesters_full(data: web::Data<AppState>) -> impl Responder {
-    // Obtener todos los bimestres
-    let bimesters =
-        match sqlx::query_as::<_, Bimester>("SELECT id, name FROM bimesters ORDER BY id")
-            .fetch_all(&data.pool)
-            .await
-        {
-            Ok(rows) => rows,
-            Err(e) => {
-                eprintln!("Error fetching bimesters: {:?}", e);
-                return HttpResponse::InternalServerError().body("Error al obtener bimestres");
-            }
-        };
+pub async fn list_bimesters_full(
+    query: web::Query<YearFilter>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    // Obtener los bimestres del año pedido (o todos)
+    let bimesters = match fetch_bimesters(&data.pool, &query).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            eprintln!("Error fetching bimesters: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error al obtener bimestres");
+        }
+    };
 
     let mut result = Vec::new();
 
@@ -94,6 +216,11 @@ pub async fn list_bimesters_full(data: web::Data<AppState>) -> impl Responder {
         result.push(json!({
             "id": bimester.id,
             "name": bimester.name,
+            "year": bimester.year,
+            "academic_year_id": bimester.academic_year_id,
+            "number": bimester.number,
+            "start_date": bimester.start_date,
+            "end_date": bimester.end_date,
             "grades": grades_with_sections
         }));
     }
@@ -101,6 +228,105 @@ pub async fn list_bimesters_full(data: web::Data<AppState>) -> impl Responder {
     HttpResponse::Ok().json(result)
 }
 
+// Copia grados, secciones y opcionalmente las nóminas a otro bimestre, devolviendo
+// la correspondencia de ids. Con dry_run se ejecuta y se deshace.
+#[post("/bimesters/{b_id}/clone")]
+pub async fn clone_bimester_tree(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    query: web::Query<CloneBimesterQuery>,
+    data: web::Data<AppState>,
+    body: web::Json<CloneBimesterIn>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Admin]).await {
+        return resp;
+    }
+    let b_id = path.into_inner();
+    let dry_run = query.dry_run.unwrap_or(false);
+
+    let mut tx = match data.pool.begin().await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+    let result = match clone_bimester(&mut tx, b_id, &body, dry_run).await {
+        Ok(r) => r,
+        Err(CloneError::NotFound(msg)) => return HttpResponse::NotFound().body(msg),
+        Err(CloneError::Conflict(msg)) => {
+            return HttpResponse::Conflict().json(json!({ "error": msg }));
+        }
+        Err(CloneError::Db(e)) => {
+            eprintln!("Error cloning bimester: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error al copiar el bimestre");
+        }
+    };
+
+    let finished = if dry_run {
+        tx.rollback().await
+    } else {
+        tx.commit().await
+    };
+    if let Err(e) = finished {
+        eprintln!("Error finishing bimester clone: {:?}", e);
+        return HttpResponse::InternalServerError().body("Error al copiar el bimestre");
+    }
+    HttpResponse::Ok().json(result)
+}
+
+// Pasa a los alumnos del bimestre al año siguiente: grado N a N+1 en la misma letra,
+// salvo quienes el personal marque como repitentes o retirados. Todo en una sola
+// transacción; con dry_run se ejecuta y se deshace para revisar la propuesta.
+#[post("/bimesters/{b_id}/promote")]
+pub async fn promote_bimester_students(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    query: web::Query<PromotionQuery>,
+    data: web::Data<AppState>,
+    body: web::Json<PromotionIn>,
+) -> impl Responder {
+    let admin = match require_role(&data.pool, &req, &[UserRole::Admin]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+    let b_id = path.into_inner();
+    let dry_run = query.dry_run.unwrap_or(false);
+
+    let mut tx = match data.pool.begin().await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+    let result = match promote_students(&mut tx, b_id, &body, admin.id, dry_run).await {
+        Ok(r) => r,
+        Err(PromotionError::NotFound(msg)) => return HttpResponse::NotFound().body(msg),
+        Err(PromotionError::Invalid(msg)) => {
+            return HttpResponse::BadRequest().json(json!({ "error": msg }));
+        }
+        Err(PromotionError::Conflict(msg)) => {
+            return HttpResponse::Conflict().json(json!({ "error": msg }));
+        }
+        Err(PromotionError::Db(e)) => {
+            eprintln!("Error promoting students: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error al promover alumnos");
+        }
+    };
+
+    let finished = if dry_run {
+        tx.rollback().await
+    } else {
+        tx.commit().await
+    };
+    if let Err(e) = finished {
+        eprintln!("Error finishing promotion: {:?}", e);
+        return HttpResponse::InternalServerError().body("Error al promover alumnos");
+    }
+    HttpResponse::Ok().json(result)
+}
+
 #[post("/bimesters/{b_id}/grades")]
 pub async fn create_grade(
     path: web::Path<i32>,
@@ -280,6 +506,108 @@ pub async fn get_section(path: web::Path<i32>, data: web::Data<AppState>) -> imp
     }
 }
 
+#[post("/sections/{sec_id}/teachers")]
+pub async fn assign_section_teacher(
+    path: web::Path<i32>,
+    data: web::Data<AppState>,
+    body: web::Json<AssignSectionTeacherIn>,
+) -> impl Responder {
+    let sec_id = path.into_inner();
+
+    let is_teacher = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM teacher_profiles WHERE user_id = $1)",
+    )
+    .bind(body.teacher_user_id)
+    .fetch_one(&data.pool)
+    .await;
+
+    match is_teacher {
+        Ok(true) => {}
+        Ok(false) => return HttpResponse::BadRequest().body("El usuario no es docente"),
+        Err(e) => {
+            eprintln!("Error checking teacher: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    }
+
+    let rec = sqlx::query_as::<_, SectionTeacher>(
+        r#"
+        WITH st AS (
+            INSERT INTO section_teachers (section_id, teacher_user_id, is_tutor)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (section_id, teacher_user_id) DO UPDATE SET is_tutor = EXCLUDED.is_tutor
+            RETURNING section_id, teacher_user_id, is_tutor, assigned_at
+        )
+        SELECT st.section_id, st.teacher_user_id, tp.full_name, st.is_tutor, st.assigned_at
+        FROM st
+        LEFT JOIN teacher_profiles tp ON tp.user_id = st.teacher_user_id
+        "#,
+    )
+    .bind(sec_id)
+    .bind(body.teacher_user_id)
+    .bind(body.is_tutor.unwrap_or(false))
+    .fetch_one(&data.pool)
+    .await;
+
+    match rec {
+        Ok(r) => HttpResponse::Ok().json(r),
+        Err(e) => {
+            eprintln!("Error assigning teacher: {:?}", e);
+            HttpResponse::InternalServerError().body("No se pudo asignar el docente")
+        }
+    }
+}
+
+#[get("/sections/{sec_id}/teachers")]
+pub async fn list_section_teachers(
+    path: web::Path<i32>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let sec_id = path.into_inner();
+    let rows = sqlx::query_as::<_, SectionTeacher>(
+        r#"
+        SELECT st.section_id, st.teacher_user_id, tp.full_name, st.is_tutor, st.assigned_at
+        FROM section_teachers st
+        LEFT JOIN teacher_profiles tp ON tp.user_id = st.teacher_user_id
+        WHERE st.section_id = $1
+        ORDER BY st.is_tutor DESC, tp.full_name
+        "#,
+    )
+    .bind(sec_id)
+    .fetch_all(&data.pool)
+    .await;
+
+    match rows {
+        Ok(r) => HttpResponse::Ok().json(r),
+        Err(e) => {
+            eprintln!("Error fetching section teachers: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al obtener docentes")
+        }
+    }
+}
+
+#[delete("/sections/{sec_id}/teachers/{teacher_user_id}")]
+pub async fn remove_section_teacher(
+    path: web::Path<(i32, i32)>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let (sec_id, teacher_user_id) = path.into_inner();
+    let result =
+        sqlx::query("DELETE FROM section_teachers WHERE section_id = $1 AND teacher_user_id = $2")
+            .bind(sec_id)
+            .bind(teacher_user_id)
+            .execute(&data.pool)
+            .await;
+
+    match result {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            eprintln!("Error removing section teacher: {:?}", e);
+            HttpResponse::InternalServerError().body("No se pudo quitar el docente")
+        }
+    }
+}
+
 #[get("/sections/{section_id}/consolidado")]
 pub async fn get_consolidado_section(
     path: web::Path<i32>,
@@ -460,6 +788,8 @@ pub async fn get_consolidado_section(
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(create_bimester)
         .service(list_bimesters)
+        .service(clone_bimester_tree)
+        .service(promote_bimester_students)
         .service(create_grade)
         .service(list_grades)
         .service(create_section)
@@ -469,5 +799,8 @@ pub fn config(cfg: &mut web::ServiceConfig) {
         .service(delete_section)
         .service(get_section)
         .service(get_consolidado_section)
-        .service(list_bimesters_full);
+        .service(list_bimesters_full)
+        .service(assign_section_teacher)
+        .service(list_section_teachers)
+        .service(remove_section_teacher);
 }
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+pub const ATTENDANCE_STATUSES: [&str; 4] = ["present", "absent", "late", "justified"];
+
+#[derive(Serialize, FromRow)]
+pub struct AttendanceRecord {
+    pub id: i32,
+    pub session_id: i32,
+    pub student_id: i32,
+    pub status: String,
+    pub note: Option<String>,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct AttendanceIn {
+    pub session_id: i32,
+    pub student_id: i32,
+    pub status: String,
+    pub note: Option<String>,
+}
@@ -0,0 +1,97 @@
+use crate::auth::guard::require_role;
+use crate::auth::models::UserRole;
+use crate::basic::session::attendance::models::*;
+use crate::AppState;
+use actix_web::{get, put, web, HttpRequest, HttpResponse, Responder};
+
+#[put("/attendance")]
+pub async fn upsert_attendance(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    body: web::Json<AttendanceIn>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+    if !ATTENDANCE_STATUSES.contains(&body.status.as_str()) {
+        return HttpResponse::BadRequest().body(format!(
+            "Estado inválido. Usa uno de: {}",
+            ATTENDANCE_STATUSES.join(", ")
+        ));
+    }
+
+    // El alumno debe pertenecer a la sección de la sesión
+    match sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM sessions s JOIN students st ON st.section_id = s.section_id
+                        WHERE s.id = $1 AND st.id = $2)",
+    )
+    .bind(body.session_id)
+    .bind(body.student_id)
+    .fetch_one(&data.pool)
+    .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::BadRequest()
+                .body("El alumno no pertenece a la sección de la sesión")
+        }
+        Err(e) => {
+            eprintln!("Error verificando alumno de la sesión: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    }
+
+    let rec = sqlx::query_as::<_, AttendanceRecord>(
+        r#"INSERT INTO attendance_records (session_id, student_id, status, note)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (session_id, student_id)
+        DO UPDATE SET status=EXCLUDED.status, note=EXCLUDED.note, updated_at=NOW()
+        RETURNING id, session_id, student_id, status, note, updated_at"#,
+    )
+    .bind(body.session_id)
+    .bind(body.student_id)
+    .bind(&body.status)
+    .bind(&body.note)
+    .fetch_one(&data.pool)
+    .await;
+
+    match rec {
+        Ok(r) => HttpResponse::Ok().json(r),
+        Err(e) => {
+            eprintln!("Error guardando asistencia: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+#[get("/sessions/{sess_id}/attendance")]
+pub async fn list_session_attendance(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+    let sess_id = path.into_inner();
+    let rows = sqlx::query_as::<_, AttendanceRecord>(
+        "SELECT id, session_id, student_id, status, note, updated_at
+         FROM attendance_records WHERE session_id=$1 ORDER BY student_id",
+    )
+    .bind(sess_id)
+    .fetch_all(&data.pool)
+    .await;
+
+    match rows {
+        Ok(r) => HttpResponse::Ok().json(r),
+        Err(e) => {
+            eprintln!("Error obteniendo asistencia: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(upsert_attendance)
+        .service(list_session_attendance);
+}
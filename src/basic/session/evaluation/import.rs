@@ -0,0 +1,457 @@
+use crate::appeals::routes::change_evaluation_value;
+use crate::basic::session::evaluation::models::*;
+use crate::imports::table::normalize_text;
+use sqlx::{PgConnection, PgPool, Row};
+use std::collections::{HashMap, HashSet};
+
+const STUDENT_HEADERS: [&str; 9] = [
+    "estudiante",
+    "estudiantes",
+    "alumno",
+    "alumnos",
+    "nombre",
+    "nombres",
+    "apellidos y nombres",
+    "nombres y apellidos",
+    "full name",
+];
+const DNI_HEADERS: [&str; 4] = ["dni", "documento", "nro documento", "n documento"];
+const OBSERVATION_HEADERS: [&str; 2] = ["observacion", "observaciones"];
+const IGNORED_HEADERS: [&str; 6] = ["", "n", "no", "nro", "numero", "codigo"];
+
+struct MatrixStudent {
+    id: i32,
+    full_name: String,
+    dni: Option<String>,
+}
+
+struct MatrixCriterion {
+    id: i32,
+    ability_id: i32,
+    ability_number: i32,
+    number: i32,
+    display_name: String,
+}
+
+// Nombre con las palabras ordenadas, para aceptar "JUAN PEREZ" y "PEREZ JUAN"
+fn sorted_tokens(name: &str) -> String {
+    let mut tokens: Vec<&str> = name.split(' ').collect();
+    tokens.sort_unstable();
+    tokens.join(" ")
+}
+
+// Un encabezado de criterio puede ser su nombre, "1.2" (capacidad 1, criterio 2)
+// o "C2"/"2". Esta última forma es posicional dentro del grupo de columnas de la
+// capacidad: se sigue en la capacidad de la columna anterior mientras el número
+// avance y se pasa a la siguiente cuando vuelve a empezar.
+fn match_criterion(
+    header: &str,
+    criteria: &[MatrixCriterion],
+    previous: Option<usize>,
+) -> Result<Option<usize>, String> {
+    let by_name: Vec<usize> = criteria
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| normalize_text(&c.display_name) == header)
+        .map(|(i, _)| i)
+        .collect();
+    if by_name.len() == 1 {
+        return Ok(Some(by_name[0]));
+    }
+
+    if let Some((a, c)) = header.split_once(' ') {
+        if let (Ok(a), Ok(c)) = (a.parse::<i32>(), c.parse::<i32>()) {
+            return Ok(criteria
+                .iter()
+                .position(|cr| cr.ability_number == a && cr.number == c));
+        }
+    }
+
+    let digits = header.strip_prefix('c').unwrap_or(header).trim();
+    let Ok(n) = digits.parse::<i32>() else {
+        if by_name.len() > 1 {
+            return Err(format!(
+                "Hay {} criterios con este nombre; use la forma capacidad.criterio",
+                by_name.len()
+            ));
+        }
+        return Ok(None);
+    };
+    let ability = match previous.map(|i| &criteria[i]) {
+        None => criteria.first().map(|c| c.ability_number),
+        Some(prev) if n > prev.number => Some(prev.ability_number),
+        Some(prev) => criteria
+            .iter()
+            .map(|c| c.ability_number)
+            .find(|a| *a > prev.ability_number),
+    };
+    let Some(ability) = ability else {
+        return Err("No quedan capacidades para esta columna".to_string());
+    };
+    match criteria
+        .iter()
+        .position(|c| c.ability_number == ability && c.number == n)
+    {
+        Some(i) => Ok(Some(i)),
+        None => Err(format!(
+            "La capacidad {} no tiene el criterio {}",
+            ability, n
+        )),
+    }
+}
+
+// Arma la vista previa de la importación: qué celdas se crean, cuáles cambian
+// y qué filas o columnas no se pudieron interpretar. No escribe nada.
+pub async fn plan_evaluation_import(
+    pool: &PgPool,
+    session_id: i32,
+    competency_id: i32,
+    product_id: i32,
+    rows: &[Vec<String>],
+) -> Result<EvaluationImportResult, sqlx::Error> {
+    let locked = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM evaluation_locks WHERE session_id=$1 AND competency_id=$2)",
+    )
+    .bind(session_id)
+    .bind(competency_id)
+    .fetch_one(pool)
+    .await?;
+
+    let students: Vec<MatrixStudent> = sqlx::query(
+        "SELECT st.id, st.full_name, st.dni
+           FROM sessions s
+           JOIN students st ON st.section_id = s.section_id
+          WHERE s.id=$1
+          ORDER BY st.full_name",
+    )
+    .bind(session_id)
+    .fetch_all(pool)
+    .await?
+    .iter()
+    .map(|r| MatrixStudent {
+        id: r.get("id"),
+        full_name: r.get("full_name"),
+        dni: r.get("dni"),
+    })
+    .collect();
+
+    let criteria: Vec<MatrixCriterion> = sqlx::query(
+        "SELECT c.id, c.ability_id, a.number AS ability_number, c.number,
+                COALESCE(c.name, 'C'||c.number::text) AS display_name
+           FROM criteria c
+           JOIN abilities a ON a.id = c.ability_id
+          WHERE a.competency_id=$1
+          ORDER BY a.number, c.number",
+    )
+    .bind(competency_id)
+    .fetch_all(pool)
+    .await?
+    .iter()
+    .map(|r| MatrixCriterion {
+        id: r.get("id"),
+        ability_id: r.get("ability_id"),
+        ability_number: r.get("ability_number"),
+        number: r.get("number"),
+        display_name: r.get("display_name"),
+    })
+    .collect();
+
+    let existing: HashMap<(i32, i32), (i32, String, Option<String>)> = sqlx::query(
+        "SELECT id, student_id, criterion_id, value::text AS value, observation
+           FROM evaluation_items
+          WHERE session_id=$1 AND competency_id=$2 AND product_id=$3",
+    )
+    .bind(session_id)
+    .bind(competency_id)
+    .bind(product_id)
+    .fetch_all(pool)
+    .await?
+    .iter()
+    .map(|r| {
+        (
+            (r.get("student_id"), r.get("criterion_id")),
+            (r.get("id"), r.get("value"), r.get("observation")),
+        )
+    })
+    .collect();
+
+    let mut result = EvaluationImportResult {
+        dry_run: true,
+        applied: false,
+        locked,
+        summary: EvaluationImportSummary::default(),
+        columns: Vec::new(),
+        changes: Vec::new(),
+        errors: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    // La fila de encabezados es la primera que tiene una columna de estudiante;
+    // así se aceptan archivos con títulos o encabezados combinados arriba
+    let header_idx = rows.iter().take(15).position(|r| {
+        r.iter()
+            .any(|c| STUDENT_HEADERS.contains(&normalize_text(c).as_str()))
+    });
+    let Some(header_idx) = header_idx else {
+        result.errors.push(ImportIssue {
+            row: 1,
+            column: None,
+            message: "No se encontró la columna de estudiantes (Estudiante, Alumno o Nombres)"
+                .to_string(),
+        });
+        result.summary.errors = 1;
+        return Ok(result);
+    };
+    let header: Vec<String> = rows[header_idx].iter().map(|h| normalize_text(h)).collect();
+
+    let mut student_col = None;
+    let mut dni_col = None;
+    let mut obs_col = None;
+    let mut criterion_cols: Vec<(usize, usize)> = Vec::new();
+    let mut seen_criteria = HashSet::new();
+    for (col, h) in header.iter().enumerate() {
+        let raw = rows[header_idx][col].clone();
+        if student_col.is_none() && STUDENT_HEADERS.contains(&h.as_str()) {
+            student_col = Some(col);
+        } else if dni_col.is_none() && DNI_HEADERS.contains(&h.as_str()) {
+            dni_col = Some(col);
+        } else if obs_col.is_none() && OBSERVATION_HEADERS.contains(&h.as_str()) {
+            obs_col = Some(col);
+        } else if IGNORED_HEADERS.contains(&h.as_str()) {
+            continue;
+        } else {
+            let previous = criterion_cols.last().map(|(_, ci)| *ci);
+            let ci = match match_criterion(h, &criteria, previous) {
+                Ok(Some(ci)) => ci,
+                Ok(None) => {
+                    result.warnings.push(ImportIssue {
+                        row: header_idx + 1,
+                        column: Some(raw),
+                        message: "Columna no reconocida como criterio; se ignora".to_string(),
+                    });
+                    continue;
+                }
+                Err(message) => {
+                    result.errors.push(ImportIssue {
+                        row: header_idx + 1,
+                        column: Some(raw),
+                        message,
+                    });
+                    continue;
+                }
+            };
+            if !seen_criteria.insert(ci) {
+                result.errors.push(ImportIssue {
+                    row: header_idx + 1,
+                    column: Some(raw),
+                    message: format!(
+                        "El criterio {}.{} aparece en más de una columna",
+                        criteria[ci].ability_number, criteria[ci].number
+                    ),
+                });
+                continue;
+            }
+            result.columns.push(ImportColumnMatch {
+                column: col + 1,
+                header: raw,
+                criterion_id: criteria[ci].id,
+                criterion_name: criteria[ci].display_name.clone(),
+            });
+            criterion_cols.push((col, ci));
+        }
+    }
+    let student_col = student_col.unwrap_or(0);
+
+    if criterion_cols.is_empty() {
+        result.errors.push(ImportIssue {
+            row: header_idx + 1,
+            column: None,
+            message: "Ninguna columna coincide con los criterios de la competencia".to_string(),
+        });
+    }
+
+    let by_name: HashMap<String, Vec<&MatrixStudent>> =
+        students.iter().fold(HashMap::new(), |mut acc, s| {
+            acc.entry(normalize_text(&s.full_name))
+                .or_insert_with(Vec::new)
+                .push(s);
+            acc
+        });
+    let by_sorted_name: HashMap<String, Vec<&MatrixStudent>> =
+        students.iter().fold(HashMap::new(), |mut acc, s| {
+            acc.entry(sorted_tokens(&normalize_text(&s.full_name)))
+                .or_insert_with(Vec::new)
+                .push(s);
+            acc
+        });
+
+    let mut seen_students = HashSet::new();
+    for (offset, row) in rows.iter().enumerate().skip(header_idx + 1) {
+        let row_number = offset + 1;
+        let cell = |col: usize| row.get(col).map(|c| c.trim()).unwrap_or("");
+        let name = cell(student_col);
+        let dni = dni_col.map(cell).unwrap_or("");
+        if name.is_empty() && dni.is_empty() {
+            continue;
+        }
+        result.summary.rows += 1;
+
+        let mut error = |message: String| {
+            result.errors.push(ImportIssue {
+                row: row_number,
+                column: None,
+                message,
+            })
+        };
+
+        // Primero por DNI, luego por nombre (exacto y con palabras en otro orden)
+        let student = if !dni.is_empty() {
+            students.iter().find(|s| s.dni.as_deref() == Some(dni))
+        } else {
+            None
+        };
+        let student = match student {
+            Some(s) => s,
+            None => {
+                let key = normalize_text(name);
+                let candidates = by_name
+                    .get(&key)
+                    .or_else(|| by_sorted_name.get(&sorted_tokens(&key)));
+                match candidates.map(|c| c.as_slice()) {
+                    Some([s]) => *s,
+                    Some(_) => {
+                        error(format!(
+                            "'{}' coincide con varios alumnos; indique el DNI",
+                            name
+                        ));
+                        continue;
+                    }
+                    None => {
+                        error(format!("Alumno no encontrado en la sección: '{}'", name));
+                        continue;
+                    }
+                }
+            }
+        };
+        if !seen_students.insert(student.id) {
+            error(format!("'{}' aparece más de una vez", student.full_name));
+            continue;
+        }
+
+        let observation = obs_col
+            .map(cell)
+            .filter(|o| !o.is_empty())
+            .map(|o| o.to_string());
+
+        for &(col, ci) in &criterion_cols {
+            let raw = cell(col);
+            if raw.is_empty() {
+                continue;
+            }
+            let value = raw.to_uppercase();
+            let criterion = &criteria[ci];
+            if !EVAL_LEVELS.contains(&value.as_str()) {
+                result.errors.push(ImportIssue {
+                    row: row_number,
+                    column: Some(criterion.display_name.clone()),
+                    message: format!("Nivel inválido '{}' (use AD, A, B o C)", raw),
+                });
+                continue;
+            }
+
+            let old = existing.get(&(student.id, criterion.id));
+            // Sin observación en la hoja se conserva la registrada
+            let action = match old {
+                None => ImportAction::Create,
+                Some((_, v, obs))
+                    if *v == value && (observation.is_none() || observation == *obs) =>
+                {
+                    ImportAction::Unchanged
+                }
+                Some(_) => ImportAction::Update,
+            };
+            match action {
+                ImportAction::Create => result.summary.created += 1,
+                ImportAction::Update => result.summary.updated += 1,
+                ImportAction::Unchanged => result.summary.unchanged += 1,
+            }
+            result.changes.push(EvaluationChange {
+                row: row_number,
+                student_id: student.id,
+                student_name: student.full_name.clone(),
+                ability_id: criterion.ability_id,
+                criterion_id: criterion.id,
+                criterion_name: criterion.display_name.clone(),
+                evaluation_item_id: old.map(|(id, _, _)| *id),
+                old_value: old.map(|(_, v, _)| v.clone()),
+                new_value: value,
+                observation: observation.clone(),
+                action,
+            });
+        }
+    }
+
+    result.summary.errors = result.errors.len();
+    Ok(result)
+}
+
+// Aplica los cambios de la vista previa. Las modificaciones de celdas existentes
+// quedan en evaluation_item_history igual que un cambio manual.
+pub async fn apply_evaluation_import(
+    conn: &mut PgConnection,
+    session_id: i32,
+    competency_id: i32,
+    product_id: i32,
+    changes: &[EvaluationChange],
+    user_id: i32,
+) -> Result<(), sqlx::Error> {
+    for change in changes {
+        match (change.action, change.evaluation_item_id) {
+            (ImportAction::Update, Some(item_id)) => {
+                if change.old_value.as_deref() != Some(change.new_value.as_str()) {
+                    change_evaluation_value(
+                        &mut *conn,
+                        item_id,
+                        &change.new_value,
+                        user_id,
+                        None,
+                        Some("Importación desde hoja de cálculo"),
+                    )
+                    .await?;
+                }
+                if let Some(obs) = &change.observation {
+                    sqlx::query(
+                        "UPDATE evaluation_items SET observation = $1, updated_at = NOW() WHERE id = $2",
+                    )
+                    .bind(obs)
+                    .bind(item_id)
+                    .execute(&mut *conn)
+                    .await?;
+                }
+            }
+            (ImportAction::Create, _) => {
+                sqlx::query(
+                    r#"INSERT INTO evaluation_items
+                       (session_id, competency_id, ability_id, criterion_id, product_id, student_id, value, observation)
+                    VALUES ($1,$2,$3,$4,$5,$6,$7::eval_level,$8)
+                    ON CONFLICT (session_id, competency_id, ability_id, criterion_id, product_id, student_id)
+                    DO UPDATE SET value=EXCLUDED.value,
+                                  observation=COALESCE(EXCLUDED.observation, evaluation_items.observation),
+                                  updated_at=NOW()"#,
+                )
+                .bind(session_id)
+                .bind(competency_id)
+                .bind(change.ability_id)
+                .bind(change.criterion_id)
+                .bind(product_id)
+                .bind(change.student_id)
+                .bind(&change.new_value)
+                .bind(&change.observation)
+                .execute(&mut *conn)
+                .await?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -1,2 +1,3 @@
+pub mod import;
 pub mod models;
-pub mod routes;
\ No newline at end of file
+pub mod routes;
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
+pub const EVAL_LEVELS: [&str; 4] = ["AD", "A", "B", "C"];
+
 #[derive(Serialize, FromRow)]
 pub struct Section {
     pub id: i32,
@@ -101,6 +103,7 @@ pub struct EvaluationItem {
 #[derive(Serialize, FromRow)]
 pub struct MatrixResponse {
     pub locked: bool,
+    pub published: bool,
     pub competency: serde_json::Value,
     pub abilities: Vec<serde_json::Value>,
     pub criteria: Vec<serde_json::Value>,
@@ -165,6 +168,7 @@ pub struct ReniecResponse {
 #[derive(Serialize)]
 pub struct EvaluationContextResponse {
     pub locked: bool,
+    pub published: bool,
     pub competency: serde_json::Value,
     pub product: serde_json::Value,
     pub abilities: Vec<serde_json::Value>,
@@ -178,4 +182,78 @@ pub struct EvaluationContextParams {
     pub session_id: i32,
     pub competency_id: i32,
     pub product_id: i32,
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, FromRow)]
+pub struct GradePublication {
+    pub session_id: i32,
+    pub competency_id: i32,
+    pub published_at: chrono::NaiveDateTime,
+    pub published_by_user_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct EvaluationImportQuery {
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct ImportColumnMatch {
+    pub column: usize,
+    pub header: String,
+    pub criterion_id: i32,
+    pub criterion_name: String,
+}
+
+#[derive(Serialize)]
+pub struct ImportIssue {
+    pub row: usize,
+    pub column: Option<String>,
+    pub message: String,
+}
+
+// Qué hace la importación con cada celda
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportAction {
+    Create,
+    Update,
+    // Mismo nivel y, si la hoja trae observación, la misma observación
+    Unchanged,
+}
+
+#[derive(Serialize)]
+pub struct EvaluationChange {
+    pub row: usize,
+    pub student_id: i32,
+    pub student_name: String,
+    pub ability_id: i32,
+    pub criterion_id: i32,
+    pub criterion_name: String,
+    pub evaluation_item_id: Option<i32>,
+    pub old_value: Option<String>,
+    pub new_value: String,
+    pub observation: Option<String>,
+    pub action: ImportAction,
+}
+
+#[derive(Serialize, Default)]
+pub struct EvaluationImportSummary {
+    pub rows: usize,
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub errors: usize,
+}
+
+#[derive(Serialize)]
+pub struct EvaluationImportResult {
+    pub dry_run: bool,
+    pub applied: bool,
+    pub locked: bool,
+    pub summary: EvaluationImportSummary,
+    pub columns: Vec<ImportColumnMatch>,
+    pub changes: Vec<EvaluationChange>,
+    pub errors: Vec<ImportIssue>,
+    pub warnings: Vec<ImportIssue>,
+}
@@ -1,6 +1,12 @@
+use crate::auth::guard::require_role;
+use crate::auth::models::{User, UserRole};
+use crate::basic::session::evaluation::import::{apply_evaluation_import, plan_evaluation_import};
 use crate::basic::session::evaluation::models::*;
+use crate::imports::table::read_table;
+use crate::imports::upload::read_upload;
 use crate::AppState;
-use actix_web::{delete, get, put, web, HttpResponse, Responder};
+use actix_multipart::Multipart;
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
 use sqlx::Row;
 
 #[put("/evaluation/value")]
@@ -115,6 +121,15 @@ pub async fn get_matrix_new(
     .await
     .unwrap();
 
+    let published = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM grade_publications WHERE session_id=$1 AND competency_id=$2)",
+    )
+    .bind(sess_id)
+    .bind(comp_id)
+    .fetch_one(&data.pool)
+    .await
+    .unwrap_or(false);
+
     let comp_row = sqlx::query(
         "SELECT id, number, COALESCE(name, 'Competencia '||number::text) AS display_name
          FROM competencies WHERE id=$1",
@@ -177,6 +192,7 @@ pub async fn get_matrix_new(
 
     let resp = MatrixResponse {
         locked,
+        published,
         competency: serde_json::json!({
             "id": comp_row.try_get::<i32, _>("id").unwrap(),
             "display_name": comp_row.try_get::<String, _>("display_name").unwrap()
@@ -250,6 +266,15 @@ pub async fn evaluation_context(
     .await
     .unwrap_or(false);
 
+    let published = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM grade_publications WHERE session_id=$1 AND competency_id=$2)",
+    )
+    .bind(session_id)
+    .bind(competency_id)
+    .fetch_one(&data.pool)
+    .await
+    .unwrap_or(false);
+
     let competency = sqlx::query(
         "SELECT id, number, COALESCE(name, 'Competencia '||number::text) AS display_name
          FROM competencies WHERE id=$1",
@@ -318,6 +343,7 @@ pub async fn evaluation_context(
 
     let resp = EvaluationContextResponse {
         locked,
+        published,
         competency: serde_json::json!({
             "id": competency.try_get::<i32, _>("id").unwrap(),
             "display_name": competency.try_get::<String, _>("display_name").unwrap()
@@ -372,10 +398,384 @@ pub async fn evaluation_context(
     HttpResponse::Ok().json(resp)
 }
 
+// El docente solo publica las notas de las sesiones de sus secciones
+async fn teaches_session(
+    pool: &sqlx::PgPool,
+    user: &User,
+    sess_id: i32,
+) -> Result<bool, sqlx::Error> {
+    if user.role == UserRole::Admin {
+        return Ok(true);
+    }
+    sqlx::query_scalar::<_, bool>(
+        r#"SELECT EXISTS(
+            SELECT 1 FROM sessions s
+            JOIN section_teachers st ON st.section_id = s.section_id
+            WHERE s.id = $1 AND st.teacher_user_id = $2
+        )"#,
+    )
+    .bind(sess_id)
+    .bind(user.id)
+    .fetch_one(pool)
+    .await
+}
+
+// None para el admin; para el docente, su id, que limita la publicación del bimestre
+// a las secciones que tiene asignadas
+fn teacher_scope(user: &User) -> Option<i32> {
+    (user.role != UserRole::Admin).then_some(user.id)
+}
+
+fn check_session_teacher(allowed: Result<bool, sqlx::Error>) -> Result<(), HttpResponse> {
+    match allowed {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            Err(HttpResponse::Forbidden().body("La sesión no corresponde a sus secciones"))
+        }
+        Err(e) => {
+            eprintln!("Error checking section teacher: {:?}", e);
+            Err(HttpResponse::InternalServerError().body("Error en la base de datos"))
+        }
+    }
+}
+
+#[post("/sessions/{sess_id}/competencies/{comp_id}/publish")]
+pub async fn publish_competency(
+    path: web::Path<(i32, i32)>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let (sess_id, comp_id) = path.into_inner();
+    let user = match require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = check_session_teacher(teaches_session(&data.pool, &user, sess_id).await) {
+        return resp;
+    }
+
+    let rec = sqlx::query_as::<_, GradePublication>(
+        r#"INSERT INTO grade_publications (session_id, competency_id, published_by_user_id)
+        SELECT c.session_id, c.id, $3
+        FROM competencies c
+        WHERE c.id = $2 AND c.session_id = $1
+        ON CONFLICT (session_id, competency_id)
+        DO UPDATE SET published_at=NOW(), published_by_user_id=EXCLUDED.published_by_user_id
+        RETURNING session_id, competency_id, published_at, published_by_user_id"#,
+    )
+    .bind(sess_id)
+    .bind(comp_id)
+    .bind(user.id)
+    .fetch_optional(&data.pool)
+    .await;
+
+    match rec {
+        Ok(Some(p)) => HttpResponse::Ok().json(p),
+        Ok(None) => HttpResponse::NotFound().body("La competencia no pertenece a la sesión"),
+        Err(e) => {
+            eprintln!("Error publicando notas: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+#[delete("/sessions/{sess_id}/competencies/{comp_id}/publish")]
+pub async fn unpublish_competency(
+    path: web::Path<(i32, i32)>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let (sess_id, comp_id) = path.into_inner();
+    let user = match require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = check_session_teacher(teaches_session(&data.pool, &user, sess_id).await) {
+        return resp;
+    }
+
+    let result =
+        sqlx::query("DELETE FROM grade_publications WHERE session_id=$1 AND competency_id=$2")
+            .bind(sess_id)
+            .bind(comp_id)
+            .execute(&data.pool)
+            .await;
+
+    match result {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            eprintln!("Error despublicando notas: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+#[get("/sessions/{sess_id}/publications")]
+pub async fn list_publications(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+    let sess_id = path.into_inner();
+    let rows = sqlx::query_as::<_, GradePublication>(
+        "SELECT session_id, competency_id, published_at, published_by_user_id
+         FROM grade_publications WHERE session_id=$1 ORDER BY competency_id",
+    )
+    .bind(sess_id)
+    .fetch_all(&data.pool)
+    .await;
+
+    match rows {
+        Ok(r) => HttpResponse::Ok().json(r),
+        Err(e) => {
+            eprintln!("Error obteniendo publicaciones: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+// Un docente sin secciones asignadas en el bimestre no puede publicar ni despublicar
+async fn check_bimester_teacher(
+    pool: &sqlx::PgPool,
+    user: &User,
+    b_id: i32,
+) -> Result<(), HttpResponse> {
+    if user.role == UserRole::Admin {
+        return Ok(());
+    }
+    let teaches = sqlx::query_scalar::<_, bool>(
+        r#"SELECT EXISTS(
+            SELECT 1 FROM section_teachers st
+            JOIN sections sec ON sec.id = st.section_id
+            JOIN grades g ON g.id = sec.grade_id
+            WHERE g.bimester_id = $1 AND st.teacher_user_id = $2
+        )"#,
+    )
+    .bind(b_id)
+    .bind(user.id)
+    .fetch_one(pool)
+    .await;
+    match teaches {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            Err(HttpResponse::Forbidden().body("No tiene secciones asignadas en el bimestre"))
+        }
+        Err(e) => {
+            eprintln!("Error checking section teacher: {:?}", e);
+            Err(HttpResponse::InternalServerError().body("Error en la base de datos"))
+        }
+    }
+}
+
+// Publica de una vez todas las competencias de todas las sesiones del bimestre; el
+// docente solo las de sus secciones
+#[post("/bimesters/{b_id}/publish")]
+pub async fn publish_bimester(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let b_id = path.into_inner();
+    let user = match require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = check_bimester_teacher(&data.pool, &user, b_id).await {
+        return resp;
+    }
+
+    let result = sqlx::query(
+        r#"INSERT INTO grade_publications (session_id, competency_id, published_by_user_id)
+        SELECT c.session_id, c.id, $2
+        FROM competencies c
+        JOIN sessions s ON s.id = c.session_id
+        JOIN sections sec ON sec.id = s.section_id
+        JOIN grades g ON g.id = sec.grade_id
+        WHERE g.bimester_id = $1
+          AND ($3::int IS NULL OR EXISTS (
+              SELECT 1 FROM section_teachers st
+              WHERE st.section_id = sec.id AND st.teacher_user_id = $3
+          ))
+        ON CONFLICT (session_id, competency_id) DO NOTHING"#,
+    )
+    .bind(b_id)
+    .bind(user.id)
+    .bind(teacher_scope(&user))
+    .execute(&data.pool)
+    .await;
+
+    match result {
+        Ok(r) => HttpResponse::Ok().json(serde_json::json!({"published": r.rows_affected()})),
+        Err(e) => {
+            eprintln!("Error publicando bimestre: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+#[delete("/bimesters/{b_id}/publish")]
+pub async fn unpublish_bimester(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let b_id = path.into_inner();
+    let user = match require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = check_bimester_teacher(&data.pool, &user, b_id).await {
+        return resp;
+    }
+
+    let result = sqlx::query(
+        r#"DELETE FROM grade_publications gp
+        USING sessions s, sections sec, grades g
+        WHERE s.id = gp.session_id
+          AND sec.id = s.section_id
+          AND g.id = sec.grade_id
+          AND g.bimester_id = $1
+          AND ($2::int IS NULL OR EXISTS (
+              SELECT 1 FROM section_teachers st
+              WHERE st.section_id = sec.id AND st.teacher_user_id = $2
+          ))"#,
+    )
+    .bind(b_id)
+    .bind(teacher_scope(&user))
+    .execute(&data.pool)
+    .await;
+
+    match result {
+        Ok(r) => HttpResponse::Ok().json(serde_json::json!({"unpublished": r.rows_affected()})),
+        Err(e) => {
+            eprintln!("Error despublicando bimestre: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+// Importa una hoja con la misma forma de la matriz: alumnos en filas y criterios
+// en columnas. Con dry_run=true solo devuelve la vista previa de los cambios.
+#[post("/sessions/{sess_id}/products/{prod_id}/competencies/{comp_id}/matrix/import")]
+pub async fn import_matrix(
+    path: web::Path<(i32, i32, i32)>,
+    query: web::Query<EvaluationImportQuery>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    mut payload: Multipart,
+) -> impl Responder {
+    let user = match require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+    let (sess_id, prod_id, comp_id) = path.into_inner();
+    let dry_run = query.dry_run.unwrap_or(false);
+
+    match sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM competencies WHERE id=$1 AND session_id=$2)
+            AND EXISTS(SELECT 1 FROM products WHERE id=$3 AND session_id=$2)",
+    )
+    .bind(comp_id)
+    .bind(sess_id)
+    .bind(prod_id)
+    .fetch_one(&data.pool)
+    .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::NotFound().body("Sesión, competencia o producto no encontrado")
+        }
+        Err(e) => {
+            eprintln!("Error checking matrix: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    }
+
+    let file = match read_upload(&mut payload).await {
+        Ok(f) => f,
+        Err(resp) => return resp,
+    };
+    let rows = match read_table(&file) {
+        Ok(r) => r,
+        Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    };
+
+    let mut result =
+        match plan_evaluation_import(&data.pool, sess_id, comp_id, prod_id, &rows).await {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("Error planning import: {:?}", e);
+                return HttpResponse::InternalServerError().body("Error en la base de datos");
+            }
+        };
+
+    if dry_run {
+        return HttpResponse::Ok().json(result);
+    }
+    result.dry_run = false;
+    if result.locked {
+        return HttpResponse::Forbidden().json(result);
+    }
+    if !result.errors.is_empty() {
+        return HttpResponse::UnprocessableEntity().json(result);
+    }
+
+    let mut tx = match data.pool.begin().await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+
+    // El bloqueo pudo activarse mientras se revisaba la vista previa
+    match sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM evaluation_locks WHERE session_id=$1 AND competency_id=$2)",
+    )
+    .bind(sess_id)
+    .bind(comp_id)
+    .fetch_one(&mut *tx)
+    .await
+    {
+        Ok(false) => {}
+        Ok(true) => {
+            result.locked = true;
+            return HttpResponse::Forbidden().json(result);
+        }
+        Err(e) => {
+            eprintln!("Error checking lock: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    }
+
+    if let Err(e) =
+        apply_evaluation_import(&mut tx, sess_id, comp_id, prod_id, &result.changes, user.id).await
+    {
+        eprintln!("Error applying import: {:?}", e);
+        return HttpResponse::InternalServerError().body("Error aplicando la importación");
+    }
+    if let Err(e) = tx.commit().await {
+        eprintln!("Error committing import: {:?}", e);
+        return HttpResponse::InternalServerError().body("Error aplicando la importación");
+    }
+
+    result.applied = true;
+    HttpResponse::Ok().json(result)
+}
+
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(upsert_eval_new)
         .service(get_matrix_new)
         .service(get_evaluation_item)
         .service(delete_evaluation_item)
-        .service(evaluation_context);
+        .service(evaluation_context)
+        .service(publish_competency)
+        .service(unpublish_competency)
+        .service(list_publications)
+        .service(publish_bimester)
+        .service(unpublish_bimester)
+        .service(import_matrix);
 }
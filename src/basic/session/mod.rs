@@ -1,3 +1,4 @@
+pub mod attendance;
 pub mod evaluation;
 pub mod models;
 pub mod products;
@@ -0,0 +1,178 @@
+use crate::basic::students::models::*;
+use crate::basic::students::roster::RosterEntry;
+use crate::imports::table::normalize_text;
+use sqlx::{PgExecutor, PgPool, Row};
+use std::collections::HashMap;
+
+struct KnownStudent {
+    id: i32,
+    full_name: String,
+    dni: Option<String>,
+}
+
+// Separa las filas a crear de las que ya están en la sección (o repetidas en el
+// mismo archivo) y señala homónimos en otras secciones del mismo bimestre
+pub async fn plan_student_import(
+    pool: &PgPool,
+    section_id: i32,
+    entries: Vec<RosterEntry>,
+    errors: Vec<String>,
+) -> Result<StudentImportResult, sqlx::Error> {
+    let bimester_id: i32 = sqlx::query_scalar(
+        "SELECT g.bimester_id FROM sections sec JOIN grades g ON g.id = sec.grade_id WHERE sec.id = $1",
+    )
+    .bind(section_id)
+    .fetch_one(pool)
+    .await?;
+
+    let existing: Vec<KnownStudent> =
+        sqlx::query("SELECT id, full_name, dni FROM students WHERE section_id = $1")
+            .bind(section_id)
+            .fetch_all(pool)
+            .await?
+            .iter()
+            .map(|r| KnownStudent {
+                id: r.get("id"),
+                full_name: r.get("full_name"),
+                dni: r.get("dni"),
+            })
+            .collect();
+
+    let others = sqlx::query(
+        r#"
+        SELECT s.id, s.full_name, s.dni, sec.id AS section_id, sec.letter, g.number AS grade_number
+        FROM students s
+        JOIN sections sec ON sec.id = s.section_id
+        JOIN grades g ON g.id = sec.grade_id
+        WHERE g.bimester_id = $1 AND s.section_id <> $2
+        "#,
+    )
+    .bind(bimester_id)
+    .bind(section_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut by_name: HashMap<String, &KnownStudent> = HashMap::new();
+    let mut by_dni: HashMap<&str, &KnownStudent> = HashMap::new();
+    for s in &existing {
+        by_name.insert(normalize_text(&s.full_name), s);
+        if let Some(dni) = s.dni.as_deref() {
+            by_dni.insert(dni, s);
+        }
+    }
+
+    let mut result = StudentImportResult {
+        dry_run: true,
+        atomic: false,
+        applied: false,
+        encoding: None,
+        imported: 0,
+        successes: Vec::new(),
+        to_create: Vec::new(),
+        duplicates: Vec::new(),
+        homonyms: Vec::new(),
+        errors,
+    };
+    let mut seen_names: HashMap<String, usize> = HashMap::new();
+    let mut seen_dnis: HashMap<String, usize> = HashMap::new();
+
+    for entry in entries {
+        let key = normalize_text(&entry.full_name);
+        let duplicate = |existing: &KnownStudent, reason: &str| StudentImportDuplicate {
+            row: entry.row,
+            full_name: entry.full_name.clone(),
+            existing_student_id: Some(existing.id),
+            reason: format!("{} que '{}'", reason, existing.full_name),
+        };
+
+        if let Some(s) = entry.dni.as_deref().and_then(|d| by_dni.get(d)) {
+            result
+                .duplicates
+                .push(duplicate(s, "Ya está en la sección con el mismo DNI"));
+            continue;
+        }
+        if let Some(s) = by_name.get(&key) {
+            result
+                .duplicates
+                .push(duplicate(s, "Ya está en la sección con el mismo nombre"));
+            continue;
+        }
+        let repeated = seen_names
+            .get(&key)
+            .or_else(|| entry.dni.as_ref().and_then(|d| seen_dnis.get(d.as_str())));
+        if let Some(first_row) = repeated {
+            result.duplicates.push(StudentImportDuplicate {
+                row: entry.row,
+                full_name: entry.full_name.clone(),
+                existing_student_id: None,
+                reason: format!("Repetido en el archivo (fila {})", first_row),
+            });
+            continue;
+        }
+        seen_names.insert(key.clone(), entry.row);
+        if let Some(dni) = &entry.dni {
+            seen_dnis.insert(dni.clone(), entry.row);
+        }
+
+        for other in &others {
+            let other_dni: Option<String> = other.get("dni");
+            let matched_by = if entry.dni.is_some() && entry.dni == other_dni {
+                "dni"
+            } else if normalize_text(other.get("full_name")) == key {
+                "nombre"
+            } else {
+                continue;
+            };
+            result.homonyms.push(StudentHomonym {
+                row: entry.row,
+                full_name: entry.full_name.clone(),
+                student_id: other.get("id"),
+                existing_full_name: other.get("full_name"),
+                section_id: other.get("section_id"),
+                grade_number: other.get("grade_number"),
+                section_letter: other.get("letter"),
+                matched_by: matched_by.to_string(),
+            });
+        }
+        result.to_create.push(entry);
+    }
+
+    Ok(result)
+}
+
+pub async fn insert_roster_entry<'e, E: PgExecutor<'e>>(
+    executor: E,
+    section_id: i32,
+    entry: &RosterEntry,
+) -> Result<Student, sqlx::Error> {
+    sqlx::query_as::<_, Student>(&format!(
+        "INSERT INTO students (section_id, full_name, paternal_surname, maternal_surname,
+                               given_names, name_review_reason, dni, birth_date, gender)
+         VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9)
+         RETURNING {}",
+        STUDENT_COLUMNS
+    ))
+    .bind(section_id)
+    .bind(&entry.full_name)
+    .bind(&entry.names.paternal_surname)
+    .bind(&entry.names.maternal_surname)
+    .bind(&entry.names.given_names)
+    .bind(&entry.name_review_reason)
+    .bind(&entry.dni)
+    .bind(entry.birth_date)
+    .bind(&entry.gender)
+    .fetch_one(executor)
+    .await
+}
+
+pub fn insert_error_message(name: &str, e: &sqlx::Error) -> String {
+    if let sqlx::Error::Database(db_err) = e {
+        if db_err.code().as_deref() == Some("23505") {
+            format!("{}: ya existe en la sección", name)
+        } else {
+            format!("{}: {}", name, db_err.message())
+        }
+    } else {
+        format!("{}: {}", name, e)
+    }
+}
@@ -0,0 +1,444 @@
+use crate::appeals::routes::change_evaluation_value;
+use crate::basic::students::models::*;
+use sqlx::{PgConnection, Row};
+
+const MERGE_REASON: &str = "Fusión de registros duplicados";
+
+pub enum MergeError {
+    NotFound,
+    // La fusión no es posible sin intervención (cuentas o DNIs distintos, otro bimestre)
+    Conflict(String),
+    Db(sqlx::Error),
+}
+
+impl From<sqlx::Error> for MergeError {
+    fn from(e: sqlx::Error) -> Self {
+        MergeError::Db(e)
+    }
+}
+
+struct MergeRow {
+    user_id: Option<i32>,
+    dni: Option<String>,
+    bimester_id: i32,
+    snapshot: serde_json::Value,
+    // Campos opcionales del alumno que se completan desde el duplicado
+    optional: Vec<(&'static str, bool)>,
+}
+
+const FILLABLE_FIELDS: [&str; 6] = [
+    "dni",
+    "birth_date",
+    "gender",
+    "paternal_surname",
+    "maternal_surname",
+    "given_names",
+];
+
+async fn lock_student(conn: &mut PgConnection, student_id: i32) -> Result<MergeRow, MergeError> {
+    let row = sqlx::query(
+        r#"
+        SELECT s.user_id, s.dni, g.bimester_id, to_jsonb(s) AS snapshot,
+               s.dni IS NOT NULL AS has_dni,
+               s.birth_date IS NOT NULL AS has_birth_date,
+               s.gender IS NOT NULL AS has_gender,
+               s.paternal_surname IS NOT NULL AS has_paternal_surname,
+               s.maternal_surname IS NOT NULL AS has_maternal_surname,
+               s.given_names IS NOT NULL AS has_given_names
+        FROM students s
+        JOIN sections sec ON sec.id = s.section_id
+        JOIN grades g ON g.id = sec.grade_id
+        WHERE s.id = $1
+        FOR UPDATE OF s
+        "#,
+    )
+    .bind(student_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(MergeError::NotFound)?;
+
+    Ok(MergeRow {
+        user_id: row.get("user_id"),
+        dni: row.get("dni"),
+        bimester_id: row.get("bimester_id"),
+        snapshot: row.get("snapshot"),
+        optional: FILLABLE_FIELDS
+            .iter()
+            .map(|f| (*f, row.get::<bool, _>(format!("has_{}", f).as_str())))
+            .collect(),
+    })
+}
+
+fn takes_duplicate(
+    policy: MergeConflictPolicy,
+    surviving_updated: chrono::NaiveDateTime,
+    duplicate_updated: chrono::NaiveDateTime,
+) -> bool {
+    match policy {
+        MergeConflictPolicy::Surviving => false,
+        MergeConflictPolicy::Duplicate => true,
+        MergeConflictPolicy::Latest => duplicate_updated > surviving_updated,
+    }
+}
+
+// Celdas de evaluación: los conflictos se resuelven uno a uno para conservar el
+// historial y los reclamos de la celda que desaparece
+async fn merge_evaluation_items(
+    conn: &mut PgConnection,
+    surviving_id: i32,
+    duplicate_id: i32,
+    policy: MergeConflictPolicy,
+    admin_id: i32,
+    summary: &mut StudentMergeSummary,
+) -> Result<(), sqlx::Error> {
+    let conflicts = sqlx::query(
+        r#"
+        SELECT a.id AS surviving_item_id, b.id AS duplicate_item_id,
+               a.session_id, a.competency_id, a.ability_id, a.criterion_id, a.product_id,
+               a.value::text AS surviving_value, b.value::text AS duplicate_value,
+               a.updated_at AS surviving_updated, b.updated_at AS duplicate_updated,
+               b.observation AS duplicate_observation
+        FROM evaluation_items a
+        JOIN evaluation_items b
+          ON b.session_id = a.session_id AND b.competency_id = a.competency_id
+         AND b.ability_id = a.ability_id AND b.criterion_id = a.criterion_id
+         AND b.product_id = a.product_id
+        WHERE a.student_id = $1 AND b.student_id = $2
+        ORDER BY a.session_id, a.competency_id, a.ability_id, a.criterion_id, a.product_id
+        "#,
+    )
+    .bind(surviving_id)
+    .bind(duplicate_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for row in &conflicts {
+        let surviving_item: i32 = row.get("surviving_item_id");
+        let duplicate_item: i32 = row.get("duplicate_item_id");
+        let surviving_value: String = row.get("surviving_value");
+        let duplicate_value: String = row.get("duplicate_value");
+        let take = takes_duplicate(
+            policy,
+            row.get("surviving_updated"),
+            row.get("duplicate_updated"),
+        );
+
+        if take {
+            if surviving_value != duplicate_value {
+                change_evaluation_value(
+                    &mut *conn,
+                    surviving_item,
+                    &duplicate_value,
+                    admin_id,
+                    None,
+                    Some(MERGE_REASON),
+                )
+                .await?;
+            }
+            if let Some(obs) = row.get::<Option<String>, _>("duplicate_observation") {
+                sqlx::query("UPDATE evaluation_items SET observation = $1 WHERE id = $2")
+                    .bind(obs)
+                    .bind(surviving_item)
+                    .execute(&mut *conn)
+                    .await?;
+            }
+            summary.evaluation_items.taken_from_duplicate += 1;
+        }
+
+        // Solo puede quedar un reclamo pendiente por celda
+        sqlx::query(
+            r#"
+            UPDATE grade_appeals
+            SET status = 'withdrawn', resolved_at = NOW(), response = $3
+            WHERE evaluation_item_id = $2 AND status = 'pending'
+              AND EXISTS (
+                  SELECT 1 FROM grade_appeals
+                  WHERE evaluation_item_id = $1 AND status = 'pending'
+              )
+            "#,
+        )
+        .bind(surviving_item)
+        .bind(duplicate_item)
+        .bind(MERGE_REASON)
+        .execute(&mut *conn)
+        .await?;
+        sqlx::query(
+            "UPDATE grade_appeals SET evaluation_item_id = $1 WHERE evaluation_item_id = $2",
+        )
+        .bind(surviving_item)
+        .bind(duplicate_item)
+        .execute(&mut *conn)
+        .await?;
+        sqlx::query(
+            "UPDATE evaluation_item_history SET evaluation_item_id = $1 WHERE evaluation_item_id = $2",
+        )
+        .bind(surviving_item)
+        .bind(duplicate_item)
+        .execute(&mut *conn)
+        .await?;
+        sqlx::query("DELETE FROM evaluation_items WHERE id = $1")
+            .bind(duplicate_item)
+            .execute(&mut *conn)
+            .await?;
+
+        summary.evaluation_conflicts.push(EvaluationMergeConflict {
+            session_id: row.get("session_id"),
+            competency_id: row.get("competency_id"),
+            ability_id: row.get("ability_id"),
+            criterion_id: row.get("criterion_id"),
+            product_id: row.get("product_id"),
+            surviving_value,
+            duplicate_value,
+            takes_duplicate: take,
+        });
+    }
+    summary.evaluation_items.conflicts = conflicts.len() as u64;
+
+    summary.evaluation_items.moved =
+        sqlx::query("UPDATE evaluation_items SET student_id = $1 WHERE student_id = $2")
+            .bind(surviving_id)
+            .bind(duplicate_id)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected();
+    summary.appeals_moved =
+        sqlx::query("UPDATE grade_appeals SET student_id = $1 WHERE student_id = $2")
+            .bind(surviving_id)
+            .bind(duplicate_id)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected();
+    Ok(())
+}
+
+// Tablas con una fila por alumno y clave: se copia el valor ganador sobre la fila
+// del registro que sobrevive, se borra la del duplicado y se mueve el resto
+async fn merge_keyed_rows(
+    conn: &mut PgConnection,
+    table: &str,
+    key: &str,
+    copied: &str,
+    surviving_id: i32,
+    duplicate_id: i32,
+    policy: MergeConflictPolicy,
+) -> Result<MergeCounts, sqlx::Error> {
+    let taken = sqlx::query(&format!(
+        r#"
+        UPDATE {table} a SET ({copied}) = (SELECT {copied} FROM {table} WHERE id = b.id)
+        FROM {table} b
+        WHERE a.student_id = $1 AND b.student_id = $2 AND ({join})
+          AND ($3 = 'keep_duplicate' OR ($3 = 'keep_latest' AND b.updated_at > a.updated_at))
+        "#,
+        join = join_condition(key),
+    ))
+    .bind(surviving_id)
+    .bind(duplicate_id)
+    .bind(policy.as_str())
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    let conflicts = sqlx::query(&format!(
+        r#"
+        DELETE FROM {table} b USING {table} a
+        WHERE a.student_id = $1 AND b.student_id = $2 AND ({join})
+        "#,
+        join = join_condition(key),
+    ))
+    .bind(surviving_id)
+    .bind(duplicate_id)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    let moved = sqlx::query(&format!(
+        "UPDATE {table} SET student_id = $1 WHERE student_id = $2"
+    ))
+    .bind(surviving_id)
+    .bind(duplicate_id)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    Ok(MergeCounts {
+        moved,
+        conflicts,
+        taken_from_duplicate: taken,
+    })
+}
+
+fn join_condition(key: &str) -> String {
+    key.split(", ")
+        .map(|k| format!("b.{k} = a.{k}"))
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
+
+// Fusiona el registro duplicado en el que sobrevive y lo elimina. Todo ocurre en la
+// conexión recibida: quien llama decide si confirmar o, en la vista previa, deshacer.
+pub async fn merge_students(
+    conn: &mut PgConnection,
+    input: &StudentMergeIn,
+    admin_id: i32,
+) -> Result<(i32, StudentMergeSummary), MergeError> {
+    let surviving_id = input.surviving_student_id;
+    let duplicate_id = input.duplicate_student_id;
+    let policy = input.conflict_policy;
+
+    // Se bloquean siempre en el mismo orden para no cruzarse con otra fusión
+    let (surviving, duplicate) = if surviving_id < duplicate_id {
+        let s = lock_student(conn, surviving_id).await?;
+        (s, lock_student(conn, duplicate_id).await?)
+    } else {
+        let d = lock_student(conn, duplicate_id).await?;
+        (lock_student(conn, surviving_id).await?, d)
+    };
+
+    if surviving.bimester_id != duplicate.bimester_id {
+        return Err(MergeError::Conflict(
+            "Los registros pertenecen a bimestres distintos".to_string(),
+        ));
+    }
+    if let (Some(a), Some(b)) = (surviving.user_id, duplicate.user_id) {
+        if a != b {
+            return Err(MergeError::Conflict(
+                "Los registros están vinculados a cuentas distintas".to_string(),
+            ));
+        }
+    }
+    if let (Some(a), Some(b)) = (&surviving.dni, &duplicate.dni) {
+        if a != b {
+            return Err(MergeError::Conflict(format!(
+                "Los registros tienen DNIs distintos ({} y {})",
+                a, b
+            )));
+        }
+    }
+
+    let mut summary = StudentMergeSummary::default();
+    merge_evaluation_items(
+        conn,
+        surviving_id,
+        duplicate_id,
+        policy,
+        admin_id,
+        &mut summary,
+    )
+    .await?;
+    summary.attendance = merge_keyed_rows(
+        conn,
+        "attendance_records",
+        "session_id",
+        "status, note, updated_at",
+        surviving_id,
+        duplicate_id,
+        policy,
+    )
+    .await?;
+    summary.conclusions = merge_keyed_rows(
+        conn,
+        "descriptive_conclusions",
+        "bimester_id, competency_name",
+        "level, draft_text, text, status, generated_at, updated_at, approved_by_user_id, approved_at",
+        surviving_id,
+        duplicate_id,
+        policy,
+    )
+    .await?;
+
+    summary.profile_links_moved = sqlx::query(
+        r#"
+        INSERT INTO student_profile_links (student_id, user_id, linked_by_method, linked_at)
+        SELECT $1, user_id, linked_by_method, linked_at
+        FROM student_profile_links WHERE student_id = $2
+        ON CONFLICT (student_id, user_id) DO NOTHING
+        "#,
+    )
+    .bind(surviving_id)
+    .bind(duplicate_id)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    sqlx::query(
+        r#"
+        INSERT INTO link_review_rejections (user_id, student_id, rejected_by_user_id, reason, rejected_at)
+        SELECT user_id, $1, rejected_by_user_id, reason, rejected_at
+        FROM link_review_rejections WHERE student_id = $2
+        ON CONFLICT (user_id, student_id) DO NOTHING
+        "#,
+    )
+    .bind(surviving_id)
+    .bind(duplicate_id)
+    .execute(&mut *conn)
+    .await?;
+    summary.guardian_invitations_moved =
+        sqlx::query("UPDATE guardian_invitations SET student_id = $1 WHERE student_id = $2")
+            .bind(surviving_id)
+            .bind(duplicate_id)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected();
+
+    // El duplicado se elimina antes de pasar su cuenta y DNI, para no chocar con
+    // restricciones de unicidad sobre students
+    sqlx::query("DELETE FROM students WHERE id = $1")
+        .bind(duplicate_id)
+        .execute(&mut *conn)
+        .await?;
+    // La matrícula del duplicado se borra en cascada y, con ella, su learner si quedó
+    // sin matrículas (trg_enrollments_delete_orphan_learner); así el trigger de
+    // students puede pasar la cuenta y el DNI al learner del que sobrevive al
+    // actualizarlo abajo
+
+    if surviving.user_id.is_none() {
+        summary.user_id_transferred = duplicate.user_id;
+    }
+    summary.fields_filled = surviving
+        .optional
+        .iter()
+        .zip(&duplicate.optional)
+        .filter(|((_, has_surviving), (_, has_duplicate))| !has_surviving && *has_duplicate)
+        .map(|((field, _), _)| field.to_string())
+        .collect();
+    sqlx::query(
+        r#"
+        UPDATE students s SET
+            user_id = COALESCE(s.user_id, $2),
+            dni = COALESCE(s.dni, d.dni),
+            birth_date = COALESCE(s.birth_date, d.birth_date),
+            gender = COALESCE(s.gender, d.gender),
+            paternal_surname = COALESCE(s.paternal_surname, d.paternal_surname),
+            maternal_surname = COALESCE(s.maternal_surname, d.maternal_surname),
+            given_names = COALESCE(s.given_names, d.given_names)
+        FROM jsonb_populate_record(NULL::students, $3) d
+        WHERE s.id = $1
+        "#,
+    )
+    .bind(surviving_id)
+    .bind(duplicate.user_id)
+    .bind(&duplicate.snapshot)
+    .execute(&mut *conn)
+    .await?;
+
+    let summary_json = serde_json::to_value(&summary).unwrap_or_default();
+    let merge_id = sqlx::query_scalar::<_, i32>(
+        r#"
+        INSERT INTO student_merges
+        (surviving_student_id, duplicate_student_id, duplicate_snapshot, conflict_policy,
+         summary, reason, merged_by_user_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id
+        "#,
+    )
+    .bind(surviving_id)
+    .bind(duplicate_id)
+    .bind(&duplicate.snapshot)
+    .bind(policy.as_str())
+    .bind(summary_json)
+    .bind(&input.reason)
+    .bind(admin_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok((merge_id, summary))
+}
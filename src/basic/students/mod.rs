@@ -1,2 +1,6 @@
+pub mod import;
+pub mod merge;
 pub mod models;
-pub mod routes;
\ No newline at end of file
+pub mod names;
+pub mod roster;
+pub mod routes;
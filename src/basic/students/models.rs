@@ -1,3 +1,5 @@
+use crate::basic::students::names::NameParts;
+use crate::basic::students::roster::RosterEntry;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
@@ -8,10 +10,8 @@ pub struct Section {
     pub letter: String,
 }
 
-#[derive(Serialize, Deserialize, FromRow)]
-pub struct NewName {
-    pub full_name: String,
-}
+pub const STUDENT_COLUMNS: &str = "id, section_id, full_name, user_id, dni, birth_date, gender, \
+     paternal_surname, maternal_surname, given_names, name_review_reason";
 
 #[derive(Serialize, FromRow)]
 pub struct Student {
@@ -20,6 +20,23 @@ pub struct Student {
     pub full_name: String,
     pub user_id: Option<i32>,
     pub dni: Option<String>,
+    pub birth_date: Option<chrono::NaiveDate>,
+    pub gender: Option<String>,
+    pub paternal_surname: Option<String>,
+    pub maternal_surname: Option<String>,
+    pub given_names: Option<String>,
+    pub name_review_reason: Option<String>,
+}
+
+// Se puede enviar el nombre por partes (preferido) o solo full_name
+#[derive(Deserialize)]
+pub struct StudentIn {
+    pub full_name: Option<String>,
+    #[serde(flatten)]
+    pub names: NameParts,
+    pub dni: Option<String>,
+    pub birth_date: Option<chrono::NaiveDate>,
+    pub gender: Option<String>,
 }
 
 #[derive(Serialize, FromRow)]
@@ -40,6 +57,8 @@ pub struct StudentGradeItem {
 
 #[derive(Serialize)]
 pub struct StudentGradeSession {
+    pub session_id: i32,
+    pub bimester_id: i32,
     pub bimester_name: String,
     pub grade_number: i32,
     pub section_letter: String,
@@ -117,12 +136,17 @@ pub struct FlatGradeRow {
 
 #[derive(Serialize)]
 pub struct LinkedStudent {
+    pub enrollment_id: i32,
+    pub learner_id: i32,
     pub student_id: i32,
     pub full_name: String,
     pub section_letter: String,
     pub grade_number: i32,
     pub bimester_name: String,
     pub year: i32,
+    pub status: String,
+    pub start_date: Option<chrono::NaiveDate>,
+    pub end_date: Option<chrono::NaiveDate>,
 }
 
 #[derive(Serialize)]
@@ -180,5 +204,171 @@ pub struct ReniecResponse {
 
 #[derive(Deserialize)]
 pub struct BatchStudentsIn {
-    pub students: Vec<NewName>,
+    pub students: Vec<StudentIn>,
+}
+
+#[derive(Deserialize)]
+pub struct StudentGradesFilter {
+    pub bimester_id: Option<i32>,
+    pub session_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct StudentImportQuery {
+    pub dry_run: Option<bool>,
+    pub atomic: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct StudentImportDuplicate {
+    pub row: usize,
+    pub full_name: String,
+    pub existing_student_id: Option<i32>,
+    pub reason: String,
+}
+
+#[derive(Serialize)]
+pub struct StudentHomonym {
+    pub row: usize,
+    pub full_name: String,
+    pub student_id: i32,
+    pub existing_full_name: String,
+    pub section_id: i32,
+    pub grade_number: i32,
+    pub section_letter: String,
+    pub matched_by: String,
+}
+
+#[derive(Serialize)]
+pub struct StudentImportResult {
+    pub dry_run: bool,
+    pub atomic: bool,
+    pub applied: bool,
+    pub encoding: Option<&'static str>,
+    pub imported: usize,
+    pub successes: Vec<String>,
+    pub to_create: Vec<RosterEntry>,
+    pub duplicates: Vec<StudentImportDuplicate>,
+    pub homonyms: Vec<StudentHomonym>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct NameSplitQuery {
+    // Por defecto solo vista previa; hay que enviar dry_run=false para guardar
+    pub dry_run: Option<bool>,
+    // Confirma que los nombres sin coma están escritos con los apellidos primero
+    pub surnames_first: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct NameSplitChange {
+    pub student_id: i32,
+    pub full_name: String,
+    pub new_full_name: String,
+    #[serde(flatten)]
+    pub names: NameParts,
+}
+
+#[derive(Serialize)]
+pub struct NameReviewItem {
+    pub student_id: i32,
+    pub full_name: String,
+    pub section_id: i32,
+    pub grade_number: i32,
+    pub section_letter: String,
+    pub reason: String,
+    // Mejor propuesta de la heurística, para confirmar o corregir
+    pub proposed: NameParts,
+}
+
+#[derive(Serialize)]
+pub struct NameSplitResult {
+    pub dry_run: bool,
+    pub processed: usize,
+    pub split: Vec<NameSplitChange>,
+    pub needs_review: Vec<NameReviewItem>,
+}
+
+// Qué valor gana cuando ambos registros tienen la misma celda, asistencia o conclusión
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum MergeConflictPolicy {
+    #[default]
+    #[serde(rename = "keep_surviving")]
+    Surviving,
+    #[serde(rename = "keep_duplicate")]
+    Duplicate,
+    #[serde(rename = "keep_latest")]
+    Latest,
+}
+
+impl MergeConflictPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MergeConflictPolicy::Surviving => "keep_surviving",
+            MergeConflictPolicy::Duplicate => "keep_duplicate",
+            MergeConflictPolicy::Latest => "keep_latest",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct StudentMergeQuery {
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct StudentMergeIn {
+    pub surviving_student_id: i32,
+    pub duplicate_student_id: i32,
+    #[serde(default)]
+    pub conflict_policy: MergeConflictPolicy,
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct EvaluationMergeConflict {
+    pub session_id: i32,
+    pub competency_id: i32,
+    pub ability_id: i32,
+    pub criterion_id: i32,
+    pub product_id: i32,
+    pub surviving_value: String,
+    pub duplicate_value: String,
+    // true si la celda del registro que sobrevive toma el valor del duplicado
+    pub takes_duplicate: bool,
+}
+
+#[derive(Serialize, Default)]
+pub struct MergeCounts {
+    pub moved: u64,
+    pub conflicts: u64,
+    // Conflictos resueltos con el valor del duplicado
+    pub taken_from_duplicate: u64,
+}
+
+#[derive(Serialize, Default)]
+pub struct StudentMergeSummary {
+    pub evaluation_items: MergeCounts,
+    pub evaluation_conflicts: Vec<EvaluationMergeConflict>,
+    pub attendance: MergeCounts,
+    pub conclusions: MergeCounts,
+    pub appeals_moved: u64,
+    pub profile_links_moved: u64,
+    pub guardian_invitations_moved: u64,
+    // Cuenta del duplicado que pasa al registro que sobrevive
+    pub user_id_transferred: Option<i32>,
+    // Datos que el registro que sobrevive no tenía y toma del duplicado
+    pub fields_filled: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct StudentMergeResult {
+    pub dry_run: bool,
+    pub merge_id: Option<i32>,
+    pub surviving_student_id: i32,
+    pub duplicate_student_id: i32,
+    pub conflict_policy: MergeConflictPolicy,
+    #[serde(flatten)]
+    pub summary: StudentMergeSummary,
 }
@@ -0,0 +1,243 @@
+use crate::imports::table::normalize_text;
+use serde::{Deserialize, Serialize};
+
+// Partículas que forman parte del apellido siguiente: "DE LA CRUZ", "DEL AGUILA"
+const SURNAME_PARTICLES: [&str; 8] = ["de", "del", "la", "las", "los", "y", "san", "santa"];
+
+// Nombres de pila frecuentes: si el nombre empieza con uno de ellos, probablemente
+// se escribió "Nombres Apellidos" y no en el orden de las nóminas
+const COMMON_GIVEN_NAMES: [&str; 40] = [
+    "jose",
+    "juan",
+    "luis",
+    "carlos",
+    "jorge",
+    "miguel",
+    "angel",
+    "jesus",
+    "pedro",
+    "david",
+    "daniel",
+    "diego",
+    "fernando",
+    "alejandro",
+    "sebastian",
+    "mateo",
+    "santiago",
+    "gabriel",
+    "adrian",
+    "rodrigo",
+    "maria",
+    "ana",
+    "rosa",
+    "carmen",
+    "lucia",
+    "valeria",
+    "camila",
+    "sofia",
+    "andrea",
+    "daniela",
+    "fiorella",
+    "milagros",
+    "gabriela",
+    "alessandra",
+    "ximena",
+    "luz",
+    "flor",
+    "mariana",
+    "fatima",
+    "valentina",
+];
+
+// Nombre completo con el formato de las nóminas: "PATERNO MATERNO, NOMBRES"
+pub fn compose_full_name(paternal: &str, maternal: &str, given_names: &str) -> String {
+    let surnames = [paternal.trim(), maternal.trim()]
+        .iter()
+        .filter(|s| !s.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let given_names = given_names.trim();
+    match (surnames.is_empty(), given_names.is_empty()) {
+        (false, false) => format!("{}, {}", surnames, given_names),
+        (false, true) => surnames,
+        _ => given_names.to_string(),
+    }
+}
+
+pub fn compose_parts(parts: &NameParts) -> String {
+    compose_full_name(
+        parts.paternal_surname.as_deref().unwrap_or(""),
+        parts.maternal_surname.as_deref().unwrap_or(""),
+        parts.given_names.as_deref().unwrap_or(""),
+    )
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct NameParts {
+    pub paternal_surname: Option<String>,
+    pub maternal_surname: Option<String>,
+    pub given_names: Option<String>,
+}
+
+pub struct NameSplit {
+    pub parts: NameParts,
+    // Motivo por el que la división necesita revisión manual
+    pub ambiguity: Option<String>,
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!s.is_empty()).then_some(s)
+}
+
+// Agrupa las palabras de un texto de apellidos, uniendo las partículas con la palabra siguiente
+fn surname_groups(text: &str) -> Vec<String> {
+    let mut groups = Vec::new();
+    let mut pending: Vec<&str> = Vec::new();
+    for word in text.split_whitespace() {
+        pending.push(word);
+        if !SURNAME_PARTICLES.contains(&normalize_text(word).as_str()) {
+            groups.push(pending.join(" "));
+            pending.clear();
+        }
+    }
+    if !pending.is_empty() {
+        groups.push(pending.join(" "));
+    }
+    groups
+}
+
+// "PATERNO MATERNO" → (paterno, materno); con más grupos el reparto es dudoso
+fn split_surnames(text: &str) -> (Option<String>, Option<String>, bool) {
+    let groups = surname_groups(text);
+    match groups.len() {
+        0 => (None, None, true),
+        1 => (Some(groups[0].clone()), None, true),
+        2 => (Some(groups[0].clone()), Some(groups[1].clone()), true),
+        _ => (
+            Some(groups[0].clone()),
+            non_empty(&groups[1..].join(" ")),
+            false,
+        ),
+    }
+}
+
+// División heurística de un nombre libre. Con coma ("APELLIDOS, NOMBRES") el orden
+// es seguro. Sin coma solo se acepta si quien llama confirma que la nómina va con
+// los apellidos primero (surnames_first); si no, queda para revisión con la propuesta.
+pub fn split_full_name(full_name: &str, surnames_first: bool) -> NameSplit {
+    let ambiguous = |parts: NameParts, reason: &str| NameSplit {
+        parts,
+        ambiguity: Some(reason.to_string()),
+    };
+
+    if let Some((surnames, given)) = full_name.split_once(',') {
+        let (paternal, maternal, clear) = split_surnames(surnames);
+        let parts = NameParts {
+            paternal_surname: paternal,
+            maternal_surname: maternal,
+            given_names: non_empty(given),
+        };
+        if parts.paternal_surname.is_none() || parts.given_names.is_none() {
+            return ambiguous(parts, "Falta el apellido o el nombre a un lado de la coma");
+        }
+        if !clear {
+            return ambiguous(parts, "Más de dos apellidos antes de la coma");
+        }
+        return NameSplit {
+            parts,
+            ambiguity: None,
+        };
+    }
+
+    let groups = surname_groups(full_name);
+    if groups.len() < 3 {
+        let parts = match groups.as_slice() {
+            [paternal, given] => NameParts {
+                paternal_surname: Some(paternal.clone()),
+                given_names: Some(given.clone()),
+                ..Default::default()
+            },
+            _ => NameParts {
+                given_names: non_empty(full_name),
+                ..Default::default()
+            },
+        };
+        return ambiguous(
+            parts,
+            "Muy pocas palabras para distinguir apellidos y nombres",
+        );
+    }
+
+    let parts = NameParts {
+        paternal_surname: Some(groups[0].clone()),
+        maternal_surname: Some(groups[1].clone()),
+        given_names: non_empty(&groups[2..].join(" ")),
+    };
+    if COMMON_GIVEN_NAMES.contains(&normalize_text(&groups[0]).as_str()) {
+        // Se propone el orden inverso: los dos últimos grupos son los apellidos
+        let n = groups.len();
+        let reversed = NameParts {
+            paternal_surname: Some(groups[n - 2].clone()),
+            maternal_surname: Some(groups[n - 1].clone()),
+            given_names: non_empty(&groups[..n - 2].join(" ")),
+        };
+        return ambiguous(reversed, "Parece escrito como 'Nombres Apellidos'");
+    }
+    if groups.len() > 4 {
+        return ambiguous(
+            parts,
+            "Demasiadas palabras sin coma que separe los apellidos",
+        );
+    }
+    if !surnames_first {
+        return ambiguous(
+            parts,
+            "Sin coma no se puede confirmar si los apellidos van primero",
+        );
+    }
+    NameSplit {
+        parts,
+        ambiguity: None,
+    }
+}
+
+pub struct ResolvedName {
+    pub full_name: String,
+    pub parts: NameParts,
+    pub review_reason: Option<String>,
+}
+
+// Nombre a guardar: si llegan apellidos o nombres por separado, full_name se deriva
+// de ellos; si solo llega el nombre libre, se divide con la heurística y, cuando es
+// dudosa, se conserva el texto tal cual y se marca para revisión
+pub fn resolve_name(full_name: Option<&str>, parts: NameParts) -> Option<ResolvedName> {
+    let parts = NameParts {
+        paternal_surname: parts.paternal_surname.as_deref().and_then(non_empty),
+        maternal_surname: parts.maternal_surname.as_deref().and_then(non_empty),
+        given_names: parts.given_names.as_deref().and_then(non_empty),
+    };
+    if parts.paternal_surname.is_some() || parts.given_names.is_some() {
+        return Some(ResolvedName {
+            full_name: compose_parts(&parts),
+            parts,
+            review_reason: None,
+        });
+    }
+
+    let full_name = full_name.and_then(non_empty)?;
+    let split = split_full_name(&full_name, false);
+    Some(match split.ambiguity {
+        None => ResolvedName {
+            full_name: compose_parts(&split.parts),
+            parts: split.parts,
+            review_reason: None,
+        },
+        Some(reason) => ResolvedName {
+            full_name,
+            parts: NameParts::default(),
+            review_reason: Some(reason),
+        },
+    })
+}
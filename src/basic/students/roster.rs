@@ -0,0 +1,342 @@
+use crate::basic::students::models::StudentIn;
+use crate::basic::students::names::{resolve_name, NameParts, ResolvedName};
+use crate::imports::table::normalize_text;
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+
+pub const FULL_NAME_HEADERS: [&str; 8] = [
+    "full name",
+    "nombre completo",
+    "apellidos y nombres",
+    "nombres y apellidos",
+    "estudiante",
+    "alumno",
+    "nombre",
+    "nombres",
+];
+const PATERNAL_HEADERS: [&str; 5] = [
+    "apellido paterno",
+    "ap paterno",
+    "paterno",
+    "primer apellido",
+    "apellido 1",
+];
+const MATERNAL_HEADERS: [&str; 5] = [
+    "apellido materno",
+    "ap materno",
+    "materno",
+    "segundo apellido",
+    "apellido 2",
+];
+const GIVEN_NAMES_HEADERS: [&str; 3] = ["nombres", "nombre", "nombre s"];
+// Ambos apellidos en una sola columna, junto a otra de nombres
+const SURNAMES_HEADERS: [&str; 3] = ["apellidos", "apellido", "apellido s"];
+pub const DNI_HEADERS: [&str; 6] = [
+    "dni",
+    "documento",
+    "nro documento",
+    "n documento",
+    "numero de documento",
+    "nro de documento",
+];
+const BIRTH_DATE_HEADERS: [&str; 6] = [
+    "fecha de nacimiento",
+    "fecha nacimiento",
+    "f nacimiento",
+    "nacimiento",
+    "birth date",
+    "fec nac",
+];
+const GENDER_HEADERS: [&str; 4] = ["sexo", "genero", "gender", "sex"];
+const DOCUMENT_TYPE_HEADERS: [&str; 3] = ["tipo de documento", "tipo documento", "tipo doc"];
+const HEADER_SEARCH_ROWS: usize = 25;
+
+#[derive(Serialize, Clone)]
+pub struct RosterEntry {
+    pub row: usize,
+    pub full_name: String,
+    #[serde(flatten)]
+    pub names: NameParts,
+    pub name_review_reason: Option<String>,
+    pub dni: Option<String>,
+    pub birth_date: Option<NaiveDate>,
+    pub gender: Option<String>,
+}
+
+impl RosterEntry {
+    pub fn new(row: usize, name: ResolvedName) -> Self {
+        RosterEntry {
+            row,
+            full_name: name.full_name,
+            names: name.parts,
+            name_review_reason: name.review_reason,
+            dni: None,
+            birth_date: None,
+            gender: None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct RosterColumns {
+    full_name: Option<usize>,
+    surnames: Option<usize>,
+    paternal: Option<usize>,
+    maternal: Option<usize>,
+    given_names: Option<usize>,
+    dni: Option<usize>,
+    birth_date: Option<usize>,
+    gender: Option<usize>,
+}
+
+pub struct ParsedRoster {
+    pub entries: Vec<RosterEntry>,
+    pub errors: Vec<String>,
+}
+
+pub fn find_column(header: &[String], names: &[&str]) -> Option<usize> {
+    header.iter().position(|h| names.contains(&h.as_str()))
+}
+
+fn map_columns(header: &[String]) -> RosterColumns {
+    let mut cols = RosterColumns {
+        paternal: find_column(header, &PATERNAL_HEADERS),
+        maternal: find_column(header, &MATERNAL_HEADERS),
+        dni: find_column(header, &DNI_HEADERS),
+        birth_date: find_column(header, &BIRTH_DATE_HEADERS),
+        gender: find_column(header, &GENDER_HEADERS),
+        ..Default::default()
+    };
+    // Con columnas de apellidos, "nombres" son solo los nombres de pila
+    if cols.paternal.is_some() {
+        cols.given_names = find_column(header, &GIVEN_NAMES_HEADERS);
+    } else if let Some(surnames) = find_column(header, &SURNAMES_HEADERS) {
+        cols.surnames = Some(surnames);
+        cols.given_names = find_column(header, &GIVEN_NAMES_HEADERS);
+    } else {
+        cols.full_name = find_column(header, &FULL_NAME_HEADERS);
+    }
+    cols
+}
+
+// Excel suele quitar los ceros a la izquierda del DNI: se completan hasta 8 dígitos
+pub fn normalize_dni(raw: &str) -> Result<Option<String>, String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Ok(None);
+    }
+    let digits = raw.strip_suffix(".0").unwrap_or(raw);
+    if !digits.chars().all(|c| c.is_ascii_digit()) || digits.len() > 8 || digits.len() < 7 {
+        return Err(format!("DNI inválido '{}'", raw));
+    }
+    Ok(Some(format!("{:0>8}", digits)))
+}
+
+// Acepta dd/mm/aaaa, dd-mm-aaaa, aaaa-mm-dd y el número de serie de fecha de Excel
+pub fn parse_birth_date(raw: &str) -> Result<Option<NaiveDate>, String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Ok(None);
+    }
+    let date_part = raw.split_whitespace().next().unwrap_or(raw);
+    for fmt in ["%d/%m/%Y", "%d-%m-%Y", "%Y-%m-%d", "%Y/%m/%d", "%d.%m.%Y"] {
+        if let Ok(d) = NaiveDate::parse_from_str(date_part, fmt) {
+            return Ok(Some(d));
+        }
+    }
+    if let Ok(serial) = raw.parse::<f64>() {
+        if (1.0..100000.0).contains(&serial) {
+            let base = NaiveDate::from_ymd_opt(1899, 12, 30).unwrap();
+            return Ok(Some(base + Duration::days(serial as i64)));
+        }
+    }
+    Err(format!("Fecha de nacimiento inválida '{}'", raw))
+}
+
+pub fn normalize_gender(raw: &str) -> Result<Option<String>, String> {
+    match normalize_text(raw).as_str() {
+        "" => Ok(None),
+        "m" | "masculino" | "hombre" | "h" | "varon" => Ok(Some("M".to_string())),
+        "f" | "femenino" | "mujer" => Ok(Some("F".to_string())),
+        _ => Err(format!("Sexo inválido '{}'", raw.trim())),
+    }
+}
+
+// Alta individual o desde JSON: mismas validaciones que una fila del padrón
+pub fn entry_from_input(row: usize, input: &StudentIn) -> Result<RosterEntry, String> {
+    let name = resolve_name(input.full_name.as_deref(), input.names.clone())
+        .ok_or_else(|| "Nombre vacío".to_string())?;
+    let mut entry = RosterEntry::new(row, name);
+    entry.dni = normalize_dni(input.dni.as_deref().unwrap_or(""))?;
+    entry.birth_date = input.birth_date;
+    entry.gender = normalize_gender(input.gender.as_deref().unwrap_or(""))?;
+    Ok(entry)
+}
+
+// Interpreta un padrón tabular (CSV o Excel). La primera fila es el encabezado.
+// Los datos opcionales inválidos no descartan al alumno: se omiten y se informa.
+pub fn parse_roster(rows: &[Vec<String>]) -> Result<ParsedRoster, String> {
+    parse_roster_at(rows, 0)
+}
+
+// `skipped_rows` son las filas del archivo que preceden al encabezado, para que
+// los números de fila informados coincidan con el archivo original
+fn parse_roster_at(rows: &[Vec<String>], skipped_rows: usize) -> Result<ParsedRoster, String> {
+    let Some(header_row) = rows.first() else {
+        return Err("El archivo está vacío".to_string());
+    };
+    let header: Vec<String> = header_row.iter().map(|h| normalize_text(h)).collect();
+    let cols = map_columns(&header);
+    if cols.surnames.is_some() && cols.given_names.is_none() {
+        return Err(
+            "Con la columna 'apellidos' también debe haber una columna 'nombres'.".to_string(),
+        );
+    }
+    if cols.full_name.is_none() && cols.paternal.is_none() && cols.surnames.is_none() {
+        return Err(
+            "El archivo debe tener una columna 'full_name'/'nombre' o 'apellido paterno' y 'nombres'."
+                .to_string(),
+        );
+    }
+
+    let mut parsed = ParsedRoster {
+        entries: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    for (i, row) in rows.iter().enumerate().skip(1) {
+        let row_number = skipped_rows + i + 1;
+        let cell =
+            |col: Option<usize>| col.and_then(|c| row.get(c)).map(|v| v.trim()).unwrap_or("");
+
+        // "APELLIDOS, NOMBRES" deja a la heurística separar paterno y materno con
+        // el orden ya asegurado
+        let (full_name, parts) = match cols.surnames {
+            Some(_) => {
+                let (surnames, given) = (cell(cols.surnames), cell(cols.given_names));
+                let full_name = if surnames.is_empty() && given.is_empty() {
+                    String::new()
+                } else {
+                    format!("{}, {}", surnames, given)
+                };
+                (full_name, NameParts::default())
+            }
+            None => (
+                cell(cols.full_name).to_string(),
+                NameParts {
+                    paternal_surname: Some(cell(cols.paternal).to_string()),
+                    maternal_surname: Some(cell(cols.maternal).to_string()),
+                    given_names: Some(cell(cols.given_names).to_string()),
+                },
+            ),
+        };
+        let Some(name) = resolve_name(Some(&full_name), parts) else {
+            parsed
+                .errors
+                .push(format!("Fila {}: nombre vacío", row_number));
+            continue;
+        };
+
+        let mut entry = RosterEntry::new(row_number, name);
+        match normalize_dni(cell(cols.dni)) {
+            Ok(dni) => entry.dni = dni,
+            Err(e) => parsed
+                .errors
+                .push(format!("Fila {}: {}; se importa sin DNI", row_number, e)),
+        }
+        match parse_birth_date(cell(cols.birth_date)) {
+            Ok(d) => entry.birth_date = d,
+            Err(e) => parsed.errors.push(format!("Fila {}: {}", row_number, e)),
+        }
+        match normalize_gender(cell(cols.gender)) {
+            Ok(g) => entry.gender = g,
+            Err(e) => parsed.errors.push(format!("Fila {}: {}", row_number, e)),
+        }
+        parsed.entries.push(entry);
+    }
+
+    Ok(parsed)
+}
+
+// Nómina de matrícula de SIAGIE: el encabezado de la tabla llega después de varias
+// filas con datos de la institución, y al final suele haber totales o firmas.
+// Se ubica la fila con "apellido paterno" y "nombres", se descarta lo anterior y
+// la tabla termina en la primera fila sin apellidos ni nombres.
+pub fn parse_enrollment_list(rows: &[Vec<String>]) -> Result<ParsedRoster, String> {
+    let normalized =
+        |row: &Vec<String>| -> Vec<String> { row.iter().map(|c| normalize_text(c)).collect() };
+    let header_idx = rows
+        .iter()
+        .take(HEADER_SEARCH_ROWS)
+        .position(|row| {
+            let header = normalized(row);
+            find_column(&header, &PATERNAL_HEADERS).is_some()
+                && find_column(&header, &GIVEN_NAMES_HEADERS).is_some()
+        })
+        .ok_or_else(|| {
+            "No se encontró el encabezado de la nómina (columnas 'Apellido paterno' y 'Nombres')"
+                .to_string()
+        })?;
+
+    let header = normalized(&rows[header_idx]);
+    let cols = map_columns(&header);
+    let name_cols: Vec<usize> = [cols.paternal, cols.maternal, cols.given_names]
+        .into_iter()
+        .flatten()
+        .collect();
+    let document_type = find_column(&header, &DOCUMENT_TYPE_HEADERS);
+
+    let body: Vec<&Vec<String>> = rows[header_idx + 1..]
+        .iter()
+        .take_while(|row| {
+            name_cols
+                .iter()
+                .any(|c| row.get(*c).is_some_and(|v| !v.trim().is_empty()))
+        })
+        .collect();
+
+    // SIAGIE registra el sexo como H (hombre) / M (mujer). Solo se lee así si la
+    // columna trae alguna H y ninguna F; si no, M sigue siendo masculino.
+    let uses_h_m = cols.gender.is_some_and(|g| {
+        let values: Vec<String> = body
+            .iter()
+            .filter_map(|row| row.get(g))
+            .map(|v| normalize_text(v))
+            .collect();
+        values.iter().any(|v| v == "h") && !values.iter().any(|v| v == "f")
+    });
+
+    let mut notes = Vec::new();
+    let mut table = vec![rows[header_idx].clone()];
+    for (i, row) in body.iter().enumerate() {
+        let mut row = (*row).clone();
+        if let Some(g) = cols.gender.filter(|_| uses_h_m) {
+            if let Some(v) = row.get_mut(g) {
+                match normalize_text(v).as_str() {
+                    "h" => *v = "M".to_string(),
+                    "m" => *v = "F".to_string(),
+                    _ => {}
+                }
+            }
+        }
+        // Con otro tipo de documento (carné de extranjería, pasaporte) el número no es un DNI
+        if let (Some(t), Some(d)) = (document_type, cols.dni) {
+            let doc_type = row.get(t).map(|v| v.trim().to_string()).unwrap_or_default();
+            if !doc_type.is_empty() && normalize_text(&doc_type) != "dni" {
+                if let Some(v) = row.get_mut(d).filter(|v| !v.trim().is_empty()) {
+                    notes.push(format!(
+                        "Fila {}: documento de tipo '{}', se importa sin DNI",
+                        header_idx + i + 2,
+                        doc_type
+                    ));
+                    v.clear();
+                }
+            }
+        }
+        table.push(row);
+    }
+
+    let mut parsed = parse_roster_at(&table, header_idx)?;
+    parsed.errors.extend(notes);
+    Ok(parsed)
+}
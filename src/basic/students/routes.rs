@@ -1,34 +1,105 @@
+use crate::auth::guard::{current_user, require_role};
+use crate::auth::models::UserRole;
+use crate::basic::learners::models::LearnerRef;
+use crate::basic::students::import::{
+    insert_error_message, insert_roster_entry, plan_student_import,
+};
+use crate::basic::students::merge::{merge_students, MergeError};
 use crate::basic::students::models::*;
+use crate::basic::students::names::{compose_parts, resolve_name, split_full_name, NameParts};
+use crate::basic::students::roster::{
+    entry_from_input, parse_enrollment_list, parse_roster, RosterEntry,
+};
+use crate::imports::encoding::decode_text;
+use crate::imports::table::{parse_csv, read_table};
+use crate::imports::upload::read_upload;
 use crate::AppState;
-use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
 use actix_multipart::Multipart;
-use futures_util::StreamExt;
-use sqlx::Row;
+use actix_web::guard::GuardContext;
+use actix_web::http::header;
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
 
 #[post("/sections/{sec_id}/students")]
 pub async fn create_student(
     path: web::Path<i32>,
     data: web::Data<AppState>,
-    body: web::Json<NewName>,
+    body: web::Json<StudentIn>,
 ) -> impl Responder {
     let sec_id = path.into_inner();
-    let rec = sqlx::query_as::<_, Student>(
-        "INSERT INTO students (section_id, full_name) VALUES ($1,$2) RETURNING id, section_id, full_name, user_id, dni"
+    let entry = match entry_from_input(1, &body) {
+        Ok(e) => e,
+        Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    };
+
+    match insert_roster_entry(&data.pool, sec_id, &entry).await {
+        Ok(rec) => HttpResponse::Ok().json(rec),
+        Err(sqlx::Error::Database(db_err)) if db_err.code().as_deref() == Some("23505") => {
+            HttpResponse::Conflict().json(serde_json::json!({
+                "error": format!("{}: ya existe en la sección", entry.full_name)
+            }))
+        }
+        Err(e) => {
+            eprintln!("Error creating student: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+// Las peticiones multipart a /sections/{id}/students traen la nómina de matrícula
+fn is_multipart(ctx: &GuardContext) -> bool {
+    ctx.head()
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.to_lowercase().starts_with("multipart/form-data"))
+}
+
+#[post("/sections/{sec_id}/students", guard = "is_multipart")]
+pub async fn import_enrollment_list(
+    path: web::Path<i32>,
+    query: web::Query<StudentImportQuery>,
+    data: web::Data<AppState>,
+    mut payload: Multipart,
+) -> impl Responder {
+    let sec_id = path.into_inner();
+
+    let file = match read_upload(&mut payload).await {
+        Ok(f) => f,
+        Err(resp) => return resp,
+    };
+    let roster = match read_table(&file).and_then(|rows| parse_enrollment_list(&rows)) {
+        Ok(r) => r,
+        Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    };
+
+    if roster.entries.is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "La nómina no tiene estudiantes.",
+            "errors": roster.errors,
+        }));
+    }
+
+    run_student_import(
+        &data.pool,
+        sec_id,
+        &query,
+        roster.entries,
+        roster.errors,
+        None,
     )
-    .bind(sec_id)
-    .bind(&body.full_name)
-    .fetch_one(&data.pool)
     .await
-    .unwrap();
-    HttpResponse::Ok().json(rec)
 }
 
 #[get("/sections/{sec_id}/students")]
 pub async fn list_students(path: web::Path<i32>, data: web::Data<AppState>) -> impl Responder {
     let sec_id = path.into_inner();
-    let rows = sqlx::query_as::<_, Student>(
-        "SELECT id, section_id, full_name, user_id, dni FROM students WHERE section_id=$1 ORDER BY full_name",
-    )
+    let rows = sqlx::query_as::<_, Student>(&format!(
+        "SELECT {} FROM students WHERE section_id=$1
+         ORDER BY COALESCE(paternal_surname, full_name), maternal_surname, given_names, full_name",
+        STUDENT_COLUMNS
+    ))
     .bind(sec_id)
     .fetch_all(&data.pool)
     .await
@@ -36,22 +107,48 @@ pub async fn list_students(path: web::Path<i32>, data: web::Data<AppState>) -> i
     HttpResponse::Ok().json(rows)
 }
 
+// Los datos que no se envían (DNI, fecha de nacimiento, sexo) se conservan
 #[put("/students/{id}")]
 pub async fn update_student(
     path: web::Path<i32>,
     data: web::Data<AppState>,
-    body: web::Json<NewName>,
+    body: web::Json<StudentIn>,
 ) -> impl Responder {
     let id = path.into_inner();
-    let rec = sqlx::query_as::<_, Student>(
-        "UPDATE students SET full_name=$1 WHERE id=$2 RETURNING id, section_id, full_name, user_id, dni",
-    )
-    .bind(&body.full_name)
+    let entry = match entry_from_input(1, &body) {
+        Ok(e) => e,
+        Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    };
+
+    let rec = sqlx::query_as::<_, Student>(&format!(
+        "UPDATE students
+         SET full_name=$1, paternal_surname=$2, maternal_surname=$3, given_names=$4,
+             name_review_reason=$5, dni=COALESCE($6, dni),
+             birth_date=COALESCE($7, birth_date), gender=COALESCE($8, gender)
+         WHERE id=$9
+         RETURNING {}",
+        STUDENT_COLUMNS
+    ))
+    .bind(&entry.full_name)
+    .bind(&entry.names.paternal_surname)
+    .bind(&entry.names.maternal_surname)
+    .bind(&entry.names.given_names)
+    .bind(&entry.name_review_reason)
+    .bind(&entry.dni)
+    .bind(entry.birth_date)
+    .bind(&entry.gender)
     .bind(id)
     .fetch_one(&data.pool)
-    .await
-    .unwrap();
-    HttpResponse::Ok().json(rec)
+    .await;
+
+    match rec {
+        Ok(rec) => HttpResponse::Ok().json(rec),
+        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("Estudiante no encontrado"),
+        Err(e) => {
+            eprintln!("Error updating student: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
 }
 
 #[delete("/students/{id}")]
@@ -65,235 +162,193 @@ pub async fn delete_student(path: web::Path<i32>, data: web::Data<AppState>) ->
     HttpResponse::NoContent().finish()
 }
 
+// Ejecuta una importación de padrón ya interpretada. Con dry_run solo informa qué
+// se crearía; con atomic todo se inserta en una transacción y cualquier error la revierte.
+async fn run_student_import(
+    pool: &PgPool,
+    sec_id: i32,
+    query: &StudentImportQuery,
+    entries: Vec<RosterEntry>,
+    errors: Vec<String>,
+    encoding: Option<&'static str>,
+) -> HttpResponse {
+    let mut result = match plan_student_import(pool, sec_id, entries, errors).await {
+        Ok(r) => r,
+        Err(sqlx::Error::RowNotFound) => {
+            return HttpResponse::NotFound().body("Sección no encontrada")
+        }
+        Err(e) => {
+            eprintln!("Error planning student import: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+    result.encoding = encoding;
+    result.atomic = query.atomic.unwrap_or(false);
+    if query.dry_run.unwrap_or(false) {
+        return HttpResponse::Ok().json(result);
+    }
+    result.dry_run = false;
+
+    if result.atomic {
+        if !result.errors.is_empty() {
+            return HttpResponse::UnprocessableEntity().json(result);
+        }
+        let mut tx = match pool.begin().await {
+            Ok(t) => t,
+            Err(e) => {
+                eprintln!("Error starting transaction: {:?}", e);
+                return HttpResponse::InternalServerError().body("Error en la base de datos");
+            }
+        };
+        let mut successes = Vec::new();
+        for entry in &result.to_create {
+            match insert_roster_entry(&mut *tx, sec_id, entry).await {
+                Ok(student) => successes.push(student.full_name),
+                Err(e) => {
+                    // Al descartar la transacción se revierten las filas ya insertadas
+                    result
+                        .errors
+                        .push(insert_error_message(&entry.full_name, &e));
+                    return HttpResponse::UnprocessableEntity().json(result);
+                }
+            }
+        }
+        if let Err(e) = tx.commit().await {
+            eprintln!("Error committing student import: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error aplicando la importación");
+        }
+        result.successes = successes;
+    } else {
+        for entry in &result.to_create {
+            match insert_roster_entry(pool, sec_id, entry).await {
+                Ok(student) => result.successes.push(student.full_name),
+                Err(e) => result
+                    .errors
+                    .push(insert_error_message(&entry.full_name, &e)),
+            }
+        }
+    }
+
+    result.imported = result.successes.len();
+    result.applied = true;
+    HttpResponse::Ok().json(result)
+}
+
 #[post("/sections/{sec_id}/students/import")]
 pub async fn import_students_json(
     path: web::Path<i32>,
-    data: web::Data<AppState>, // ← Cambia esto
+    query: web::Query<StudentImportQuery>,
+    data: web::Data<AppState>,
     body: web::Json<BatchStudentsIn>,
 ) -> impl Responder {
     let sec_id = path.into_inner();
-    let mut successes = Vec::new();
+    let mut entries = Vec::new();
     let mut errors = Vec::new();
 
-    for s in &body.students {
-        let name = s.full_name.trim();
-        if name.is_empty() {
-            errors.push(format!("Nombre vacío"));
-            continue;
-        }
-        let res = sqlx::query_as::<_, Student>(
-            "INSERT INTO students (section_id, full_name) VALUES ($1,$2) RETURNING id, section_id, full_name, user_id, dni",
-        )
-        .bind(sec_id)
-        .bind(name)
-        .fetch_one(&data.pool)  // ← Cambia esto a data.pool
-        .await;
-
-        match res {
-            Ok(student) => successes.push(student.full_name),
-            Err(e) => errors.push(format!("{}: {}", name, e)),
+    for (i, s) in body.students.iter().enumerate() {
+        match entry_from_input(i + 1, s) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => errors.push(format!("Fila {}: {}", i + 1, e)),
         }
     }
 
-    HttpResponse::Ok().json(serde_json::json!({
-        "imported": successes.len(),
-        "successes": successes,
-        "errors": errors,
-    }))
+    run_student_import(&data.pool, sec_id, &query, entries, errors, None).await
 }
 
 #[post("/sections/{sec_id}/students/import_csv")]
 pub async fn import_students_csv(
     path: web::Path<i32>,
+    query: web::Query<StudentImportQuery>,
     data: web::Data<AppState>,
     mut payload: Multipart,
 ) -> impl Responder {
     let sec_id = path.into_inner();
-    let mut csv_data = Vec::new();
-
-    // Leer el archivo del multipart
-    while let Some(field_result) = payload.next().await {
-        match field_result {
-            Ok(mut field) => {
-                while let Some(chunk_result) = field.next().await {
-                    match chunk_result {
-                        Ok(chunk) => csv_data.extend_from_slice(&chunk),
-                        Err(e) => {
-                            return HttpResponse::BadRequest().json(
-                                serde_json::json!({"error": format!("Error leyendo chunk: {}", e)}),
-                            );
-                        }
-                    }
-                }
-                break;
-            }
-            Err(e) => {
-                return HttpResponse::BadRequest()
-                    .json(serde_json::json!({"error": format!("Error leyendo campo: {}", e)}));
-            }
-        }
-    }
-
-    let content = String::from_utf8_lossy(&csv_data);
-    let lines: Vec<&str> = content
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
-        .collect();
-
-    if lines.len() < 2 {
-        return HttpResponse::BadRequest().json(
-            serde_json::json!({"error": "El archivo debe tener encabezado y al menos un alumno."}),
-        );
-    }
 
-    // Verificar que el header sea válido
-    let header = lines[0].to_lowercase();
-    if !header.contains("full_name") && !header.contains("nombre") {
-        return HttpResponse::BadRequest().json(
-            serde_json::json!({"error": "El archivo debe tener una columna llamada 'full_name' o 'nombre'."}),
-        );
-    }
-
-    let mut successes = Vec::new();
-    let mut errors = Vec::new();
-
-    // Procesar cada línea después del header
-    for (i, line) in lines.iter().enumerate().skip(1) {
-        let name = line.trim();
-
-        if name.is_empty() {
-            errors.push(format!("Fila {}: nombre vacío", i + 1));
-            continue;
-        }
+    let file = match read_upload(&mut payload).await {
+        Ok(f) => f,
+        Err(resp) => return resp,
+    };
 
-        // Guardar exactamente como viene (sin invertir)
-        let res = sqlx::query_as::<_, Student>(
-            "INSERT INTO students (section_id, full_name) VALUES ($1,$2) RETURNING id, section_id, full_name, user_id, dni",
-        )
-        .bind(sec_id)
-        .bind(name)
-        .fetch_one(&data.pool)
-        .await;
+    let decoded = decode_text(&file.bytes);
+    let roster = match parse_csv(&decoded.text).and_then(|rows| parse_roster(&rows)) {
+        Ok(r) => r,
+        Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    };
 
-        match res {
-            Ok(student) => successes.push(student.full_name),
-            Err(e) => {
-                let err_msg = if let sqlx::Error::Database(db_err) = &e {
-                    if db_err.code().as_deref() == Some("23505") {
-                        format!("{}: ya existe en la sección", name)
-                    } else {
-                        format!("{}: {}", name, db_err.message())
-                    }
-                } else {
-                    format!("{}: {}", name, e)
-                };
-                errors.push(err_msg);
-            }
-        }
+    if roster.entries.is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "El archivo debe tener encabezado y al menos un alumno.",
+            "errors": roster.errors,
+            "encoding": decoded.encoding,
+        }));
     }
 
-    HttpResponse::Ok().json(serde_json::json!({
-        "imported": successes.len(),
-        "successes": successes,
-        "errors": errors,
-    }))
+    run_student_import(
+        &data.pool,
+        sec_id,
+        &query,
+        roster.entries,
+        roster.errors,
+        Some(decoded.encoding),
+    )
+    .await
 }
 
 #[post("/sections/{sec_id}/students/import_txt")]
 pub async fn import_students_txt(
     path: web::Path<i32>,
+    query: web::Query<StudentImportQuery>,
     data: web::Data<AppState>,
     mut payload: Multipart,
 ) -> impl Responder {
     let sec_id = path.into_inner();
-    let mut txt_data = Vec::new();
-
-    // Leer el archivo del multipart con manejo de errores
-    while let Some(field_result) = payload.next().await {
-        match field_result {
-            Ok(mut field) => {
-                while let Some(chunk_result) = field.next().await {
-                    match chunk_result {
-                        Ok(chunk) => txt_data.extend_from_slice(&chunk),
-                        Err(e) => {
-                            return HttpResponse::BadRequest().json(
-                                serde_json::json!({"error": format!("Error leyendo chunk: {}", e)}),
-                            );
-                        }
-                    }
-                }
-                break;
-            }
-            Err(e) => {
-                return HttpResponse::BadRequest()
-                    .json(serde_json::json!({"error": format!("Error leyendo campo: {}", e)}));
-            }
-        }
-    }
 
-    if txt_data.is_empty() {
-        return HttpResponse::BadRequest()
-            .json(serde_json::json!({"error": "No se recibió ningún archivo o está vacío"}));
-    }
+    let file = match read_upload(&mut payload).await {
+        Ok(f) => f,
+        Err(resp) => return resp,
+    };
 
-    let content = String::from_utf8_lossy(&txt_data);
+    let decoded = decode_text(&file.bytes);
 
-    // Procesar líneas (sin invertir, mantener orden original)
-    let lines: Vec<String> = content
+    // Una línea por alumno, en el orden original
+    let entries: Vec<RosterEntry> = decoded
+        .text
         .lines()
-        .map(|l| l.trim().to_string())
-        .filter(|l| !l.is_empty())
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim()))
+        .filter(|(_, l)| !l.is_empty())
+        .filter_map(|(row, name)| {
+            resolve_name(Some(name), NameParts::default()).map(|n| RosterEntry::new(row, n))
+        })
         .collect();
 
-    if lines.is_empty() {
+    if entries.is_empty() {
         return HttpResponse::BadRequest().json(
             serde_json::json!({"error": "El archivo está vacío o no contiene nombres válidos."}),
         );
     }
 
-    let mut successes = Vec::new();
-    let mut errors = Vec::new();
-
-    for name in lines.iter() {
-        let res = sqlx::query_as::<_, Student>(
-            "INSERT INTO students (section_id, full_name) VALUES ($1,$2) RETURNING id, section_id, full_name, user_id, dni",
-        )
-        .bind(sec_id)
-        .bind(name)
-        .fetch_one(&data.pool)
-        .await;
-
-        match res {
-            Ok(student) => successes.push(student.full_name),
-            Err(e) => {
-                let err_msg = if let sqlx::Error::Database(db_err) = &e {
-                    if db_err.code().as_deref() == Some("23505") {
-                        format!("{}: ya existe en la sección", name)
-                    } else {
-                        format!("{}: {}", name, db_err.message())
-                    }
-                } else {
-                    format!("{}: {}", name, e)
-                };
-                errors.push(err_msg);
-            }
-        }
-    }
-
-    HttpResponse::Ok().json(serde_json::json!({
-        "imported": successes.len(),
-        "successes": successes,
-        "errors": errors,
-    }))
+    run_student_import(
+        &data.pool,
+        sec_id,
+        &query,
+        entries,
+        Vec::new(),
+        Some(decoded.encoding),
+    )
+    .await
 }
 
 #[get("/students/{user_id}/profile")]
 pub async fn get_student_profile(
     path: web::Path<i32>,
+    req: HttpRequest,
     data: web::Data<AppState>,
 ) -> impl Responder {
     let user_id = path.into_inner();
+    if let Err(resp) = authorize_student_access(&data.pool, &req, user_id).await {
+        return resp;
+    }
 
     // Obtener perfil
     let profile = sqlx::query(
@@ -365,50 +420,113 @@ pub async fn get_student_profile(
     }))
 }
 
-#[get("/students/{user_id}/enrollments")]
-pub async fn get_student_enrollments(
-    path: web::Path<i32>,
-    data: web::Data<AppState>,
-) -> impl Responder {
-    let user_id = path.into_inner();
-
+// Historial de matrículas de la persona, reunido a través de su learner aunque
+// las filas por sección no tengan la cuenta vinculada
+pub async fn fetch_student_enrollments(
+    pool: &PgPool,
+    learner: LearnerRef,
+) -> Result<Vec<LinkedStudent>, sqlx::Error> {
     let rows = sqlx::query(
         r#"
-        SELECT 
+        SELECT
+            e.id AS enrollment_id,
+            e.learner_id,
             s.id AS student_id,
             s.full_name,
             sec.letter,
             g.number AS grade_number,
             b.name AS bimester_name,
-            b.year
-        FROM students s
-        JOIN sections sec ON sec.id = s.section_id
+            b.year,
+            e.status,
+            e.start_date,
+            e.end_date
+        FROM enrollments e
+        JOIN learners l ON l.id = e.learner_id
+        JOIN students s ON s.id = e.student_id
+        JOIN sections sec ON sec.id = e.section_id
         JOIN grades g ON g.id = sec.grade_id
         JOIN bimesters b ON b.id = g.bimester_id
-        WHERE s.user_id = $1
+        WHERE ($1::int IS NULL OR l.user_id = $1 OR s.user_id = $1)
+          AND ($2::int IS NULL OR l.id = $2)
         ORDER BY b.year DESC, b.id DESC, g.number, sec.letter
         "#,
     )
-    .bind(user_id)
-    .fetch_all(&data.pool)
-    .await;
-
-    match rows {
-        Ok(data) => {
-            let enrollments: Vec<LinkedStudent> = data
-                .into_iter()
-                .map(|row| LinkedStudent {
-                    student_id: row.try_get("student_id").unwrap(),
-                    full_name: row.try_get("full_name").unwrap(),
-                    section_letter: row.try_get("letter").unwrap(),
-                    grade_number: row.try_get("grade_number").unwrap(),
-                    bimester_name: row.try_get("bimester_name").unwrap(),
-                    year: row.try_get("year").unwrap(),
-                })
-                .collect();
+    .bind(learner.user_id())
+    .bind(learner.learner_id())
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| LinkedStudent {
+            enrollment_id: row.try_get("enrollment_id").unwrap(),
+            learner_id: row.try_get("learner_id").unwrap(),
+            student_id: row.try_get("student_id").unwrap(),
+            full_name: row.try_get("full_name").unwrap(),
+            section_letter: row.try_get("letter").unwrap(),
+            grade_number: row.try_get("grade_number").unwrap(),
+            bimester_name: row.try_get("bimester_name").unwrap(),
+            year: row.try_get("year").unwrap(),
+            status: row.try_get("status").unwrap(),
+            start_date: row.try_get("start_date").unwrap(),
+            end_date: row.try_get("end_date").unwrap(),
+        })
+        .collect())
+}
 
-            HttpResponse::Ok().json(enrollments)
+// Quién puede ver el historial de un alumno: docentes y administradores, el propio
+// alumno y sus apoderados. Devuelve true si es personal del colegio.
+async fn authorize_student_access(
+    pool: &PgPool,
+    req: &HttpRequest,
+    student_user_id: i32,
+) -> Result<bool, HttpResponse> {
+    let user = current_user(pool, req).await?;
+    let allowed = match user.role {
+        UserRole::Docente | UserRole::Admin => return Ok(true),
+        UserRole::Alumno => user.id == student_user_id,
+        UserRole::Apoderado => {
+            match sqlx::query_scalar::<_, bool>(
+                "SELECT EXISTS(SELECT 1 FROM guardian_student_relationships
+                                WHERE guardian_user_id = $1 AND student_user_id = $2)",
+            )
+            .bind(user.id)
+            .bind(student_user_id)
+            .fetch_one(pool)
+            .await
+            {
+                Ok(b) => b,
+                Err(e) => {
+                    eprintln!("Error checking guardian relationship: {:?}", e);
+                    return Err(
+                        HttpResponse::InternalServerError().body("Error en la base de datos")
+                    );
+                }
+            }
         }
+    };
+    if allowed {
+        Ok(false)
+    } else {
+        Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Acceso denegado"
+        })))
+    }
+}
+
+#[get("/students/{user_id}/enrollments")]
+pub async fn get_student_enrollments(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    if let Err(resp) = authorize_student_access(&data.pool, &req, user_id).await {
+        return resp;
+    }
+
+    match fetch_student_enrollments(&data.pool, LearnerRef::User(user_id)).await {
+        Ok(enrollments) => HttpResponse::Ok().json(enrollments),
         Err(e) => {
             eprintln!("Error fetching enrollments: {:?}", e);
             HttpResponse::InternalServerError().body("Error al obtener matrículas")
@@ -416,16 +534,19 @@ pub async fn get_student_enrollments(
     }
 }
 
-#[get("/students/{user_id}/grades")]
-pub async fn get_student_grades(path: web::Path<i32>, data: web::Data<AppState>) -> impl Responder {
-    let user_id = path.into_inner();
-
+pub async fn fetch_student_grades(
+    pool: &PgPool,
+    learner: LearnerRef,
+    filter: &StudentGradesFilter,
+    published_only: bool,
+) -> Result<Vec<StudentGradeSession>, sqlx::Error> {
     let rows = sqlx::query(
         r#"
         SELECT
             s.full_name,
             sec.letter AS section_letter,
             g.number AS grade_number,
+            b.id AS bimester_id,
             b.name AS bimester_name,
             sess.id AS session_id,
             sess.title AS session_title,
@@ -439,6 +560,8 @@ pub async fn get_student_grades(path: web::Path<i32>, data: web::Data<AppState>)
             ei.observation,
             ei.updated_at
         FROM evaluation_items ei
+        JOIN enrollments e ON e.student_id = ei.student_id
+        JOIN learners l ON l.id = e.learner_id
         JOIN students s ON s.id = ei.student_id
         JOIN sessions sess ON sess.id = ei.session_id
         JOIN sections sec ON sec.id = sess.section_id
@@ -447,22 +570,24 @@ pub async fn get_student_grades(path: web::Path<i32>, data: web::Data<AppState>)
         JOIN competencies comp ON comp.id = ei.competency_id
         JOIN abilities abl ON abl.id = ei.ability_id
         JOIN criteria crt ON crt.id = ei.criterion_id
-        WHERE s.user_id = $1
+        WHERE ($1::int IS NULL OR l.user_id = $1 OR s.user_id = $1)
+          AND ($5::int IS NULL OR l.id = $5)
+          AND ($2::int IS NULL OR b.id = $2)
+          AND ($3::int IS NULL OR sess.id = $3)
+          AND (NOT $4 OR EXISTS (
+              SELECT 1 FROM grade_publications gp
+              WHERE gp.session_id = ei.session_id AND gp.competency_id = ei.competency_id
+          ))
         ORDER BY b.id, sess.number, comp.number, abl.number, crt.number
         "#,
     )
-    .bind(user_id)
-    .fetch_all(&data.pool)
-    .await;
-
-    if let Err(e) = rows {
-        eprintln!("SQL ERROR: {:?}", e);
-        return HttpResponse::InternalServerError().body("Error al obtener notas");
-    }
-
-    let rows = rows.unwrap();
-
-    use std::collections::HashMap;
+    .bind(learner.user_id())
+    .bind(filter.bimester_id)
+    .bind(filter.session_id)
+    .bind(published_only)
+    .bind(learner.learner_id())
+    .fetch_all(pool)
+    .await?;
 
     // Agrupadores
     let mut sessions_map: HashMap<i32, StudentGradeSession> = HashMap::new();
@@ -478,6 +603,8 @@ pub async fn get_student_grades(path: web::Path<i32>, data: web::Data<AppState>)
         sessions_map
             .entry(session_id)
             .or_insert_with(|| StudentGradeSession {
+                session_id,
+                bimester_id: row.try_get("bimester_id").unwrap(),
                 bimester_name: row.try_get("bimester_name").unwrap(),
                 grade_number: row.try_get("grade_number").unwrap(),
                 section_letter: row.try_get("section_letter").unwrap(),
@@ -539,11 +666,251 @@ pub async fn get_student_grades(path: web::Path<i32>, data: web::Data<AppState>)
     // Orden por título
     sessions.sort_by(|a, b| a.session_title.cmp(&b.session_title));
 
-    HttpResponse::Ok().json(sessions)
+    Ok(sessions)
+}
+
+#[get("/students/{user_id}/grades")]
+pub async fn get_student_grades(
+    path: web::Path<i32>,
+    query: web::Query<StudentGradesFilter>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+
+    // Docentes y administradores ven también las notas aún no publicadas
+    let published_only = match authorize_student_access(&data.pool, &req, user_id).await {
+        Ok(is_staff) => !is_staff,
+        Err(resp) => return resp,
+    };
+
+    match fetch_student_grades(
+        &data.pool,
+        LearnerRef::User(user_id),
+        &query,
+        published_only,
+    )
+    .await
+    {
+        Ok(sessions) => HttpResponse::Ok().json(sessions),
+        Err(e) => {
+            eprintln!("SQL ERROR: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al obtener notas")
+        }
+    }
+}
+
+const NAME_REVIEW_SELECT: &str = r#"
+    SELECT s.id, s.full_name, s.name_review_reason, sec.id AS section_id,
+           sec.letter, g.number AS grade_number
+    FROM students s
+    JOIN sections sec ON sec.id = s.section_id
+    JOIN grades g ON g.id = sec.grade_id
+"#;
+
+fn name_review_item(row: &sqlx::postgres::PgRow, reason: String) -> NameReviewItem {
+    let full_name: String = row.get("full_name");
+    NameReviewItem {
+        student_id: row.get("id"),
+        proposed: split_full_name(&full_name, true).parts,
+        full_name,
+        section_id: row.get("section_id"),
+        grade_number: row.get("grade_number"),
+        section_letter: row.get("letter"),
+        reason,
+    }
+}
+
+// Migración de nombres libres a nombres por partes. Los casos claros se guardan
+// (y full_name pasa al formato derivado); los dudosos quedan en la lista de revisión.
+// Sin dry_run=false solo devuelve la vista previa.
+#[post("/admin/students/split-names")]
+pub async fn split_student_names(
+    req: HttpRequest,
+    query: web::Query<NameSplitQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Admin]).await {
+        return resp;
+    }
+    let dry_run = query.dry_run.unwrap_or(true);
+
+    let rows = match sqlx::query(&format!(
+        "{} WHERE s.paternal_surname IS NULL AND s.given_names IS NULL ORDER BY s.id",
+        NAME_REVIEW_SELECT
+    ))
+    .fetch_all(&data.pool)
+    .await
+    {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Error fetching student names: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+
+    let mut result = NameSplitResult {
+        dry_run,
+        processed: rows.len(),
+        split: Vec::new(),
+        needs_review: Vec::new(),
+    };
+    for row in &rows {
+        let full_name: String = row.get("full_name");
+        let split = split_full_name(&full_name, query.surnames_first.unwrap_or(false));
+        match split.ambiguity {
+            None => result.split.push(NameSplitChange {
+                student_id: row.get("id"),
+                new_full_name: compose_parts(&split.parts),
+                full_name,
+                names: split.parts,
+            }),
+            Some(reason) => result.needs_review.push(name_review_item(row, reason)),
+        }
+    }
+    if dry_run {
+        return HttpResponse::Ok().json(result);
+    }
+
+    let mut tx = match data.pool.begin().await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+    for change in &result.split {
+        if let Err(e) = sqlx::query(
+            "UPDATE students
+             SET full_name=$1, paternal_surname=$2, maternal_surname=$3, given_names=$4,
+                 name_review_reason=NULL
+             WHERE id=$5",
+        )
+        .bind(&change.new_full_name)
+        .bind(&change.names.paternal_surname)
+        .bind(&change.names.maternal_surname)
+        .bind(&change.names.given_names)
+        .bind(change.student_id)
+        .execute(&mut *tx)
+        .await
+        {
+            eprintln!("Error splitting student name: {:?}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("No se pudo actualizar '{}'", change.full_name)
+            }));
+        }
+    }
+    for item in &result.needs_review {
+        if let Err(e) = sqlx::query("UPDATE students SET name_review_reason=$1 WHERE id=$2")
+            .bind(&item.reason)
+            .bind(item.student_id)
+            .execute(&mut *tx)
+            .await
+        {
+            eprintln!("Error flagging student name: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    }
+    if let Err(e) = tx.commit().await {
+        eprintln!("Error committing name split: {:?}", e);
+        return HttpResponse::InternalServerError().body("Error en la base de datos");
+    }
+
+    HttpResponse::Ok().json(result)
+}
+
+// Nombres que la división automática no pudo resolver. Se corrigen enviando las
+// partes con PUT /students/{id}, lo que también los quita de esta lista.
+#[get("/admin/students/name-review")]
+pub async fn list_name_review(req: HttpRequest, data: web::Data<AppState>) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+
+    match sqlx::query(&format!(
+        "{} WHERE s.name_review_reason IS NOT NULL ORDER BY g.number, sec.letter, s.full_name",
+        NAME_REVIEW_SELECT
+    ))
+    .fetch_all(&data.pool)
+    .await
+    {
+        Ok(rows) => {
+            let items: Vec<NameReviewItem> = rows
+                .iter()
+                .map(|r| name_review_item(r, r.get("name_review_reason")))
+                .collect();
+            HttpResponse::Ok().json(items)
+        }
+        Err(e) => {
+            eprintln!("Error fetching name review list: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+// Fusiona un alumno ingresado dos veces. Con dry_run la fusión se ejecuta y se
+// deshace, así la vista previa muestra exactamente lo que se movería.
+#[post("/admin/students/merge")]
+pub async fn merge_duplicate_students(
+    req: HttpRequest,
+    query: web::Query<StudentMergeQuery>,
+    data: web::Data<AppState>,
+    body: web::Json<StudentMergeIn>,
+) -> impl Responder {
+    let admin = match require_role(&data.pool, &req, &[UserRole::Admin]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+    if body.surviving_student_id == body.duplicate_student_id {
+        return HttpResponse::BadRequest().body("Debe indicar dos alumnos distintos");
+    }
+    let dry_run = query.dry_run.unwrap_or(false);
+
+    let mut tx = match data.pool.begin().await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+    let (merge_id, summary) = match merge_students(&mut tx, &body, admin.id).await {
+        Ok(r) => r,
+        Err(MergeError::NotFound) => {
+            return HttpResponse::NotFound().body("Estudiante no encontrado");
+        }
+        Err(MergeError::Conflict(msg)) => {
+            return HttpResponse::Conflict().json(serde_json::json!({ "error": msg }));
+        }
+        Err(MergeError::Db(e)) => {
+            eprintln!("Error merging students: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error al fusionar alumnos");
+        }
+    };
+
+    let finished = if dry_run {
+        tx.rollback().await
+    } else {
+        tx.commit().await
+    };
+    if let Err(e) = finished {
+        eprintln!("Error finishing student merge: {:?}", e);
+        return HttpResponse::InternalServerError().body("Error al fusionar alumnos");
+    }
+
+    HttpResponse::Ok().json(StudentMergeResult {
+        dry_run,
+        merge_id: (!dry_run).then_some(merge_id),
+        surviving_student_id: body.surviving_student_id,
+        duplicate_student_id: body.duplicate_student_id,
+        conflict_policy: body.conflict_policy,
+        summary,
+    })
 }
 
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(create_student)
+    // La nómina comparte ruta con create_student: debe registrarse antes
+    cfg.service(import_enrollment_list)
+        .service(create_student)
         .service(list_students)
         .service(update_student)
         .service(delete_student)
@@ -552,5 +919,8 @@ pub fn config(cfg: &mut web::ServiceConfig) {
         .service(import_students_txt)
         .service(get_student_grades)
         .service(get_student_profile)
-        .service(get_student_enrollments);
+        .service(get_student_enrollments)
+        .service(split_student_names)
+        .service(list_name_review)
+        .service(merge_duplicate_students);
 }
@@ -0,0 +1,43 @@
+use crate::basic::models::Bimester;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+pub const ACADEMIC_YEAR_COLUMNS: &str = "id, year, start_date, end_date, status, created_at";
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct AcademicYear {
+    pub id: i32,
+    pub year: i32,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    // "planning", "active" o "closed"
+    pub status: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct BimesterRangeIn {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+}
+
+#[derive(Deserialize)]
+pub struct AcademicYearIn {
+    pub year: i32,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    // Fechas de los cuatro bimestres; si no se indican se reparte el año en partes iguales
+    pub bimesters: Option<Vec<BimesterRangeIn>>,
+}
+
+#[derive(Deserialize)]
+pub struct AcademicYearStatusIn {
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct AcademicYearWithBimesters {
+    #[serde(flatten)]
+    pub year: AcademicYear,
+    pub bimesters: Vec<Bimester>,
+}
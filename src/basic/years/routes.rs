@@ -0,0 +1,266 @@
+use crate::auth::guard::require_role;
+use crate::auth::models::UserRole;
+use crate::basic::models::{Bimester, BIMESTER_COLUMNS};
+use crate::basic::years::models::*;
+use crate::AppState;
+use actix_web::{get, post, put, web, HttpRequest, HttpResponse, Responder};
+use chrono::{Duration, NaiveDate};
+use sqlx::PgPool;
+
+pub const BIMESTER_NAMES: [&str; 4] = ["I", "II", "III", "IV"];
+const YEAR_STATUSES: [&str; 3] = ["planning", "active", "closed"];
+
+// Reparte el año en cuatro bimestres consecutivos de igual duración; el último
+// absorbe los días sobrantes
+pub fn split_bimesters(start: NaiveDate, end: NaiveDate) -> Vec<(NaiveDate, NaiveDate)> {
+    let days = (end - start).num_days() + 1;
+    let chunk = days / 4;
+    (0..4)
+        .map(|i| {
+            let from = start + Duration::days(chunk * i);
+            let to = if i == 3 {
+                end
+            } else {
+                start + Duration::days(chunk * (i + 1) - 1)
+            };
+            (from, to)
+        })
+        .collect()
+}
+
+fn validate_ranges(input: &AcademicYearIn) -> Result<Vec<(NaiveDate, NaiveDate)>, String> {
+    if input.end_date <= input.start_date {
+        return Err("La fecha de fin debe ser posterior a la de inicio".to_string());
+    }
+    let Some(ranges) = &input.bimesters else {
+        if (input.end_date - input.start_date).num_days() < 3 {
+            return Err("El año es demasiado corto para cuatro bimestres".to_string());
+        }
+        return Ok(split_bimesters(input.start_date, input.end_date));
+    };
+    if ranges.len() != 4 {
+        return Err("Deben indicarse exactamente cuatro bimestres".to_string());
+    }
+    let mut previous_end: Option<NaiveDate> = None;
+    for (i, r) in ranges.iter().enumerate() {
+        if r.end_date < r.start_date {
+            return Err(format!(
+                "Bimestre {}: la fecha de fin es anterior a la de inicio",
+                BIMESTER_NAMES[i]
+            ));
+        }
+        if r.start_date < input.start_date || r.end_date > input.end_date {
+            return Err(format!(
+                "Bimestre {}: fuera de las fechas del año",
+                BIMESTER_NAMES[i]
+            ));
+        }
+        if previous_end.is_some_and(|prev| r.start_date <= prev) {
+            return Err(format!(
+                "Bimestre {}: se superpone con el anterior",
+                BIMESTER_NAMES[i]
+            ));
+        }
+        previous_end = Some(r.end_date);
+    }
+    Ok(ranges.iter().map(|r| (r.start_date, r.end_date)).collect())
+}
+
+async fn fetch_year_bimesters(pool: &PgPool, year_id: i32) -> Result<Vec<Bimester>, sqlx::Error> {
+    sqlx::query_as::<_, Bimester>(&format!(
+        "SELECT {} FROM bimesters WHERE academic_year_id = $1 ORDER BY number, id",
+        BIMESTER_COLUMNS
+    ))
+    .bind(year_id)
+    .fetch_all(pool)
+    .await
+}
+
+#[post("/academic-years")]
+pub async fn create_academic_year(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    body: web::Json<AcademicYearIn>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Admin]).await {
+        return resp;
+    }
+    let ranges = match validate_ranges(&body) {
+        Ok(r) => r,
+        Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    };
+
+    let mut tx = match data.pool.begin().await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+    let year = match sqlx::query_as::<_, AcademicYear>(&format!(
+        "INSERT INTO academic_years (year, start_date, end_date) VALUES ($1, $2, $3) RETURNING {}",
+        ACADEMIC_YEAR_COLUMNS
+    ))
+    .bind(body.year)
+    .bind(body.start_date)
+    .bind(body.end_date)
+    .fetch_one(&mut *tx)
+    .await
+    {
+        Ok(y) => y,
+        Err(sqlx::Error::Database(db_err)) if db_err.code().as_deref() == Some("23505") => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": format!("El año {} ya existe", body.year)
+            }));
+        }
+        Err(e) => {
+            eprintln!("Error creating academic year: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+
+    let mut bimesters = Vec::new();
+    for (i, (start, end)) in ranges.into_iter().enumerate() {
+        match sqlx::query_as::<_, Bimester>(&format!(
+            r#"
+            INSERT INTO bimesters (name, year, academic_year_id, number, start_date, end_date)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING {}
+            "#,
+            BIMESTER_COLUMNS
+        ))
+        .bind(BIMESTER_NAMES[i])
+        .bind(year.year)
+        .bind(year.id)
+        .bind(i as i32 + 1)
+        .bind(start)
+        .bind(end)
+        .fetch_one(&mut *tx)
+        .await
+        {
+            Ok(b) => bimesters.push(b),
+            Err(e) => {
+                eprintln!("Error creating bimester: {:?}", e);
+                return HttpResponse::InternalServerError().body("Error en la base de datos");
+            }
+        }
+    }
+    if let Err(e) = tx.commit().await {
+        eprintln!("Error committing academic year: {:?}", e);
+        return HttpResponse::InternalServerError().body("Error en la base de datos");
+    }
+
+    HttpResponse::Created().json(AcademicYearWithBimesters { year, bimesters })
+}
+
+#[get("/academic-years")]
+pub async fn list_academic_years(data: web::Data<AppState>) -> impl Responder {
+    match sqlx::query_as::<_, AcademicYear>(&format!(
+        "SELECT {} FROM academic_years ORDER BY year DESC",
+        ACADEMIC_YEAR_COLUMNS
+    ))
+    .fetch_all(&data.pool)
+    .await
+    {
+        Ok(rows) => HttpResponse::Ok().json(rows),
+        Err(e) => {
+            eprintln!("Error fetching academic years: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+#[get("/academic-years/{year_id}")]
+pub async fn get_academic_year(path: web::Path<i32>, data: web::Data<AppState>) -> impl Responder {
+    let year_id = path.into_inner();
+    let year = match sqlx::query_as::<_, AcademicYear>(&format!(
+        "SELECT {} FROM academic_years WHERE id = $1",
+        ACADEMIC_YEAR_COLUMNS
+    ))
+    .bind(year_id)
+    .fetch_optional(&data.pool)
+    .await
+    {
+        Ok(Some(y)) => y,
+        Ok(None) => return HttpResponse::NotFound().body("Año académico no encontrado"),
+        Err(e) => {
+            eprintln!("Error fetching academic year: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+    match fetch_year_bimesters(&data.pool, year_id).await {
+        Ok(bimesters) => HttpResponse::Ok().json(AcademicYearWithBimesters { year, bimesters }),
+        Err(e) => {
+            eprintln!("Error fetching bimesters: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+// El estado solo avanza: planning → active → closed. Solo puede haber un año activo.
+#[put("/academic-years/{year_id}/status")]
+pub async fn update_academic_year_status(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    body: web::Json<AcademicYearStatusIn>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Admin]).await {
+        return resp;
+    }
+    let year_id = path.into_inner();
+    let Some(new_rank) = YEAR_STATUSES.iter().position(|s| *s == body.status) else {
+        return HttpResponse::BadRequest().body("Estado inválido: planning, active o closed");
+    };
+
+    let current =
+        match sqlx::query_scalar::<_, String>("SELECT status FROM academic_years WHERE id = $1")
+            .bind(year_id)
+            .fetch_optional(&data.pool)
+            .await
+        {
+            Ok(Some(s)) => s,
+            Ok(None) => return HttpResponse::NotFound().body("Año académico no encontrado"),
+            Err(e) => {
+                eprintln!("Error fetching academic year: {:?}", e);
+                return HttpResponse::InternalServerError().body("Error en la base de datos");
+            }
+        };
+    let current_rank = YEAR_STATUSES
+        .iter()
+        .position(|s| *s == current)
+        .unwrap_or(0);
+    if new_rank <= current_rank {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": format!("No se puede pasar de '{}' a '{}'", current, body.status)
+        }));
+    }
+
+    match sqlx::query_as::<_, AcademicYear>(&format!(
+        "UPDATE academic_years SET status = $1 WHERE id = $2 RETURNING {}",
+        ACADEMIC_YEAR_COLUMNS
+    ))
+    .bind(&body.status)
+    .bind(year_id)
+    .fetch_one(&data.pool)
+    .await
+    {
+        Ok(year) => HttpResponse::Ok().json(year),
+        Err(sqlx::Error::Database(db_err)) if db_err.code().as_deref() == Some("23505") => {
+            HttpResponse::Conflict().json(serde_json::json!({
+                "error": "Ya hay un año activo; ciérrelo antes de activar otro"
+            }))
+        }
+        Err(e) => {
+            eprintln!("Error updating academic year: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_academic_year)
+        .service(list_academic_years)
+        .service(get_academic_year)
+        .service(update_academic_year_status);
+}
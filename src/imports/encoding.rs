@@ -0,0 +1,43 @@
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+
+pub struct DecodedText {
+    pub text: String,
+    pub encoding: &'static str,
+}
+
+// Excel en Windows guarda los CSV y TXT en Windows-1252 (o Latin-1), no en UTF-8.
+// Se respeta el BOM si existe; si no, se intenta UTF-8 estricto y, si falla,
+// se decodifica como Windows-1252, que coincide con Latin-1 en los caracteres imprimibles.
+pub fn decode_text(bytes: &[u8]) -> DecodedText {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        let name = if encoding == UTF_8 {
+            "UTF-8 (BOM)"
+        } else {
+            encoding.name()
+        };
+        return DecodedText {
+            text: text.into_owned(),
+            encoding: name,
+        };
+    }
+
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return DecodedText {
+            text: text.to_string(),
+            encoding: "UTF-8",
+        };
+    }
+
+    // Los bytes 0x80-0x9F son controles en Latin-1 y letras o signos en Windows-1252
+    let encoding = if bytes.iter().any(|b| (0x80..=0x9F).contains(b)) {
+        "Windows-1252"
+    } else {
+        "ISO-8859-1"
+    };
+    let (text, _) = WINDOWS_1252.decode_without_bom_handling(bytes);
+    DecodedText {
+        text: text.into_owned(),
+        encoding,
+    }
+}
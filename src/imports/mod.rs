@@ -0,0 +1,3 @@
+pub mod encoding;
+pub mod table;
+pub mod upload;
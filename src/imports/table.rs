@@ -0,0 +1,108 @@
+use crate::imports::encoding::decode_text;
+use crate::imports::upload::UploadedFile;
+use calamine::{open_workbook_from_rs, Reader, Xlsx};
+use std::io::Cursor;
+
+// Los .xlsx son archivos ZIP: se reconocen por la firma PK\x03\x04
+pub fn is_xlsx(file: &UploadedFile) -> bool {
+    file.bytes.starts_with(b"PK\x03\x04")
+        || file
+            .filename
+            .as_deref()
+            .map(|f| f.to_lowercase().ends_with(".xlsx"))
+            .unwrap_or(false)
+}
+
+// Excel en configuración regional de Perú guarda los CSV con punto y coma.
+// Se elige el separador que más aparece fuera de comillas en las primeras líneas.
+pub fn detect_delimiter(text: &str) -> u8 {
+    let mut counts = [(b',', 0usize), (b';', 0usize), (b'\t', 0usize)];
+    for line in text.lines().filter(|l| !l.trim().is_empty()).take(10) {
+        let mut in_quotes = false;
+        for c in line.bytes() {
+            if c == b'"' {
+                in_quotes = !in_quotes;
+            } else if !in_quotes {
+                for (d, n) in counts.iter_mut() {
+                    if c == *d {
+                        *n += 1;
+                    }
+                }
+            }
+        }
+    }
+    counts
+        .iter()
+        .max_by_key(|(_, n)| *n)
+        .filter(|(_, n)| *n > 0)
+        .map(|(d, _)| *d)
+        .unwrap_or(b',')
+}
+
+pub fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(detect_delimiter(text))
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+
+    let mut rows = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| format!("Fila {}: {}", i + 1, e))?;
+        rows.push(record.iter().map(|c| c.to_string()).collect());
+    }
+    Ok(rows)
+}
+
+pub fn parse_xlsx(bytes: &[u8]) -> Result<Vec<Vec<String>>, String> {
+    let mut workbook: Xlsx<_> = open_workbook_from_rs(Cursor::new(bytes))
+        .map_err(|e| format!("No se pudo abrir el Excel: {}", e))?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| "El Excel no tiene hojas".to_string())?
+        .map_err(|e| format!("No se pudo leer la hoja: {}", e))?;
+
+    Ok(range
+        .rows()
+        .map(|row| {
+            row.iter()
+                .map(|c| c.to_string().trim().to_string())
+                .collect()
+        })
+        .collect())
+}
+
+// Lee un CSV o XLSX como tabla de celdas de texto, descartando filas vacías
+pub fn read_table(file: &UploadedFile) -> Result<Vec<Vec<String>>, String> {
+    let rows = if is_xlsx(file) {
+        parse_xlsx(&file.bytes)?
+    } else {
+        parse_csv(&decode_text(&file.bytes).text)?
+    };
+
+    Ok(rows
+        .into_iter()
+        .filter(|r| r.iter().any(|c| !c.is_empty()))
+        .collect())
+}
+
+// Minúsculas, sin tildes, sin signos y con espacios simples: para comparar
+// nombres y encabezados escritos a mano
+pub fn normalize_text(s: &str) -> String {
+    let folded: String = s
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' | 'ä' | 'â' => 'a',
+            'é' | 'è' | 'ë' | 'ê' => 'e',
+            'í' | 'ì' | 'ï' | 'î' => 'i',
+            'ó' | 'ò' | 'ö' | 'ô' => 'o',
+            'ú' | 'ù' | 'ü' | 'û' => 'u',
+            'ñ' => 'n',
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        })
+        .collect();
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
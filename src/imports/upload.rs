@@ -0,0 +1,42 @@
+use actix_multipart::Multipart;
+use actix_web::HttpResponse;
+use futures_util::StreamExt;
+
+pub struct UploadedFile {
+    pub filename: Option<String>,
+    pub bytes: Vec<u8>,
+}
+
+// Lee el primer archivo del multipart junto con su nombre
+pub async fn read_upload(payload: &mut Multipart) -> Result<UploadedFile, HttpResponse> {
+    let mut file = UploadedFile {
+        filename: None,
+        bytes: Vec::new(),
+    };
+
+    if let Some(field_result) = payload.next().await {
+        let mut field = field_result.map_err(|e| {
+            HttpResponse::BadRequest()
+                .json(serde_json::json!({"error": format!("Error leyendo campo: {}", e)}))
+        })?;
+        file.filename = field
+            .content_disposition()
+            .and_then(|cd| cd.get_filename())
+            .map(|f| f.to_string());
+
+        while let Some(chunk_result) = field.next().await {
+            let chunk = chunk_result.map_err(|e| {
+                HttpResponse::BadRequest()
+                    .json(serde_json::json!({"error": format!("Error leyendo chunk: {}", e)}))
+            })?;
+            file.bytes.extend_from_slice(&chunk);
+        }
+    }
+
+    if file.bytes.is_empty() {
+        return Err(HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": "No se recibió ningún archivo o está vacío"})));
+    }
+
+    Ok(file)
+}
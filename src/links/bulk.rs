@@ -0,0 +1,401 @@
+use crate::basic::students::roster::{find_column, normalize_dni, DNI_HEADERS, FULL_NAME_HEADERS};
+use crate::imports::table::normalize_text;
+use crate::links::dni::{DniLookup, DniLookupError};
+use crate::links::matching::name_similarity;
+use crate::links::models::BulkDniLinkRow;
+use sqlx::{Acquire, PgConnection, Row};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+
+const STUDENT_ID_HEADERS: [&str; 4] = ["student id", "id alumno", "id estudiante", "id"];
+const SECTION_ID_HEADERS: [&str; 2] = ["section id", "id seccion"];
+const GRADE_HEADERS: [&str; 2] = ["grado", "grade"];
+const LETTER_HEADERS: [&str; 3] = ["seccion", "section", "letra"];
+
+struct BulkColumns {
+    student_id: Option<usize>,
+    full_name: Option<usize>,
+    section_id: Option<usize>,
+    grade: Option<usize>,
+    letter: Option<usize>,
+    dni: usize,
+}
+
+fn map_columns(header: &[String]) -> Result<BulkColumns, String> {
+    let dni = find_column(header, &DNI_HEADERS)
+        .ok_or_else(|| "El archivo debe tener una columna 'dni'.".to_string())?;
+    let cols = BulkColumns {
+        student_id: find_column(header, &STUDENT_ID_HEADERS),
+        full_name: find_column(header, &FULL_NAME_HEADERS),
+        section_id: find_column(header, &SECTION_ID_HEADERS),
+        grade: find_column(header, &GRADE_HEADERS),
+        letter: find_column(header, &LETTER_HEADERS),
+        dni,
+    };
+    let has_section = cols.section_id.is_some() || (cols.grade.is_some() && cols.letter.is_some());
+    if cols.student_id.is_none() && !(cols.full_name.is_some() && has_section) {
+        return Err(
+            "El archivo debe tener 'student_id' o el nombre con 'section_id' (o 'grado' y 'seccion')."
+                .to_string(),
+        );
+    }
+    Ok(cols)
+}
+
+fn row_result(row: usize, status: &'static str, message: String) -> BulkDniLinkRow {
+    BulkDniLinkRow {
+        row,
+        student_id: None,
+        dni: None,
+        status,
+        message: Some(message),
+        detail: None,
+    }
+}
+
+// Resuelve filas por id, o por nombre dentro de una sección, y guarda en caché los
+// alumnos de cada sección para no consultarlos por cada fila
+struct StudentResolver {
+    bimester_id: Option<i32>,
+    sections: HashMap<(i32, String), Option<i32>>,
+    students: HashMap<i32, Vec<(i32, String)>>,
+}
+
+impl StudentResolver {
+    async fn section_by_grade(
+        &mut self,
+        conn: &mut PgConnection,
+        grade: i32,
+        letter: &str,
+    ) -> Result<Option<i32>, sqlx::Error> {
+        let key = (grade, letter.to_uppercase());
+        if let Some(found) = self.sections.get(&key) {
+            return Ok(*found);
+        }
+        let bimester_id = match self.bimester_id {
+            Some(id) => id,
+            None => {
+                let latest = sqlx::query_scalar::<_, i32>(
+                    "SELECT id FROM bimesters ORDER BY year DESC, id DESC LIMIT 1",
+                )
+                .fetch_optional(&mut *conn)
+                .await?;
+                let Some(id) = latest else {
+                    return Ok(None);
+                };
+                self.bimester_id = Some(id);
+                id
+            }
+        };
+        let section = sqlx::query_scalar::<_, i32>(
+            r#"
+            SELECT sec.id FROM sections sec
+            JOIN grades g ON g.id = sec.grade_id
+            WHERE g.bimester_id = $1 AND g.number = $2 AND UPPER(sec.letter) = $3
+            "#,
+        )
+        .bind(bimester_id)
+        .bind(grade)
+        .bind(&key.1)
+        .fetch_optional(&mut *conn)
+        .await?;
+        self.sections.insert(key, section);
+        Ok(section)
+    }
+
+    async fn student_by_name(
+        &mut self,
+        conn: &mut PgConnection,
+        section_id: i32,
+        full_name: &str,
+    ) -> Result<Vec<i32>, sqlx::Error> {
+        let students = match self.students.entry(section_id) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => {
+                let rows = sqlx::query("SELECT id, full_name FROM students WHERE section_id = $1")
+                    .bind(section_id)
+                    .fetch_all(&mut *conn)
+                    .await?;
+                e.insert(
+                    rows.iter()
+                        .map(|r| (r.get("id"), r.get("full_name")))
+                        .collect(),
+                )
+            }
+        };
+        // Mismo nombre sin importar tildes, mayúsculas ni el orden de las palabras
+        Ok(students
+            .iter()
+            .filter(|(_, name)| name_similarity(full_name, name) >= 1.0)
+            .map(|(id, _)| *id)
+            .collect())
+    }
+}
+
+async fn resolve_student(
+    conn: &mut PgConnection,
+    resolver: &mut StudentResolver,
+    cols: &BulkColumns,
+    cell: impl Fn(Option<usize>) -> String,
+) -> Result<Result<i32, String>, sqlx::Error> {
+    let raw_id = cell(cols.student_id);
+    if !raw_id.is_empty() {
+        return Ok(raw_id
+            .parse::<i32>()
+            .map_err(|_| format!("ID de alumno inválido '{}'", raw_id)));
+    }
+
+    let full_name = cell(cols.full_name);
+    if full_name.is_empty() {
+        return Ok(Err("Falta el ID o el nombre del alumno".to_string()));
+    }
+    let raw_section = cell(cols.section_id);
+    let section_id = if !raw_section.is_empty() {
+        match raw_section.parse::<i32>() {
+            Ok(id) => id,
+            Err(_) => return Ok(Err(format!("ID de sección inválido '{}'", raw_section))),
+        }
+    } else {
+        let (raw_grade, letter) = (cell(cols.grade), cell(cols.letter));
+        let Ok(grade) = normalize_text(&raw_grade)
+            .trim_end_matches(|c: char| !c.is_ascii_digit())
+            .parse::<i32>()
+        else {
+            return Ok(Err(format!("Grado inválido '{}'", raw_grade)));
+        };
+        match resolver.section_by_grade(conn, grade, &letter).await? {
+            Some(id) => id,
+            None => {
+                return Ok(Err(format!(
+                    "No existe la sección {}° {} en el bimestre",
+                    grade, letter
+                )))
+            }
+        }
+    };
+
+    let matches = resolver
+        .student_by_name(conn, section_id, &full_name)
+        .await?;
+    Ok(match matches.as_slice() {
+        [id] => Ok(*id),
+        [] => Err(format!("No se encontró a '{}' en la sección", full_name)),
+        _ => Err(format!(
+            "Hay {} alumnos llamados '{}' en la sección",
+            matches.len(),
+            full_name
+        )),
+    })
+}
+
+// Fila ya resuelta a un alumno, pendiente de verificar su DNI y vincular
+struct PendingLink {
+    row: usize,
+    student_id: i32,
+    dni: String,
+}
+
+pub struct ResolvedBulkRows {
+    // Filas que no llegan a vincularse: sin DNI, DNI inválido o alumno no encontrado
+    failed: Vec<BulkDniLinkRow>,
+    pending: Vec<PendingLink>,
+}
+
+// Lee el archivo y resuelve el alumno de cada fila, sin transacción ni consultas a RENIEC
+pub async fn resolve_bulk_rows(
+    conn: &mut PgConnection,
+    rows: &[Vec<String>],
+    bimester_id: Option<i32>,
+) -> Result<Result<ResolvedBulkRows, String>, sqlx::Error> {
+    let Some(header_row) = rows.first() else {
+        return Ok(Err("El archivo está vacío".to_string()));
+    };
+    let header: Vec<String> = header_row.iter().map(|h| normalize_text(h)).collect();
+    let cols = match map_columns(&header) {
+        Ok(c) => c,
+        Err(e) => return Ok(Err(e)),
+    };
+
+    let mut resolver = StudentResolver {
+        bimester_id,
+        sections: HashMap::new(),
+        students: HashMap::new(),
+    };
+    let mut resolved = ResolvedBulkRows {
+        failed: Vec::new(),
+        pending: Vec::new(),
+    };
+    for (i, row) in rows.iter().enumerate().skip(1) {
+        let row_number = i + 1;
+        let cell = |col: Option<usize>| {
+            col.and_then(|c| row.get(c))
+                .map(|v| v.trim().to_string())
+                .unwrap_or_default()
+        };
+
+        let dni = match normalize_dni(&cell(Some(cols.dni))) {
+            Ok(Some(d)) => d,
+            Ok(None) => {
+                resolved
+                    .failed
+                    .push(row_result(row_number, "error", "Falta el DNI".to_string()));
+                continue;
+            }
+            Err(e) => {
+                resolved.failed.push(row_result(row_number, "error", e));
+                continue;
+            }
+        };
+        match resolve_student(conn, &mut resolver, &cols, cell).await? {
+            Ok(student_id) => resolved.pending.push(PendingLink {
+                row: row_number,
+                student_id,
+                dni,
+            }),
+            Err(e) => resolved.failed.push(BulkDniLinkRow {
+                dni: Some(dni),
+                ..row_result(row_number, "error", e)
+            }),
+        }
+    }
+    Ok(Ok(resolved))
+}
+
+// Consulta RENIEC una sola vez por DNI distinto. Se llama antes de abrir la
+// transacción para no retener la conexión ni los bloqueos durante las consultas.
+pub async fn verify_bulk_dnis(
+    lookup: &dyn DniLookup,
+    resolved: &ResolvedBulkRows,
+) -> HashMap<String, Result<(), DniLookupError>> {
+    let mut verified = HashMap::new();
+    for link in &resolved.pending {
+        if verified.contains_key(&link.dni) {
+            continue;
+        }
+        let outcome = lookup.lookup(&link.dni).await.map(|_| ());
+        if let Err(e) = &outcome {
+            if !matches!(e, DniLookupError::NotFound) {
+                eprintln!("Error verificando DNI {}: {}", link.dni, e);
+            }
+        }
+        verified.insert(link.dni.clone(), outcome);
+    }
+    verified
+}
+
+// Vincula cada fila con link_student_by_dni dentro de un punto de guardado: una fila
+// fallida se deshace sola y no afecta a las demás. Un DNI que RENIEC no conoce se
+// rechaza; si el servicio no respondió se vincula igual y se avisa. Quien llama
+// decide si confirma.
+pub async fn link_bulk_rows(
+    conn: &mut PgConnection,
+    resolved: ResolvedBulkRows,
+    verified: &HashMap<String, Result<(), DniLookupError>>,
+) -> Result<Vec<BulkDniLinkRow>, sqlx::Error> {
+    let mut results = resolved.failed;
+    for PendingLink {
+        row: row_number,
+        student_id,
+        dni,
+    } in resolved.pending
+    {
+        let unverified = match verified.get(&dni) {
+            Some(Err(DniLookupError::NotFound)) => {
+                results.push(BulkDniLinkRow {
+                    student_id: Some(student_id),
+                    dni: Some(dni),
+                    ..row_result(
+                        row_number,
+                        "rejected",
+                        "El DNI no existe en RENIEC".to_string(),
+                    )
+                });
+                continue;
+            }
+            Some(Err(e)) => Some(format!("No se pudo verificar el DNI en RENIEC: {}", e)),
+            _ => None,
+        };
+
+        let mut result = BulkDniLinkRow {
+            row: row_number,
+            student_id: Some(student_id),
+            dni: Some(dni.clone()),
+            status: "linked",
+            message: unverified,
+            detail: None,
+        };
+        let mut savepoint = conn.begin().await?;
+        let current = sqlx::query_scalar::<_, Option<String>>(
+            "SELECT dni FROM students WHERE id = $1 FOR UPDATE",
+        )
+        .bind(student_id)
+        .fetch_optional(&mut *savepoint)
+        .await?;
+        match current {
+            None => {
+                result.status = "error";
+                result.message = Some("Estudiante no encontrado".to_string());
+            }
+            Some(Some(existing)) if existing != dni => {
+                result.status = "rejected";
+                result.message = Some(format!("El alumno ya tiene el DNI {}", existing));
+            }
+            Some(_) => {
+                match sqlx::query_scalar::<_, serde_json::Value>(
+                    "SELECT public.link_student_by_dni($1, $2)",
+                )
+                .bind(student_id)
+                .bind(&dni)
+                .fetch_one(&mut *savepoint)
+                .await
+                {
+                    Ok(detail) => {
+                        if detail.get("success").and_then(|s| s.as_bool()) == Some(false) {
+                            result.status = "rejected";
+                            result.message = detail
+                                .get("message")
+                                .and_then(|m| m.as_str())
+                                .map(str::to_string);
+                        }
+                        result.detail = Some(detail);
+                    }
+                    Err(e) => {
+                        eprintln!("Error linking by DNI (fila {}): {:?}", row_number, e);
+                        result.status = "error";
+                        result.message = Some("Error al vincular por DNI".to_string());
+                    }
+                }
+            }
+        }
+        if result.status == "linked" {
+            savepoint.commit().await?;
+        } else {
+            savepoint.rollback().await?;
+        }
+        results.push(result);
+    }
+    results.sort_by_key(|r| r.row);
+    Ok(results)
+}
+
+// Alumnos procesados agrupados por las categorías de student_linking_status
+pub async fn linking_status_summary(
+    conn: &mut PgConnection,
+    student_ids: &[i32],
+) -> Result<BTreeMap<String, i64>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT link_status, COUNT(*) AS total
+        FROM public.student_linking_status
+        WHERE student_id = ANY($1)
+        GROUP BY link_status
+        "#,
+    )
+    .bind(student_ids)
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(rows
+        .iter()
+        .map(|r| (r.get("link_status"), r.get("total")))
+        .collect())
+}
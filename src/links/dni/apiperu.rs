@@ -0,0 +1,74 @@
+use super::{DniLookup, DniLookupError};
+use crate::links::models::{ReniecData, ReniecResponse};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use std::time::Duration;
+
+const APIPERU_URL: &str = "https://apiperu.dev/api/dni";
+
+// Consulta a apiperu.dev con un único cliente HTTP compartido entre peticiones
+pub struct ApiPeruLookup {
+    client: reqwest::Client,
+    token: String,
+}
+
+impl ApiPeruLookup {
+    pub fn new(token: String) -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()?;
+        Ok(Self { client, token })
+    }
+
+    // El token no se guarda en el código: sin APIPERU_TOKEN no hay consulta a RENIEC
+    pub fn from_env() -> Result<Self, String> {
+        let token = std::env::var("APIPERU_TOKEN")
+            .ok()
+            .filter(|t| !t.trim().is_empty())
+            .ok_or_else(|| {
+                "Falta APIPERU_TOKEN (o use DNI_LOOKUP=fixture para desarrollo)".to_string()
+            })?;
+        Self::new(token).map_err(|e| format!("No se pudo crear el cliente HTTP de RENIEC: {}", e))
+    }
+
+    async fn fetch(&self, dni: &str) -> Result<ReniecData, DniLookupError> {
+        let resp = self
+            .client
+            .post(APIPERU_URL)
+            .header("Accept", "application/json")
+            .bearer_auth(&self.token)
+            .json(&serde_json::json!({ "dni": dni }))
+            .send()
+            .await
+            .map_err(|e| DniLookupError::Unavailable(e.to_string()))?;
+
+        let status = resp.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Err(DniLookupError::NotFound);
+        }
+        if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(DniLookupError::Unavailable(format!("status {}", status)));
+        }
+        if !status.is_success() {
+            return Err(DniLookupError::InvalidResponse(format!(
+                "status {}",
+                status
+            )));
+        }
+
+        let body = resp
+            .json::<ReniecResponse>()
+            .await
+            .map_err(|e| DniLookupError::InvalidResponse(e.to_string()))?;
+        match body.data {
+            Some(data) if body.success => Ok(data),
+            _ => Err(DniLookupError::NotFound),
+        }
+    }
+}
+
+impl DniLookup for ApiPeruLookup {
+    fn lookup<'a>(&'a self, dni: &'a str) -> BoxFuture<'a, Result<ReniecData, DniLookupError>> {
+        self.fetch(dni).boxed()
+    }
+}
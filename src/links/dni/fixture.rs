@@ -0,0 +1,37 @@
+use super::{DniLookup, DniLookupError};
+use crate::links::models::ReniecData;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use std::collections::HashMap;
+
+// Datos fijos para desarrollo sin conexión: un JSON { "<dni>": { "nombre_completo": ... } }
+pub struct FixtureDniLookup {
+    records: HashMap<String, ReniecData>,
+}
+
+impl FixtureDniLookup {
+    pub fn new(records: HashMap<String, ReniecData>) -> Self {
+        Self { records }
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let records = serde_json::from_str(&content).map_err(|e| format!("{}: {}", path, e))?;
+        Ok(Self::new(records))
+    }
+}
+
+impl DniLookup for FixtureDniLookup {
+    fn lookup<'a>(&'a self, dni: &'a str) -> BoxFuture<'a, Result<ReniecData, DniLookupError>> {
+        let result = self
+            .records
+            .get(dni)
+            .cloned()
+            .map(|data| ReniecData {
+                numero: data.numero.or_else(|| Some(dni.to_string())),
+                ..data
+            })
+            .ok_or(DniLookupError::NotFound);
+        async move { result }.boxed()
+    }
+}
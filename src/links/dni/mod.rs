@@ -0,0 +1,28 @@
+pub mod apiperu;
+pub mod fixture;
+pub mod service;
+
+use crate::links::models::ReniecData;
+use futures::future::BoxFuture;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DniLookupError {
+    #[error("DNI no encontrado en RENIEC")]
+    NotFound,
+    // Fallas de red, errores 5xx o circuito abierto: conviene reintentar más tarde
+    #[error("Servicio RENIEC no disponible: {0}")]
+    Unavailable(String),
+    #[error("Respuesta inválida de RENIEC: {0}")]
+    InvalidResponse(String),
+}
+
+impl DniLookupError {
+    pub fn is_transient(&self) -> bool {
+        matches!(self, DniLookupError::Unavailable(_))
+    }
+}
+
+// Proveedor de datos de RENIEC por DNI
+pub trait DniLookup: Send + Sync {
+    fn lookup<'a>(&'a self, dni: &'a str) -> BoxFuture<'a, Result<ReniecData, DniLookupError>>;
+}
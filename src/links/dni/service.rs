@@ -0,0 +1,202 @@
+use super::apiperu::ApiPeruLookup;
+use super::fixture::FixtureDniLookup;
+use super::{DniLookup, DniLookupError};
+use crate::links::models::ReniecData;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const DEFAULT_CACHE_TTL_DAYS: u64 = 30;
+const MAX_ATTEMPTS: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(300);
+// Fallas seguidas que abren el circuito y tiempo que permanece abierto
+const FAILURE_THRESHOLD: u32 = 5;
+const OPEN_DURATION: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+// Proveedor sin configurar: cada consulta falla como servicio no disponible
+struct UnavailableDniLookup(String);
+
+impl DniLookup for UnavailableDniLookup {
+    fn lookup<'a>(&'a self, _dni: &'a str) -> BoxFuture<'a, Result<ReniecData, DniLookupError>> {
+        async move { Err(DniLookupError::Unavailable(self.0.clone())) }.boxed()
+    }
+}
+
+// Envuelve un proveedor con caché en Postgres, reintentos con espera creciente y
+// un cortocircuito que deja de llamar al proveedor mientras está caído
+pub struct CachedDniLookup {
+    provider: Box<dyn DniLookup>,
+    pool: PgPool,
+    // Sin TTL no se usa la caché (p. ej. con datos de prueba)
+    cache_ttl: Option<Duration>,
+    breaker: Mutex<BreakerState>,
+}
+
+impl CachedDniLookup {
+    pub fn new(provider: Box<dyn DniLookup>, pool: PgPool, cache_ttl: Option<Duration>) -> Self {
+        Self {
+            provider,
+            pool,
+            cache_ttl,
+            breaker: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    // DNI_LOOKUP=fixture usa el archivo DNI_LOOKUP_FIXTURES; si no, apiperu.dev, que
+    // exige APIPERU_TOKEN. Sin token el servidor arranca igual y toda consulta
+    // responde como servicio no disponible.
+    pub fn from_env(pool: PgPool) -> Arc<dyn DniLookup> {
+        if env::var("DNI_LOOKUP").is_ok_and(|v| v == "fixture") {
+            let fixture = match env::var("DNI_LOOKUP_FIXTURES") {
+                Ok(path) => FixtureDniLookup::from_file(&path).unwrap_or_else(|e| {
+                    eprintln!("Error leyendo datos de prueba de DNI: {}", e);
+                    FixtureDniLookup::new(HashMap::new())
+                }),
+                Err(_) => FixtureDniLookup::new(HashMap::new()),
+            };
+            return Arc::new(Self::new(Box::new(fixture), pool, None));
+        }
+
+        let ttl_days = env::var("DNI_CACHE_TTL_DAYS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_CACHE_TTL_DAYS);
+        let provider = match ApiPeruLookup::from_env() {
+            Ok(p) => p,
+            Err(e) => {
+                eprintln!("Consulta de DNI deshabilitada: {}", e);
+                return Arc::new(UnavailableDniLookup(e));
+            }
+        };
+        Arc::new(Self::new(
+            Box::new(provider),
+            pool,
+            Some(Duration::from_secs(ttl_days * 24 * 60 * 60)),
+        ))
+    }
+
+    fn circuit_open(&self) -> bool {
+        let mut breaker = self.breaker.lock().unwrap();
+        match breaker.open_until {
+            Some(until) if Instant::now() < until => true,
+            Some(_) => {
+                // Pasado el tiempo se deja pasar una llamada de prueba
+                breaker.open_until = None;
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn record_result(&self, result: &Result<ReniecData, DniLookupError>) {
+        let mut breaker = self.breaker.lock().unwrap();
+        match result {
+            Err(e) if e.is_transient() => {
+                breaker.consecutive_failures += 1;
+                if breaker.consecutive_failures >= FAILURE_THRESHOLD {
+                    tracing::warn!(
+                        "⚠️ RENIEC no responde, circuito abierto por {:?}",
+                        OPEN_DURATION
+                    );
+                    breaker.open_until = Some(Instant::now() + OPEN_DURATION);
+                }
+            }
+            _ => *breaker = BreakerState::default(),
+        }
+    }
+
+    async fn cached(&self, dni: &str, ttl: Duration) -> Option<ReniecData> {
+        let result = sqlx::query_scalar::<_, serde_json::Value>(
+            r#"
+            SELECT data FROM dni_lookup_cache
+            WHERE dni = $1 AND fetched_at > NOW() - make_interval(secs => $2)
+            "#,
+        )
+        .bind(dni)
+        .bind(ttl.as_secs_f64())
+        .fetch_optional(&self.pool)
+        .await;
+        match result {
+            Ok(value) => value.and_then(|v| serde_json::from_value(v).ok()),
+            Err(e) => {
+                eprintln!("Error reading DNI cache: {:?}", e);
+                None
+            }
+        }
+    }
+
+    async fn store(&self, dni: &str, data: &ReniecData) {
+        let Ok(value) = serde_json::to_value(data) else {
+            return;
+        };
+        let result = sqlx::query(
+            r#"
+            INSERT INTO dni_lookup_cache (dni, data, fetched_at)
+            VALUES ($1, $2, NOW())
+            ON CONFLICT (dni) DO UPDATE SET data = EXCLUDED.data, fetched_at = NOW()
+            "#,
+        )
+        .bind(dni)
+        .bind(value)
+        .execute(&self.pool)
+        .await;
+        if let Err(e) = result {
+            eprintln!("Error writing DNI cache: {:?}", e);
+        }
+    }
+
+    async fn fetch_with_retries(&self, dni: &str) -> Result<ReniecData, DniLookupError> {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            if self.circuit_open() {
+                return Err(DniLookupError::Unavailable("circuito abierto".to_string()));
+            }
+            let result = self.provider.lookup(dni).await;
+            self.record_result(&result);
+            match result {
+                Err(e) if e.is_transient() && attempt < MAX_ATTEMPTS => {
+                    tracing::warn!(
+                        "⚠️ Consulta RENIEC fallida (intento {}/{}): {}",
+                        attempt,
+                        MAX_ATTEMPTS,
+                        e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                other => return other,
+            }
+        }
+    }
+
+    async fn lookup_dni(&self, dni: &str) -> Result<ReniecData, DniLookupError> {
+        if let Some(ttl) = self.cache_ttl {
+            if let Some(data) = self.cached(dni, ttl).await {
+                return Ok(data);
+            }
+        }
+        let data = self.fetch_with_retries(dni).await?;
+        if self.cache_ttl.is_some() {
+            self.store(dni, &data).await;
+        }
+        Ok(data)
+    }
+}
+
+impl DniLookup for CachedDniLookup {
+    fn lookup<'a>(&'a self, dni: &'a str) -> BoxFuture<'a, Result<ReniecData, DniLookupError>> {
+        self.lookup_dni(dni).boxed()
+    }
+}
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Serialize, FromRow)]
+pub struct GuardianInvitation {
+    pub id: i32,
+    pub code: String,
+    pub student_id: Option<i32>,
+    pub student_user_id: i32,
+    pub relationship_type: Option<String>,
+    pub created_by_user_id: Option<i32>,
+    pub created_at: chrono::NaiveDateTime,
+    pub expires_at: chrono::NaiveDateTime,
+    pub redeemed_at: Option<chrono::NaiveDateTime>,
+    pub redeemed_by_user_id: Option<i32>,
+    pub revoked_at: Option<chrono::NaiveDateTime>,
+    pub revoked_by_user_id: Option<i32>,
+    pub revoke_reason: Option<String>,
+    pub status: String,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct InvitationRedemption {
+    pub id: i32,
+    pub invitation_id: Option<i32>,
+    pub code: String,
+    pub guardian_user_id: Option<i32>,
+    pub success: bool,
+    pub reason: String,
+    pub attempted_at: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize)]
+pub struct PrintableInvitation {
+    pub invitation_id: i32,
+    pub code: String,
+    pub student_id: i32,
+    pub student_name: String,
+    pub section_letter: String,
+    pub grade_number: i32,
+    pub expires_at: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct CreateInvitationIn {
+    pub student_id: i32,
+    pub expires_in_days: Option<i64>,
+    pub relationship_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateSectionInvitationsIn {
+    pub expires_in_days: Option<i64>,
+    pub relationship_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RevokeInvitationIn {
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct InvitationFilter {
+    pub student_id: Option<i32>,
+    pub status: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RedemptionFilter {
+    pub invitation_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct RedeemInvitationIn {
+    pub code: String,
+    pub relationship_type: Option<String>,
+}
+
+pub enum RedeemOutcome {
+    Linked {
+        invitation_id: i32,
+        student_user_id: i32,
+    },
+    AlreadyLinked {
+        invitation_id: i32,
+        student_user_id: i32,
+    },
+    NotFound,
+    Expired(i32),
+    Revoked(i32),
+    AlreadyUsed(i32),
+}
+
+impl RedeemOutcome {
+    pub fn is_success(&self) -> bool {
+        matches!(
+            self,
+            RedeemOutcome::Linked { .. } | RedeemOutcome::AlreadyLinked { .. }
+        )
+    }
+
+    pub fn invitation_id(&self) -> Option<i32> {
+        match self {
+            RedeemOutcome::Linked { invitation_id, .. }
+            | RedeemOutcome::AlreadyLinked { invitation_id, .. } => Some(*invitation_id),
+            RedeemOutcome::Expired(id)
+            | RedeemOutcome::Revoked(id)
+            | RedeemOutcome::AlreadyUsed(id) => Some(*id),
+            RedeemOutcome::NotFound => None,
+        }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        match self {
+            RedeemOutcome::Linked { .. } => "linked",
+            RedeemOutcome::AlreadyLinked { .. } => "already_linked",
+            RedeemOutcome::NotFound => "not_found",
+            RedeemOutcome::Expired(_) => "expired",
+            RedeemOutcome::Revoked(_) => "revoked",
+            RedeemOutcome::AlreadyUsed(_) => "already_used",
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            RedeemOutcome::Linked { .. } => "Apoderado vinculado exitosamente",
+            RedeemOutcome::AlreadyLinked { .. } => "El apoderado ya estaba vinculado a este alumno",
+            RedeemOutcome::NotFound => "Código de invitación no encontrado",
+            RedeemOutcome::Expired(_) => "El código de invitación ha expirado",
+            RedeemOutcome::Revoked(_) => "El código de invitación fue revocado",
+            RedeemOutcome::AlreadyUsed(_) => "El código de invitación ya fue utilizado",
+        }
+    }
+}
@@ -0,0 +1,661 @@
+use crate::auth::guard::require_role;
+use crate::auth::models::{User, UserRole};
+use crate::links::invitations::models::*;
+use crate::AppState;
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use sqlx::postgres::PgExecutor;
+use sqlx::{PgConnection, Row};
+
+const DEFAULT_EXPIRATION_DAYS: i64 = 30;
+const MAX_EXPIRATION_DAYS: i64 = 365;
+
+// Sin 0/O ni 1/I para que el código impreso no se preste a confusión
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+const INVITATION_COLUMNS: &str = r#"
+    id, code, student_id, student_user_id, relationship_type, created_by_user_id,
+    created_at, expires_at, redeemed_at, redeemed_by_user_id,
+    revoked_at, revoked_by_user_id, revoke_reason,
+    CASE
+        WHEN revoked_at IS NOT NULL THEN 'revoked'
+        WHEN redeemed_at IS NOT NULL THEN 'redeemed'
+        WHEN expires_at <= NOW() THEN 'expired'
+        ELSE 'active'
+    END AS status
+"#;
+
+fn generate_code() -> String {
+    let bytes = uuid::Uuid::new_v4().into_bytes();
+    let chars: String = bytes
+        .iter()
+        .take(8)
+        .map(|b| CODE_ALPHABET[(*b as usize) % CODE_ALPHABET.len()] as char)
+        .collect();
+    format!("{}-{}", &chars[..4], &chars[4..])
+}
+
+// Acepta el código con o sin guion, en minúsculas o con espacios
+pub fn normalize_code(code: &str) -> String {
+    let clean: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if clean.len() == 8 {
+        format!("{}-{}", &clean[..4], &clean[4..])
+    } else {
+        clean
+    }
+}
+
+// Un docente solo emite invitaciones para las secciones que tiene asignadas
+async fn may_invite_for_section(
+    pool: &sqlx::PgPool,
+    user: &User,
+    section_id: i32,
+) -> Result<bool, sqlx::Error> {
+    if user.role == UserRole::Admin {
+        return Ok(true);
+    }
+    sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM section_teachers WHERE section_id = $1 AND teacher_user_id = $2)",
+    )
+    .bind(section_id)
+    .bind(user.id)
+    .fetch_one(pool)
+    .await
+}
+
+// Para los listados: None para el admin (ve todo) o el id del docente, que solo ve
+// invitaciones de alumnos de sus secciones
+fn teacher_scope(user: &User) -> Option<i32> {
+    (user.role != UserRole::Admin).then_some(user.id)
+}
+
+fn forbidden_section() -> HttpResponse {
+    HttpResponse::Forbidden().json(serde_json::json!({
+        "success": false,
+        "message": "No tiene asignada la sección del alumno"
+    }))
+}
+
+fn expiration_days(requested: Option<i64>) -> Result<i32, HttpResponse> {
+    let days = requested.unwrap_or(DEFAULT_EXPIRATION_DAYS);
+    if !(1..=MAX_EXPIRATION_DAYS).contains(&days) {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": format!("La vigencia debe estar entre 1 y {} días", MAX_EXPIRATION_DAYS)
+        })));
+    }
+    Ok(days as i32)
+}
+
+async fn insert_invitation(
+    conn: &mut PgConnection,
+    student_id: i32,
+    student_user_id: i32,
+    relationship_type: Option<&str>,
+    created_by: i32,
+    days: i32,
+) -> Result<GuardianInvitation, sqlx::Error> {
+    // Reintenta ante la (improbable) colisión de códigos sin abortar la transacción
+    loop {
+        let sql = format!(
+            r#"
+            INSERT INTO guardian_invitations
+            (code, student_id, student_user_id, relationship_type, created_by_user_id, expires_at)
+            VALUES ($1, $2, $3, $4, $5, NOW() + make_interval(days => $6))
+            ON CONFLICT (code) DO NOTHING
+            RETURNING {}
+            "#,
+            INVITATION_COLUMNS
+        );
+        let inserted = sqlx::query_as::<_, GuardianInvitation>(&sql)
+            .bind(generate_code())
+            .bind(student_id)
+            .bind(student_user_id)
+            .bind(relationship_type)
+            .bind(created_by)
+            .bind(days)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+        if let Some(invitation) = inserted {
+            return Ok(invitation);
+        }
+    }
+}
+
+pub async fn record_redemption_attempt<'e, E: PgExecutor<'e>>(
+    executor: E,
+    code: &str,
+    guardian_user_id: Option<i32>,
+    outcome: &RedeemOutcome,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO guardian_invitation_redemptions
+        (invitation_id, code, guardian_user_id, success, reason)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(outcome.invitation_id())
+    .bind(code)
+    .bind(guardian_user_id)
+    .bind(outcome.is_success())
+    .bind(outcome.reason())
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+// Canjea un código dentro de la transacción del llamador y deja registro del intento
+pub async fn redeem_invitation(
+    conn: &mut PgConnection,
+    code: &str,
+    guardian_user_id: i32,
+    relationship_type: Option<&str>,
+) -> Result<RedeemOutcome, sqlx::Error> {
+    let code = normalize_code(code);
+
+    let row = sqlx::query(
+        r#"
+        SELECT id, student_user_id, relationship_type, redeemed_by_user_id,
+               revoked_at IS NOT NULL AS revoked,
+               redeemed_at IS NOT NULL AS redeemed,
+               expires_at <= NOW() AS expired
+        FROM guardian_invitations
+        WHERE code = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(&code)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let outcome = match row {
+        None => RedeemOutcome::NotFound,
+        Some(row) => {
+            let invitation_id: i32 = row.try_get("id")?;
+            let student_user_id: i32 = row.try_get("student_user_id")?;
+            let redeemed_by: Option<i32> = row.try_get("redeemed_by_user_id")?;
+
+            if row.try_get::<bool, _>("revoked")? {
+                RedeemOutcome::Revoked(invitation_id)
+            } else if row.try_get::<bool, _>("redeemed")? {
+                if redeemed_by == Some(guardian_user_id) {
+                    RedeemOutcome::AlreadyLinked {
+                        invitation_id,
+                        student_user_id,
+                    }
+                } else {
+                    RedeemOutcome::AlreadyUsed(invitation_id)
+                }
+            } else if row.try_get::<bool, _>("expired")? {
+                RedeemOutcome::Expired(invitation_id)
+            } else {
+                let invitation_relationship: Option<String> = row.try_get("relationship_type")?;
+
+                let inserted = sqlx::query(
+                    r#"
+                    INSERT INTO guardian_student_relationships
+                    (guardian_user_id, student_user_id, relationship_type, is_primary)
+                    SELECT $1, $2,
+                           COALESCE($3, (SELECT relationship_type FROM guardian_profiles WHERE user_id = $1), 'APODERADO'),
+                           NOT EXISTS (SELECT 1 FROM guardian_student_relationships WHERE student_user_id = $2)
+                    WHERE NOT EXISTS (
+                        SELECT 1 FROM guardian_student_relationships
+                        WHERE guardian_user_id = $1 AND student_user_id = $2
+                    )
+                    "#,
+                )
+                .bind(guardian_user_id)
+                .bind(student_user_id)
+                .bind(relationship_type.map(str::to_string).or(invitation_relationship))
+                .execute(&mut *conn)
+                .await?
+                .rows_affected()
+                    > 0;
+
+                sqlx::query(
+                    "UPDATE guardian_invitations SET redeemed_at = NOW(), redeemed_by_user_id = $1 WHERE id = $2",
+                )
+                .bind(guardian_user_id)
+                .bind(invitation_id)
+                .execute(&mut *conn)
+                .await?;
+
+                if inserted {
+                    RedeemOutcome::Linked {
+                        invitation_id,
+                        student_user_id,
+                    }
+                } else {
+                    RedeemOutcome::AlreadyLinked {
+                        invitation_id,
+                        student_user_id,
+                    }
+                }
+            }
+        }
+    };
+
+    record_redemption_attempt(&mut *conn, &code, Some(guardian_user_id), &outcome).await?;
+
+    tracing::info!(
+        "🎟️ Canje de invitación {}: guardian_user_id={}, resultado={}",
+        code,
+        guardian_user_id,
+        outcome.reason()
+    );
+
+    Ok(outcome)
+}
+
+#[post("/admin/guardian-invitations")]
+pub async fn create_invitation(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    body: web::Json<CreateInvitationIn>,
+) -> impl Responder {
+    let user = match require_role(&data.pool, &req, &[UserRole::Admin, UserRole::Docente]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+    let days = match expiration_days(body.expires_in_days) {
+        Ok(d) => d,
+        Err(resp) => return resp,
+    };
+
+    let (student_user_id, section_id) = match sqlx::query_as::<_, (Option<i32>, i32)>(
+        "SELECT user_id, section_id FROM students WHERE id = $1",
+    )
+    .bind(body.student_id)
+    .fetch_optional(&data.pool)
+    .await
+    {
+        Ok(Some(row)) => row,
+        Ok(None) => return HttpResponse::NotFound().body("Alumno no encontrado"),
+        Err(e) => {
+            eprintln!("Error fetching student: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error al buscar alumno");
+        }
+    };
+    match may_invite_for_section(&data.pool, &user, section_id).await {
+        Ok(true) => {}
+        Ok(false) => return forbidden_section(),
+        Err(e) => {
+            eprintln!("Error checking section teacher: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error de base de datos");
+        }
+    }
+    let Some(student_user_id) = student_user_id else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "El alumno aún no tiene una cuenta vinculada"
+        }));
+    };
+
+    let mut conn = match data.pool.acquire().await {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Error acquiring connection: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error de base de datos");
+        }
+    };
+
+    match insert_invitation(
+        &mut conn,
+        body.student_id,
+        student_user_id,
+        body.relationship_type.as_deref(),
+        user.id,
+        days,
+    )
+    .await
+    {
+        Ok(invitation) => HttpResponse::Ok().json(invitation),
+        Err(e) => {
+            eprintln!("Error creating invitation: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al crear invitación")
+        }
+    }
+}
+
+#[post("/sections/{sec_id}/guardian-invitations")]
+pub async fn create_section_invitations(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    body: web::Json<CreateSectionInvitationsIn>,
+) -> impl Responder {
+    let sec_id = path.into_inner();
+    let user = match require_role(&data.pool, &req, &[UserRole::Admin, UserRole::Docente]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+    let days = match expiration_days(body.expires_in_days) {
+        Ok(d) => d,
+        Err(resp) => return resp,
+    };
+    match may_invite_for_section(&data.pool, &user, sec_id).await {
+        Ok(true) => {}
+        Ok(false) => return forbidden_section(),
+        Err(e) => {
+            eprintln!("Error checking section teacher: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error de base de datos");
+        }
+    }
+
+    let students = match sqlx::query(
+        r#"
+        SELECT s.id, s.full_name, s.user_id, sec.letter, g.number AS grade_number
+        FROM students s
+        JOIN sections sec ON sec.id = s.section_id
+        JOIN grades g ON g.id = sec.grade_id
+        WHERE s.section_id = $1
+        ORDER BY s.full_name
+        "#,
+    )
+    .bind(sec_id)
+    .fetch_all(&data.pool)
+    .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            eprintln!("Error fetching students: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error al obtener alumnos");
+        }
+    };
+
+    let mut tx = match data.pool.begin().await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error de base de datos");
+        }
+    };
+
+    let mut invitations = Vec::new();
+    let mut skipped = Vec::new();
+
+    for row in students {
+        let student_id: i32 = row.try_get("id").unwrap();
+        let student_name: String = row.try_get("full_name").unwrap();
+
+        let Some(student_user_id) = row.try_get::<Option<i32>, _>("user_id").unwrap() else {
+            skipped.push(serde_json::json!({
+                "student_id": student_id,
+                "student_name": student_name,
+                "reason": "Sin cuenta vinculada"
+            }));
+            continue;
+        };
+
+        match insert_invitation(
+            &mut tx,
+            student_id,
+            student_user_id,
+            body.relationship_type.as_deref(),
+            user.id,
+            days,
+        )
+        .await
+        {
+            Ok(invitation) => invitations.push(PrintableInvitation {
+                invitation_id: invitation.id,
+                code: invitation.code,
+                student_id,
+                student_name,
+                section_letter: row.try_get("letter").unwrap(),
+                grade_number: row.try_get("grade_number").unwrap(),
+                expires_at: invitation.expires_at,
+            }),
+            Err(e) => {
+                let _ = tx.rollback().await;
+                eprintln!("Error creating invitation: {:?}", e);
+                return HttpResponse::InternalServerError().body("Error al crear invitaciones");
+            }
+        }
+    }
+
+    if let Err(e) = tx.commit().await {
+        eprintln!("Error committing invitations: {:?}", e);
+        return HttpResponse::InternalServerError().body("Error al crear invitaciones");
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "created": invitations.len(),
+        "invitations": invitations,
+        "skipped": skipped,
+    }))
+}
+
+#[get("/admin/guardian-invitations")]
+pub async fn list_invitations(
+    query: web::Query<InvitationFilter>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user = match require_role(&data.pool, &req, &[UserRole::Admin, UserRole::Docente]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let sql = format!(
+        r#"
+        SELECT * FROM (SELECT {} FROM guardian_invitations) i
+        WHERE ($1::int IS NULL OR i.student_id = $1)
+          AND ($2::text IS NULL OR i.status = $2)
+          AND ($3::int IS NULL OR EXISTS (
+              SELECT 1 FROM students s
+              JOIN section_teachers st ON st.section_id = s.section_id
+              WHERE s.id = i.student_id AND st.teacher_user_id = $3
+          ))
+        ORDER BY i.created_at DESC
+        "#,
+        INVITATION_COLUMNS
+    );
+
+    match sqlx::query_as::<_, GuardianInvitation>(&sql)
+        .bind(query.student_id)
+        .bind(query.status.as_deref())
+        .bind(teacher_scope(&user))
+        .fetch_all(&data.pool)
+        .await
+    {
+        Ok(rows) => HttpResponse::Ok().json(rows),
+        Err(e) => {
+            eprintln!("Error listing invitations: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al obtener invitaciones")
+        }
+    }
+}
+
+#[post("/admin/guardian-invitations/{id}/revoke")]
+pub async fn revoke_invitation(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    body: web::Json<RevokeInvitationIn>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let user = match require_role(&data.pool, &req, &[UserRole::Admin, UserRole::Docente]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    // La invitación de un alumno ya borrado solo la puede revocar el admin
+    let section_id = match sqlx::query_scalar::<_, Option<i32>>(
+        r#"
+        SELECT s.section_id FROM guardian_invitations i
+        LEFT JOIN students s ON s.id = i.student_id
+        WHERE i.id = $1
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(&data.pool)
+    .await
+    {
+        Ok(Some(section_id)) => section_id,
+        Ok(None) => return HttpResponse::NotFound().body("Invitación no encontrada"),
+        Err(e) => {
+            eprintln!("Error fetching invitation: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error de base de datos");
+        }
+    };
+    let allowed = match section_id {
+        Some(sec_id) => may_invite_for_section(&data.pool, &user, sec_id).await,
+        None => Ok(user.role == UserRole::Admin),
+    };
+    match allowed {
+        Ok(true) => {}
+        Ok(false) => return forbidden_section(),
+        Err(e) => {
+            eprintln!("Error checking section teacher: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error de base de datos");
+        }
+    }
+
+    let sql = format!(
+        r#"
+        UPDATE guardian_invitations
+        SET revoked_at = NOW(), revoked_by_user_id = $2, revoke_reason = $3
+        WHERE id = $1 AND revoked_at IS NULL AND redeemed_at IS NULL
+        RETURNING {}
+        "#,
+        INVITATION_COLUMNS
+    );
+
+    match sqlx::query_as::<_, GuardianInvitation>(&sql)
+        .bind(id)
+        .bind(user.id)
+        .bind(body.reason.as_deref())
+        .fetch_optional(&data.pool)
+        .await
+    {
+        Ok(Some(invitation)) => HttpResponse::Ok().json(invitation),
+        Ok(None) => HttpResponse::Conflict().json(serde_json::json!({
+            "success": false,
+            "message": "La invitación ya fue canjeada o revocada"
+        })),
+        Err(e) => {
+            eprintln!("Error revoking invitation: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al revocar invitación")
+        }
+    }
+}
+
+#[get("/admin/guardian-invitations/redemptions")]
+pub async fn list_redemptions(
+    query: web::Query<RedemptionFilter>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user = match require_role(&data.pool, &req, &[UserRole::Admin, UserRole::Docente]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    // Los intentos con códigos inexistentes no tienen sección: solo los ve el admin
+    match sqlx::query_as::<_, InvitationRedemption>(
+        r#"
+        SELECT r.id, r.invitation_id, r.code, r.guardian_user_id, r.success, r.reason,
+               r.attempted_at
+        FROM guardian_invitation_redemptions r
+        WHERE ($1::int IS NULL OR r.invitation_id = $1)
+          AND ($2::int IS NULL OR EXISTS (
+              SELECT 1 FROM guardian_invitations i
+              JOIN students s ON s.id = i.student_id
+              JOIN section_teachers st ON st.section_id = s.section_id
+              WHERE i.id = r.invitation_id AND st.teacher_user_id = $2
+          ))
+        ORDER BY r.attempted_at DESC
+        LIMIT 500
+        "#,
+    )
+    .bind(query.invitation_id)
+    .bind(teacher_scope(&user))
+    .fetch_all(&data.pool)
+    .await
+    {
+        Ok(rows) => HttpResponse::Ok().json(rows),
+        Err(e) => {
+            eprintln!("Error listing redemptions: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al obtener canjes")
+        }
+    }
+}
+
+#[post("/api/guardian/invitations/redeem")]
+pub async fn redeem_invitation_code(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    body: web::Json<RedeemInvitationIn>,
+) -> impl Responder {
+    let user = match require_role(&data.pool, &req, &[UserRole::Apoderado]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let mut tx = match data.pool.begin().await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error de base de datos");
+        }
+    };
+
+    let outcome = match redeem_invitation(
+        &mut tx,
+        &body.code,
+        user.id,
+        body.relationship_type.as_deref(),
+    )
+    .await
+    {
+        Ok(o) => o,
+        Err(e) => {
+            let _ = tx.rollback().await;
+            eprintln!("Error redeeming invitation: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error al canjear invitación");
+        }
+    };
+
+    // Se confirma también en los intentos fallidos para conservar la auditoría
+    if let Err(e) = tx.commit().await {
+        eprintln!("Error committing redemption: {:?}", e);
+        return HttpResponse::InternalServerError().body("Error al canjear invitación");
+    }
+
+    let payload = serde_json::json!({
+        "success": outcome.is_success(),
+        "message": outcome.message(),
+        "reason": outcome.reason(),
+    });
+
+    match outcome {
+        RedeemOutcome::Linked {
+            student_user_id, ..
+        }
+        | RedeemOutcome::AlreadyLinked {
+            student_user_id, ..
+        } => {
+            let mut payload = payload;
+            payload["student_user_id"] = serde_json::json!(student_user_id);
+            HttpResponse::Ok().json(payload)
+        }
+        RedeemOutcome::NotFound => HttpResponse::NotFound().json(payload),
+        RedeemOutcome::Expired(_) | RedeemOutcome::Revoked(_) => HttpResponse::Gone().json(payload),
+        RedeemOutcome::AlreadyUsed(_) => HttpResponse::Conflict().json(payload),
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_invitation)
+        .service(create_section_invitations)
+        .service(list_redemptions)
+        .service(list_invitations)
+        .service(revoke_invitation)
+        .service(redeem_invitation_code);
+}
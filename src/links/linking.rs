@@ -0,0 +1,154 @@
+use crate::links::matching::{decide_match, find_name_candidates, NameMatch};
+use crate::links::models::LinkCandidate;
+use sqlx::{PgConnection, Row};
+
+// Vincula registros del padrón con la cuenta de un alumno y deja constancia en
+// student_profile_links. Solo toma registros aún sin cuenta: devuelve cuántos vinculó.
+pub async fn link_student_rows(
+    conn: &mut PgConnection,
+    user_id: i32,
+    student_ids: &[i32],
+    dni: Option<&str>,
+    method: &str,
+) -> Result<u64, sqlx::Error> {
+    let mut linked = 0;
+    for student_id in student_ids {
+        let updated = sqlx::query(
+            r#"
+            UPDATE students
+            SET user_id = $1, dni = COALESCE($2, dni)
+            WHERE id = $3 AND user_id IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .bind(dni)
+        .bind(student_id)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            continue;
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO student_profile_links (student_id, user_id, linked_by_method)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (student_id, user_id) DO UPDATE
+            SET linked_by_method = EXCLUDED.linked_by_method,
+                linked_at = NOW()
+            "#,
+        )
+        .bind(student_id)
+        .bind(user_id)
+        .bind(method)
+        .execute(&mut *conn)
+        .await?;
+        linked += updated;
+    }
+    Ok(linked)
+}
+
+pub struct LinkedStudentRow {
+    pub student_id: i32,
+    pub full_name: String,
+}
+
+// Resultado de vincular una cuenta recién registrada con el padrón
+pub enum RegistrationLink {
+    LinkedByDni(Vec<LinkedStudentRow>),
+    LinkedByName(Vec<LinkedStudentRow>),
+    // Varios alumnos posibles: queda en la cola de revisión del administrador
+    Ambiguous(Vec<LinkCandidate>),
+    None,
+}
+
+impl RegistrationLink {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RegistrationLink::LinkedByDni(_) => "linked_by_dni",
+            RegistrationLink::LinkedByName(_) => "linked_by_name",
+            RegistrationLink::Ambiguous(_) => "ambiguous",
+            RegistrationLink::None => "none",
+        }
+    }
+}
+
+fn linked_rows(rows: &[sqlx::postgres::PgRow]) -> Vec<LinkedStudentRow> {
+    rows.iter()
+        .map(|r| LinkedStudentRow {
+            student_id: r.get("id"),
+            full_name: r.get("full_name"),
+        })
+        .collect()
+}
+
+// Vinculación en un solo paso dentro de la transacción del registro: primero por
+// DNI, luego por nombre. Si el trigger de student_profiles ya vinculó, se informa eso.
+pub async fn link_registered_student(
+    conn: &mut PgConnection,
+    user_id: i32,
+    full_name: &str,
+    dni: &str,
+) -> Result<RegistrationLink, sqlx::Error> {
+    let already = sqlx::query(
+        r#"
+        SELECT s.id, s.full_name, spl.linked_by_method
+        FROM students s
+        LEFT JOIN student_profile_links spl ON spl.student_id = s.id AND spl.user_id = s.user_id
+        WHERE s.user_id = $1
+        ORDER BY s.id DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+    if let Some(first) = already.first() {
+        let method: Option<String> = first.get("linked_by_method");
+        return Ok(match method.as_deref() {
+            Some(m) if m.starts_with("dni") => RegistrationLink::LinkedByDni(linked_rows(&already)),
+            _ => RegistrationLink::LinkedByName(linked_rows(&already)),
+        });
+    }
+
+    let by_dni = sqlx::query(
+        "SELECT id, full_name FROM students WHERE dni = $1 AND user_id IS NULL ORDER BY id DESC FOR UPDATE",
+    )
+    .bind(dni)
+    .fetch_all(&mut *conn)
+    .await?;
+    if !by_dni.is_empty() {
+        let ids: Vec<i32> = by_dni.iter().map(|r| r.get("id")).collect();
+        if link_student_rows(conn, user_id, &ids, Some(dni), "dni_auto").await? > 0 {
+            return Ok(RegistrationLink::LinkedByDni(linked_rows(&by_dni)));
+        }
+    }
+
+    // Llegado aquí ningún registro tiene el DNI de la cuenta: los que tienen otro DNI
+    // son otra persona con nombre parecido y no deben recibir el DNI de la cuenta
+    let candidates: Vec<_> = find_name_candidates(&mut *conn, full_name)
+        .await?
+        .into_iter()
+        .filter(|c| c.dni.as_deref().is_none_or(|d| d == dni))
+        .collect();
+    match decide_match(candidates) {
+        NameMatch::Unique(students) => {
+            let ids: Vec<i32> = students.iter().map(|s| s.student_id).collect();
+            // Otro registro simultáneo pudo tomar los mismos registros
+            if link_student_rows(conn, user_id, &ids, Some(dni), "full_name_auto").await? == 0 {
+                return Ok(RegistrationLink::None);
+            }
+            Ok(RegistrationLink::LinkedByName(
+                students
+                    .into_iter()
+                    .map(|s| LinkedStudentRow {
+                        student_id: s.student_id,
+                        full_name: s.full_name,
+                    })
+                    .collect(),
+            ))
+        }
+        NameMatch::Ambiguous(candidates) => Ok(RegistrationLink::Ambiguous(candidates)),
+        NameMatch::NotFound(_) => Ok(RegistrationLink::None),
+    }
+}
@@ -0,0 +1,283 @@
+use crate::imports::table::normalize_text;
+use crate::links::models::LinkCandidate;
+use sqlx::{PgExecutor, Row};
+use std::collections::HashSet;
+
+// Por encima de este puntaje y sin competidores cercanos se vincula sin intervención
+pub const AUTO_LINK_THRESHOLD: f64 = 0.95;
+// Puntaje mínimo para proponer un registro como candidato en la revisión
+pub const CANDIDATE_THRESHOLD: f64 = 0.60;
+// Dos candidatos a menos de esta distancia del mejor se consideran empatados
+const AMBIGUITY_MARGIN: f64 = 0.05;
+const MAX_CANDIDATES: usize = 10;
+
+pub enum NameMatch {
+    // Un único alumno; puede tener un registro por bimestre, todos con el mismo nombre
+    Unique(Vec<LinkCandidate>),
+    // Varios alumnos distintos con puntaje alto: requiere revisión
+    Ambiguous(Vec<LinkCandidate>),
+    // Ningún candidato supera el umbral de vinculación automática
+    NotFound(Vec<LinkCandidate>),
+}
+
+fn sorted_tokens(name: &str) -> String {
+    let mut tokens: Vec<&str> = name.split(' ').collect();
+    tokens.sort_unstable();
+    tokens.join(" ")
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        prev = curr;
+    }
+    prev[b.len()]
+}
+
+fn levenshtein_ratio(a: &str, b: &str) -> f64 {
+    let len = a.chars().count().max(b.chars().count());
+    if len == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / len as f64
+}
+
+// Trigramas al estilo pg_trgm: cada palabra con dos espacios delante y uno detrás
+fn trigrams(text: &str) -> HashSet<String> {
+    let mut set = HashSet::new();
+    for word in text.split(' ') {
+        let padded: Vec<char> = format!("  {} ", word).chars().collect();
+        for w in padded.windows(3) {
+            set.insert(w.iter().collect());
+        }
+    }
+    set
+}
+
+fn trigram_similarity(a: &str, b: &str) -> f64 {
+    let (ta, tb) = (trigrams(a), trigrams(b));
+    let union = ta.union(&tb).count();
+    if union == 0 {
+        return 0.0;
+    }
+    ta.intersection(&tb).count() as f64 / union as f64
+}
+
+// Similitud entre 0 y 1, sin tildes ni mayúsculas y sin importar el orden de las
+// palabras ("PEREZ ROJAS, JUAN" = "Juan Pérez Rojas")
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (normalize_text(a), normalize_text(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+    levenshtein_ratio(&sorted_tokens(&a), &sorted_tokens(&b)).max(trigram_similarity(&a, &b))
+}
+
+// Registros de alumnos aún sin cuenta, con puntaje 0 hasta compararlos con un nombre.
+// Solo los del año indicado o, por defecto, del año más reciente con bimestres.
+pub async fn fetch_unlinked_students<'e, E: PgExecutor<'e>>(
+    executor: E,
+    year: Option<i32>,
+) -> Result<Vec<LinkCandidate>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT s.id, s.full_name, s.dni, s.section_id, sec.letter, g.number AS grade_number,
+               b.id AS bimester_id, b.name AS bimester_name, b.year
+        FROM students s
+        JOIN sections sec ON sec.id = s.section_id
+        JOIN grades g ON g.id = sec.grade_id
+        JOIN bimesters b ON b.id = g.bimester_id
+        WHERE s.user_id IS NULL
+          AND b.year IS NOT DISTINCT FROM COALESCE($1, (SELECT MAX(year) FROM bimesters))
+        "#,
+    )
+    .bind(year)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|r| LinkCandidate {
+            student_id: r.get("id"),
+            full_name: r.get("full_name"),
+            dni: r.get("dni"),
+            section_id: r.get("section_id"),
+            section_letter: r.get("letter"),
+            grade_number: r.get("grade_number"),
+            bimester_id: r.get("bimester_id"),
+            bimester_name: r.get("bimester_name"),
+            year: r.get("year"),
+            score: 0.0,
+        })
+        .collect())
+}
+
+pub fn sort_candidates(candidates: &mut [LinkCandidate]) {
+    candidates.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(b.year.cmp(&a.year))
+            .then(b.bimester_id.cmp(&a.bimester_id))
+    });
+}
+
+// Candidatos que superan el umbral mínimo, del más al menos parecido
+pub fn rank_candidates(full_name: &str, students: &[LinkCandidate]) -> Vec<LinkCandidate> {
+    let mut candidates: Vec<LinkCandidate> = students
+        .iter()
+        .filter_map(|s| {
+            let score = name_similarity(full_name, &s.full_name);
+            (score >= CANDIDATE_THRESHOLD).then(|| LinkCandidate { score, ..s.clone() })
+        })
+        .collect();
+    sort_candidates(&mut candidates);
+    candidates
+}
+
+pub async fn find_name_candidates<'e, E: PgExecutor<'e>>(
+    executor: E,
+    full_name: &str,
+) -> Result<Vec<LinkCandidate>, sqlx::Error> {
+    let students = fetch_unlinked_students(executor, None).await?;
+    Ok(rank_candidates(full_name, &students))
+}
+
+// Decide si los candidatos señalan a un único alumno. Los empatados con el mejor
+// puntaje son el mismo alumno solo si tienen el mismo nombre, están en bimestres
+// distintos y no tienen DNIs distintos entre sí.
+pub fn decide_match(mut candidates: Vec<LinkCandidate>) -> NameMatch {
+    let Some(best) = candidates.first().map(|c| c.score) else {
+        return NameMatch::NotFound(candidates);
+    };
+    if best < AUTO_LINK_THRESHOLD {
+        candidates.truncate(MAX_CANDIDATES);
+        return NameMatch::NotFound(candidates);
+    }
+
+    let top: Vec<&LinkCandidate> = candidates
+        .iter()
+        .filter(|c| c.score >= best - AMBIGUITY_MARGIN)
+        .collect();
+    let names: HashSet<String> = top.iter().map(|c| normalize_text(&c.full_name)).collect();
+    let bimesters: HashSet<i32> = top.iter().map(|c| c.bimester_id).collect();
+    let dnis: HashSet<&str> = top.iter().filter_map(|c| c.dni.as_deref()).collect();
+
+    if names.len() == 1 && bimesters.len() == top.len() && dnis.len() <= 1 {
+        let count = top.len();
+        candidates.truncate(count);
+        NameMatch::Unique(candidates)
+    } else {
+        candidates.truncate(MAX_CANDIDATES);
+        NameMatch::Ambiguous(candidates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(
+        student_id: i32,
+        full_name: &str,
+        bimester_id: i32,
+        dni: Option<&str>,
+    ) -> LinkCandidate {
+        LinkCandidate {
+            student_id,
+            full_name: full_name.to_string(),
+            dni: dni.map(str::to_string),
+            section_id: bimester_id * 10,
+            section_letter: "A".to_string(),
+            grade_number: 3,
+            bimester_id,
+            bimester_name: format!("Bimestre {}", bimester_id),
+            year: Some(2026),
+            score: 0.0,
+        }
+    }
+
+    fn ids(candidates: &[LinkCandidate]) -> Vec<i32> {
+        candidates.iter().map(|c| c.student_id).collect()
+    }
+
+    #[test]
+    fn ignores_accents_case_punctuation_and_word_order() {
+        assert_eq!(
+            name_similarity("PEREZ ROJAS, JUAN", "Juan Pérez Rojas"),
+            1.0
+        );
+        assert_eq!(
+            name_similarity("Ñahui Quispe, María", "maria nahui quispe"),
+            1.0
+        );
+    }
+
+    #[test]
+    fn a_one_letter_typo_is_a_candidate_but_not_an_automatic_link() {
+        let score = name_similarity("Juan Perez Rojas", "Juan Peres Rojas");
+        assert!(score >= CANDIDATE_THRESHOLD, "score {}", score);
+        assert!(score < AUTO_LINK_THRESHOLD, "score {}", score);
+
+        let students = vec![candidate(1, "Juan Peres Rojas", 1, None)];
+        let ranked = rank_candidates("Juan Perez Rojas", &students);
+        assert!(matches!(decide_match(ranked), NameMatch::NotFound(c) if ids(&c) == [1]));
+    }
+
+    #[test]
+    fn unrelated_names_are_not_candidates() {
+        let students = vec![candidate(1, "Rosa Mamani Condori", 1, None)];
+        assert!(rank_candidates("Juan Perez Rojas", &students).is_empty());
+    }
+
+    #[test]
+    fn homonyms_in_the_same_bimester_are_ambiguous() {
+        let students = vec![
+            candidate(1, "Juan Perez Rojas", 1, None),
+            candidate(2, "JUAN PEREZ ROJAS", 1, None),
+        ];
+        let ranked = rank_candidates("Perez Rojas, Juan", &students);
+        assert!(matches!(decide_match(ranked), NameMatch::Ambiguous(c) if c.len() == 2));
+    }
+
+    #[test]
+    fn the_same_name_across_bimesters_is_one_student() {
+        let students = vec![
+            candidate(1, "Juan Perez Rojas", 1, None),
+            candidate(2, "Juan Perez Rojas", 2, Some("12345678")),
+            candidate(3, "Rosa Mamani Condori", 2, None),
+        ];
+        let ranked = rank_candidates("Perez Rojas, Juan", &students);
+        match decide_match(ranked) {
+            NameMatch::Unique(c) => {
+                let mut found = ids(&c);
+                found.sort_unstable();
+                assert_eq!(found, [1, 2]);
+            }
+            _ => panic!("se esperaba un único alumno"),
+        }
+    }
+
+    #[test]
+    fn conflicting_dnis_across_bimesters_are_ambiguous() {
+        let students = vec![
+            candidate(1, "Juan Perez Rojas", 1, Some("12345678")),
+            candidate(2, "Juan Perez Rojas", 2, Some("87654321")),
+        ];
+        let ranked = rank_candidates("Juan Perez Rojas", &students);
+        assert!(matches!(decide_match(ranked), NameMatch::Ambiguous(c) if c.len() == 2));
+    }
+
+    #[test]
+    fn no_candidates_is_not_found() {
+        assert!(matches!(decide_match(Vec::new()), NameMatch::NotFound(c) if c.is_empty()));
+    }
+}
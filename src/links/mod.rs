@@ -1,2 +1,8 @@
+pub mod bulk;
+pub mod dni;
+pub mod invitations;
+pub mod linking;
+pub mod matching;
 pub mod models;
+pub mod review;
 pub mod routes;
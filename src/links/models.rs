@@ -136,7 +136,7 @@ pub struct ReniecRequest {
     pub dni: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[allow(dead_code)]
 pub struct ReniecData {
     pub numero: Option<String>,
@@ -156,3 +156,63 @@ pub struct ReniecResponse {
     pub data: Option<ReniecData>,
     pub message: Option<String>,
 }
+
+#[derive(Serialize, Clone)]
+pub struct LinkCandidate {
+    pub student_id: i32,
+    pub full_name: String,
+    pub dni: Option<String>,
+    pub section_id: i32,
+    pub section_letter: String,
+    pub grade_number: i32,
+    pub bimester_id: i32,
+    pub bimester_name: String,
+    // Bimestres antiguos pueden no tener año
+    pub year: Option<i32>,
+    pub score: f64,
+}
+
+#[derive(Serialize)]
+pub struct LinkCandidatesOut {
+    pub user_id: i32,
+    pub full_name: String,
+    pub dni: String,
+    // "unique", "ambiguous" o "not_found"
+    pub decision: String,
+    pub candidates: Vec<LinkCandidate>,
+}
+
+#[derive(Deserialize)]
+pub struct BulkDniLinkQuery {
+    pub dry_run: Option<bool>,
+    pub atomic: Option<bool>,
+    // Bimestre donde buscar las columnas grado/sección; por defecto el más reciente
+    pub bimester_id: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct BulkDniLinkRow {
+    pub row: usize,
+    pub student_id: Option<i32>,
+    pub dni: Option<String>,
+    // "linked", "rejected" o "error"
+    pub status: &'static str,
+    pub message: Option<String>,
+    // Respuesta de link_student_by_dni para la fila
+    pub detail: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+pub struct BulkDniLinkResult {
+    pub dry_run: bool,
+    pub atomic: bool,
+    pub applied: bool,
+    pub encoding: Option<&'static str>,
+    pub total_rows: usize,
+    pub linked: usize,
+    pub rejected: usize,
+    pub errors: usize,
+    pub rows: Vec<BulkDniLinkRow>,
+    // Alumnos procesados agrupados por link_status de student_linking_status
+    pub linking_status: std::collections::BTreeMap<String, i64>,
+}
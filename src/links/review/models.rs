@@ -0,0 +1,49 @@
+use crate::links::models::LinkCandidate;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize)]
+pub struct ReviewCandidate {
+    #[serde(flatten)]
+    pub candidate: LinkCandidate,
+    pub dni_match: bool,
+    // "dni" y/o "nombre"
+    pub reasons: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct LinkReviewItem {
+    pub user_id: i32,
+    pub email: String,
+    pub full_name: String,
+    pub dni: String,
+    pub registered_at: Option<chrono::NaiveDateTime>,
+    pub candidates: Vec<ReviewCandidate>,
+}
+
+#[derive(Deserialize)]
+pub struct LinkReviewQuery {
+    pub section_id: Option<i32>,
+    // Año del padrón donde buscar candidatos; por defecto el más reciente
+    pub year: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct LinkReviewSummaryQuery {
+    pub year: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct LinkReviewDecisionIn {
+    pub student_ids: Vec<i32>,
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Default)]
+pub struct LinkReviewSummary {
+    pub pending_accounts: usize,
+    pub with_dni_match: usize,
+    pub with_name_candidates: usize,
+    pub without_candidates: usize,
+    pub unlinked_roster_entries: usize,
+    pub rejected_candidates: i64,
+}
@@ -0,0 +1,329 @@
+use crate::auth::guard::require_role;
+use crate::auth::models::UserRole;
+use crate::links::linking::link_student_rows;
+use crate::links::matching::{fetch_unlinked_students, name_similarity, rank_candidates};
+use crate::links::models::LinkCandidate;
+use crate::links::review::models::*;
+use crate::AppState;
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use sqlx::{PgPool, Row};
+use std::collections::HashSet;
+
+const MAX_REVIEW_CANDIDATES: usize = 10;
+
+// Cuentas de alumno registradas que aún no tienen ningún registro del padrón,
+// cada una con sus candidatos (por DNI y por nombre) sin los ya descartados
+async fn build_review_queue(
+    pool: &PgPool,
+    section_id: Option<i32>,
+    year: Option<i32>,
+) -> Result<(Vec<LinkReviewItem>, usize), sqlx::Error> {
+    let accounts = sqlx::query(
+        r#"
+        SELECT u.id AS user_id, u.email, u.created_at, sp.full_name, sp.dni
+        FROM users u
+        JOIN student_profiles sp ON sp.user_id = u.id
+        WHERE u.role = 'ALUMNO'
+          AND NOT EXISTS (SELECT 1 FROM students s WHERE s.user_id = u.id)
+        ORDER BY u.created_at, u.id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let rejected: HashSet<(i32, i32)> =
+        sqlx::query("SELECT user_id, student_id FROM link_review_rejections")
+            .fetch_all(pool)
+            .await?
+            .iter()
+            .map(|r| (r.get("user_id"), r.get("student_id")))
+            .collect();
+
+    let students = fetch_unlinked_students(pool, year).await?;
+    let unlinked_count = students.len();
+    let students: Vec<_> = students
+        .into_iter()
+        .filter(|s| section_id.is_none_or(|id| s.section_id == id))
+        .collect();
+
+    let mut queue = Vec::new();
+    for account in accounts {
+        let user_id: i32 = account.get("user_id");
+        let full_name: String = account.get("full_name");
+        let dni: String = account.get("dni");
+
+        let mut candidates: Vec<ReviewCandidate> = students
+            .iter()
+            .filter(|s| s.dni.as_deref() == Some(dni.as_str()))
+            .map(|s| ReviewCandidate {
+                candidate: LinkCandidate {
+                    score: name_similarity(&full_name, &s.full_name),
+                    ..s.clone()
+                },
+                dni_match: true,
+                reasons: vec!["dni".to_string()],
+            })
+            .collect();
+        for c in rank_candidates(&full_name, &students) {
+            match candidates
+                .iter_mut()
+                .find(|r| r.candidate.student_id == c.student_id)
+            {
+                Some(existing) => existing.reasons.push("nombre".to_string()),
+                None => candidates.push(ReviewCandidate {
+                    candidate: c,
+                    dni_match: false,
+                    reasons: vec!["nombre".to_string()],
+                }),
+            }
+        }
+        candidates.retain(|c| !rejected.contains(&(user_id, c.candidate.student_id)));
+        candidates.sort_by(|a, b| {
+            b.dni_match
+                .cmp(&a.dni_match)
+                .then(b.candidate.score.total_cmp(&a.candidate.score))
+        });
+        candidates.truncate(MAX_REVIEW_CANDIDATES);
+
+        // Al filtrar por sección solo interesan las cuentas con candidatos en ella
+        if section_id.is_some() && candidates.is_empty() {
+            continue;
+        }
+        queue.push(LinkReviewItem {
+            user_id,
+            email: account.get("email"),
+            full_name,
+            dni,
+            registered_at: account.get("created_at"),
+            candidates,
+        });
+    }
+
+    Ok((queue, unlinked_count))
+}
+
+#[get("/admin/link-review")]
+pub async fn list_link_review(
+    req: HttpRequest,
+    query: web::Query<LinkReviewQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Admin]).await {
+        return resp;
+    }
+
+    match build_review_queue(&data.pool, query.section_id, query.year).await {
+        Ok((queue, _)) => HttpResponse::Ok().json(queue),
+        Err(e) => {
+            eprintln!("Error building link review queue: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+#[get("/admin/link-review/summary")]
+pub async fn link_review_summary(
+    req: HttpRequest,
+    query: web::Query<LinkReviewSummaryQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Admin]).await {
+        return resp;
+    }
+
+    let (queue, unlinked_count) = match build_review_queue(&data.pool, None, query.year).await {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Error building link review queue: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+    let rejected_candidates =
+        match sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM link_review_rejections")
+            .fetch_one(&data.pool)
+            .await
+        {
+            Ok(n) => n,
+            Err(e) => {
+                eprintln!("Error counting rejections: {:?}", e);
+                return HttpResponse::InternalServerError().body("Error en la base de datos");
+            }
+        };
+
+    let summary = LinkReviewSummary {
+        pending_accounts: queue.len(),
+        with_dni_match: queue
+            .iter()
+            .filter(|i| i.candidates.iter().any(|c| c.dni_match))
+            .count(),
+        with_name_candidates: queue
+            .iter()
+            .filter(|i| !i.candidates.is_empty() && i.candidates.iter().all(|c| !c.dni_match))
+            .count(),
+        without_candidates: queue.iter().filter(|i| i.candidates.is_empty()).count(),
+        unlinked_roster_entries: unlinked_count,
+        rejected_candidates,
+    };
+    HttpResponse::Ok().json(summary)
+}
+
+// Aprueba uno o más registros del padrón (uno por bimestre) para la cuenta
+#[post("/admin/link-review/{user_id}/approve")]
+pub async fn approve_link_review(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    body: web::Json<LinkReviewDecisionIn>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Admin]).await {
+        return resp;
+    }
+    let user_id = path.into_inner();
+    let mut student_ids = body.student_ids.clone();
+    student_ids.sort_unstable();
+    student_ids.dedup();
+    if student_ids.is_empty() {
+        return HttpResponse::BadRequest().body("Debe indicar al menos un estudiante");
+    }
+
+    let mut tx = match data.pool.begin().await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+
+    let dni = match sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT sp.dni FROM student_profiles sp
+        JOIN users u ON u.id = sp.user_id
+        WHERE sp.user_id = $1 AND u.role = 'ALUMNO'
+        FOR UPDATE OF sp
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await
+    {
+        Ok(Some(d)) => d,
+        Ok(None) => return HttpResponse::NotFound().body("Cuenta de alumno no encontrada"),
+        Err(e) => {
+            eprintln!("Error fetching student profile: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+
+    let rows =
+        match sqlx::query("SELECT id, user_id, dni FROM students WHERE id = ANY($1) FOR UPDATE")
+            .bind(&student_ids)
+            .fetch_all(&mut *tx)
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("Error locking students: {:?}", e);
+                return HttpResponse::InternalServerError().body("Error en la base de datos");
+            }
+        };
+    if rows.len() != student_ids.len() {
+        return HttpResponse::NotFound().body("Estudiante no encontrado");
+    }
+    for row in &rows {
+        let student_id: i32 = row.get("id");
+        if row.get::<Option<i32>, _>("user_id").is_some() {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": format!("El estudiante {} ya está vinculado a otra cuenta", student_id)
+            }));
+        }
+        if let Some(student_dni) = row.get::<Option<String>, _>("dni") {
+            if student_dni != dni {
+                return HttpResponse::Conflict().json(serde_json::json!({
+                    "error": format!(
+                        "El estudiante {} tiene el DNI {}, distinto al de la cuenta",
+                        student_id, student_dni
+                    )
+                }));
+            }
+        }
+    }
+
+    let by_dni = rows
+        .iter()
+        .all(|r| r.get::<Option<String>, _>("dni").is_some());
+    let method = if by_dni {
+        "dni_manual"
+    } else {
+        "full_name_manual"
+    };
+    let linked = match link_student_rows(&mut tx, user_id, &student_ids, Some(&dni), method).await {
+        Ok(n) => n,
+        Err(e) => {
+            eprintln!("Error linking students: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error al vincular");
+        }
+    };
+    if let Err(e) = tx.commit().await {
+        eprintln!("Error committing link: {:?}", e);
+        return HttpResponse::InternalServerError().body("Error al vincular");
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "user_id": user_id,
+        "linked": linked,
+        "student_ids": student_ids,
+        "linked_by_method": method,
+    }))
+}
+
+// Descarta candidatos para que no se vuelvan a proponer a esta cuenta
+#[post("/admin/link-review/{user_id}/reject")]
+pub async fn reject_link_review(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    body: web::Json<LinkReviewDecisionIn>,
+) -> impl Responder {
+    let admin = match require_role(&data.pool, &req, &[UserRole::Admin]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+    let user_id = path.into_inner();
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO link_review_rejections (user_id, student_id, rejected_by_user_id, reason)
+        SELECT $1, s.id, $3, $4 FROM students s WHERE s.id = ANY($2)
+        ON CONFLICT (user_id, student_id) DO NOTHING
+        "#,
+    )
+    .bind(user_id)
+    .bind(&body.student_ids)
+    .bind(admin.id)
+    .bind(&body.reason)
+    .execute(&data.pool)
+    .await;
+
+    match result {
+        Ok(r) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "user_id": user_id,
+            "rejected": r.rows_affected(),
+        })),
+        Err(sqlx::Error::Database(db_err)) if db_err.code().as_deref() == Some("23503") => {
+            HttpResponse::NotFound().body("Cuenta no encontrada")
+        }
+        Err(e) => {
+            eprintln!("Error rejecting link candidates: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(link_review_summary)
+        .service(list_link_review)
+        .service(approve_link_review)
+        .service(reject_link_review);
+}
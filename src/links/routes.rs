@@ -1,6 +1,17 @@
+use crate::auth::guard::require_role;
+use crate::auth::models::UserRole;
+use crate::imports::encoding::decode_text;
+use crate::imports::table::{is_xlsx, read_table};
+use crate::imports::upload::read_upload;
+use crate::links::bulk::{
+    link_bulk_rows, linking_status_summary, resolve_bulk_rows, verify_bulk_dnis,
+};
+use crate::links::dni::DniLookupError;
+use crate::links::matching::{decide_match, find_name_candidates, NameMatch};
 use crate::links::models::*;
 use crate::AppState;
-use actix_web::{get, post, web, HttpResponse, Responder};
+use actix_multipart::Multipart;
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
 use sqlx::Row;
 use tracing;
 
@@ -201,6 +212,109 @@ pub async fn link_student_by_dni(
     }
 }
 
+// Vinculación masiva por DNI desde CSV o Excel. Con dry_run se procesa todo y se
+// deshace; con atomic cualquier fila no vinculada revierte el archivo completo.
+#[post("/admin/link-students-by-dni/bulk")]
+pub async fn bulk_link_students_by_dni(
+    req: HttpRequest,
+    query: web::Query<BulkDniLinkQuery>,
+    data: web::Data<AppState>,
+    mut payload: Multipart,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Admin]).await {
+        return resp;
+    }
+    let file = match read_upload(&mut payload).await {
+        Ok(f) => f,
+        Err(resp) => return resp,
+    };
+    let rows = match read_table(&file) {
+        Ok(r) => r,
+        Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    };
+    let encoding = (!is_xlsx(&file)).then(|| decode_text(&file.bytes).encoding);
+
+    let resolved = {
+        let mut conn = match data.pool.acquire().await {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("Error acquiring connection: {:?}", e);
+                return HttpResponse::InternalServerError().body("Error en la base de datos");
+            }
+        };
+        match resolve_bulk_rows(&mut conn, &rows, query.bimester_id).await {
+            Ok(Ok(r)) => r,
+            Ok(Err(e)) => {
+                return HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))
+            }
+            Err(e) => {
+                eprintln!("Error resolving bulk DNI rows: {:?}", e);
+                return HttpResponse::InternalServerError().body("Error al vincular por DNI");
+            }
+        }
+    };
+    // RENIEC se consulta sin conexión ni transacción abiertas
+    let verified = verify_bulk_dnis(data.dni_lookup.as_ref(), &resolved).await;
+
+    let mut tx = match data.pool.begin().await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+    let results = match link_bulk_rows(&mut tx, resolved, &verified).await {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Error in bulk DNI link: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error al vincular por DNI");
+        }
+    };
+
+    let mut student_ids: Vec<i32> = results.iter().filter_map(|r| r.student_id).collect();
+    student_ids.sort_unstable();
+    student_ids.dedup();
+    let linking_status = match linking_status_summary(&mut tx, &student_ids).await {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Error fetching linking status: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error al obtener estado");
+        }
+    };
+
+    let count = |status: &str| results.iter().filter(|r| r.status == status).count();
+    let mut result = BulkDniLinkResult {
+        dry_run: query.dry_run.unwrap_or(false),
+        atomic: query.atomic.unwrap_or(false),
+        applied: false,
+        encoding,
+        total_rows: results.len(),
+        linked: count("linked"),
+        rejected: count("rejected"),
+        errors: count("error"),
+        rows: results,
+        linking_status,
+    };
+
+    let failed = result.linked < result.total_rows;
+    if result.dry_run || (result.atomic && failed) {
+        if let Err(e) = tx.rollback().await {
+            eprintln!("Error rolling back bulk DNI link: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+        if result.dry_run {
+            return HttpResponse::Ok().json(result);
+        }
+        return HttpResponse::UnprocessableEntity().json(result);
+    }
+    if let Err(e) = tx.commit().await {
+        eprintln!("Error committing bulk DNI link: {:?}", e);
+        return HttpResponse::InternalServerError().body("Error al vincular por DNI");
+    }
+    result.applied = true;
+    HttpResponse::Ok().json(result)
+}
+
 #[get("/admin/linking-status")]
 pub async fn get_linking_status(data: web::Data<AppState>) -> impl Responder {
     let rows = sqlx::query(
@@ -251,7 +365,10 @@ pub async fn backfill_dni(data: web::Data<AppState>) -> impl Responder {
 }
 
 #[post("/api/validate-dni")]
-pub async fn validate_dni(body: web::Json<ReniecRequest>) -> impl Responder {
+pub async fn validate_dni(
+    data: web::Data<AppState>,
+    body: web::Json<ReniecRequest>,
+) -> impl Responder {
     // Validar formato de DNI
     if body.dni.len() != 8 || !body.dni.chars().all(|c| c.is_numeric()) {
         return HttpResponse::BadRequest().json(serde_json::json!({
@@ -260,86 +377,40 @@ pub async fn validate_dni(body: web::Json<ReniecRequest>) -> impl Responder {
         }));
     }
 
-    // Configurar cliente HTTP
-    let client = match reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-    {
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("Error creando cliente HTTP: {:?}", e);
-            return HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "message": "Error interno del servidor"
-            }));
-        }
-    };
-
-    // Construir request a RENIEC
-    let reniec_url = "https://apiperu.dev/api/dni";
-    let token = "Bearer 9b7d94e281d215abcea99e362033353d6a9fd467f817ac6f92fae5edab452a45";
-
-    let payload = serde_json::json!({
-        "dni": body.dni
-    });
-
     tracing::info!("🔍 Consultando RENIEC para DNI: {}", body.dni);
 
-    // Realizar petición
-    let response = client
-        .post(reniec_url)
-        .header("Accept", "application/json")
-        .header("Content-Type", "application/json")
-        .header("Authorization", token)
-        .json(&payload)
-        .send()
-        .await;
-
-    match response {
-        Ok(resp) => {
-            let status = resp.status();
-
-            if status.is_success() {
-                match resp.json::<ReniecResponse>().await {
-                    Ok(reniec_data) => {
-                        if reniec_data.success {
-                            let nombre = reniec_data
-                                .data
-                                .as_ref()
-                                .and_then(|d| d.nombre_completo.clone())
-                                .unwrap_or_else(|| "Sin nombre".to_string());
-                            tracing::info!("✅ DNI {} encontrado: {}", body.dni, nombre);
-                            HttpResponse::Ok().json(reniec_data)
-                        } else {
-                            tracing::warn!("⚠️ DNI {} no encontrado en RENIEC", body.dni);
-                            HttpResponse::NotFound().json(serde_json::json!({
-                                "success": false,
-                                "message": "DNI no encontrado en RENIEC"
-                            }))
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("❌ Error parseando respuesta de RENIEC: {:?}", e);
-                        HttpResponse::InternalServerError().json(serde_json::json!({
-                            "success": false,
-                            "message": "Error procesando respuesta de RENIEC"
-                        }))
-                    }
-                }
-            } else {
-                eprintln!(
-                    "❌ RENIEC respondió con status {}: {:?}",
-                    status,
-                    resp.text().await
-                );
-                HttpResponse::BadGateway().json(serde_json::json!({
-                    "success": false,
-                    "message": format!("Error en servicio RENIEC (status: {})", status)
-                }))
-            }
+    match data.dni_lookup.lookup(&body.dni).await {
+        Ok(reniec_data) => {
+            tracing::info!(
+                "✅ DNI {} encontrado: {}",
+                body.dni,
+                reniec_data
+                    .nombre_completo
+                    .as_deref()
+                    .unwrap_or("Sin nombre")
+            );
+            HttpResponse::Ok().json(ReniecResponse {
+                success: true,
+                data: Some(reniec_data),
+                message: None,
+            })
+        }
+        Err(DniLookupError::NotFound) => {
+            tracing::warn!("⚠️ DNI {} no encontrado en RENIEC", body.dni);
+            HttpResponse::NotFound().json(serde_json::json!({
+                "success": false,
+                "message": "DNI no encontrado en RENIEC"
+            }))
+        }
+        Err(e @ DniLookupError::InvalidResponse(_)) => {
+            eprintln!("❌ Error consultando RENIEC: {}", e);
+            HttpResponse::BadGateway().json(serde_json::json!({
+                "success": false,
+                "message": "Error procesando respuesta de RENIEC"
+            }))
         }
         Err(e) => {
-            eprintln!("❌ Error conectando con RENIEC: {:?}", e);
+            eprintln!("❌ Error conectando con RENIEC: {}", e);
             HttpResponse::ServiceUnavailable().json(serde_json::json!({
                 "success": false,
                 "message": "No se pudo conectar con el servicio RENIEC"
@@ -380,6 +451,55 @@ pub async fn create_guardian_relationship(
     }
 }
 
+// Candidatos del padrón para la cuenta de un alumno, según la similitud de nombres
+#[get("/admin/users/{user_id}/link-candidates")]
+pub async fn get_link_candidates(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Admin]).await {
+        return resp;
+    }
+    let user_id = path.into_inner();
+
+    let profile =
+        match sqlx::query("SELECT full_name, dni FROM student_profiles WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(&data.pool)
+            .await
+        {
+            Ok(Some(p)) => p,
+            Ok(None) => return HttpResponse::NotFound().body("Perfil de alumno no encontrado"),
+            Err(e) => {
+                eprintln!("Error fetching student profile: {:?}", e);
+                return HttpResponse::InternalServerError().body("Error en la base de datos");
+            }
+        };
+    let full_name: String = profile.get("full_name");
+
+    let candidates = match find_name_candidates(&data.pool, &full_name).await {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Error finding link candidates: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+    let (decision, candidates) = match decide_match(candidates) {
+        NameMatch::Unique(c) => ("unique", c),
+        NameMatch::Ambiguous(c) => ("ambiguous", c),
+        NameMatch::NotFound(c) => ("not_found", c),
+    };
+
+    HttpResponse::Ok().json(LinkCandidatesOut {
+        user_id,
+        full_name,
+        dni: profile.get("dni"),
+        decision: decision.to_string(),
+        candidates,
+    })
+}
+
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(link_student_to_user)
         .service(list_unlinked_students)
@@ -389,5 +509,7 @@ pub fn config(cfg: &mut web::ServiceConfig) {
         .service(link_student_by_dni)
         .service(get_linking_status)
         .service(backfill_dni)
-        .service(validate_dni);
+        .service(validate_dni)
+        .service(bulk_link_students_by_dni)
+        .service(get_link_candidates);
 }
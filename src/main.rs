@@ -1,7 +1,12 @@
+mod appeals;
 mod auth;
 mod basic;
+mod imports;
 mod links;
+mod me;
 mod models;
+mod notifications;
+mod reports;
 use crate::models::AppState;
 
 use actix_cors::Cors;
@@ -22,7 +27,8 @@ async fn actix_web(
         .await
         .expect("No se pudo conectar a la base de datos");
 
-    let state = AppState { pool };
+    let dni_lookup = links::dni::service::CachedDniLookup::from_env(pool.clone());
+    let state = AppState { pool, dni_lookup };
 
     let app = move |cfg: &mut web::ServiceConfig| {
         let cors = Cors::default()
@@ -42,11 +48,20 @@ async fn actix_web(
                 .app_data(web::Data::new(state.clone()))
                 .configure(auth::routes::config)
                 .configure(links::routes::config)
+                .configure(links::invitations::routes::config)
+                .configure(links::review::routes::config)
+                .configure(me::routes::config)
+                .configure(notifications::routes::config)
+                .configure(appeals::routes::config)
+                .configure(reports::routes::config)
                 .configure(basic::routes::config)
+                .configure(basic::years::routes::config)
+                .configure(basic::learners::routes::config)
                 .configure(basic::students::routes::config)
                 .configure(basic::session::routes::config)
                 .configure(basic::session::products::routes::config)
                 .configure(basic::session::evaluation::routes::config)
+                .configure(basic::session::attendance::routes::config)
                 .configure(basic::session::competencies::routes::config)
                 .configure(basic::session::competencies::abilities::routes::config)
                 .configure(basic::session::competencies::abilities::criterion::routes::config)
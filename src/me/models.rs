@@ -0,0 +1,36 @@
+use serde::Serialize;
+use sqlx::FromRow;
+
+#[derive(Serialize, FromRow)]
+pub struct MyObservation {
+    pub bimester_id: i32,
+    pub bimester_name: String,
+    pub session_id: i32,
+    pub session_title: Option<String>,
+    pub competency_name: Option<String>,
+    pub ability_name: Option<String>,
+    pub criterion_name: Option<String>,
+    pub observation: String,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct MyAttendance {
+    pub bimester_id: i32,
+    pub bimester_name: String,
+    pub session_id: i32,
+    pub session_number: i32,
+    pub session_title: Option<String>,
+    pub session_date: Option<chrono::NaiveDate>,
+    pub status: String,
+    pub note: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct MyAttendanceSummary {
+    pub present: usize,
+    pub absent: usize,
+    pub late: usize,
+    pub justified: usize,
+    pub records: Vec<MyAttendance>,
+}
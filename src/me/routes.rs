@@ -0,0 +1,176 @@
+use crate::auth::guard::require_role;
+use crate::auth::models::UserRole;
+use crate::basic::learners::models::LearnerRef;
+use crate::basic::students::models::StudentGradesFilter;
+use crate::basic::students::routes::{fetch_student_enrollments, fetch_student_grades};
+use crate::me::models::*;
+use crate::AppState;
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+
+// Todas las rutas /api/me derivan el alumno del usuario autenticado,
+// nunca de un id recibido en la petición, y lo buscan por su learner para
+// cubrir también las matrículas sin la cuenta vinculada. Las notas y
+// observaciones solo incluyen competencias publicadas.
+
+#[get("/api/me/enrollments")]
+pub async fn my_enrollments(req: HttpRequest, data: web::Data<AppState>) -> impl Responder {
+    let user = match require_role(&data.pool, &req, &[UserRole::Alumno]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    match fetch_student_enrollments(&data.pool, LearnerRef::User(user.id)).await {
+        Ok(enrollments) => HttpResponse::Ok().json(enrollments),
+        Err(e) => {
+            eprintln!("Error fetching enrollments: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al obtener matrículas")
+        }
+    }
+}
+
+#[get("/api/me/grades")]
+pub async fn my_grades(
+    req: HttpRequest,
+    query: web::Query<StudentGradesFilter>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user = match require_role(&data.pool, &req, &[UserRole::Alumno]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    match fetch_student_grades(&data.pool, LearnerRef::User(user.id), &query, true).await {
+        Ok(sessions) => HttpResponse::Ok().json(sessions),
+        Err(e) => {
+            eprintln!("Error fetching grades: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al obtener notas")
+        }
+    }
+}
+
+#[get("/api/me/observations")]
+pub async fn my_observations(
+    req: HttpRequest,
+    query: web::Query<StudentGradesFilter>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user = match require_role(&data.pool, &req, &[UserRole::Alumno]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let rows = sqlx::query_as::<_, MyObservation>(
+        r#"
+        SELECT
+            b.id AS bimester_id,
+            b.name AS bimester_name,
+            sess.id AS session_id,
+            sess.title AS session_title,
+            comp.name AS competency_name,
+            abl.name AS ability_name,
+            crt.name AS criterion_name,
+            ei.observation,
+            ei.updated_at
+        FROM evaluation_items ei
+        JOIN enrollments e ON e.student_id = ei.student_id
+        JOIN learners l ON l.id = e.learner_id
+        JOIN students s ON s.id = ei.student_id
+        JOIN sessions sess ON sess.id = ei.session_id
+        JOIN sections sec ON sec.id = sess.section_id
+        JOIN grades g ON g.id = sec.grade_id
+        JOIN bimesters b ON b.id = g.bimester_id
+        JOIN competencies comp ON comp.id = ei.competency_id
+        JOIN abilities abl ON abl.id = ei.ability_id
+        JOIN criteria crt ON crt.id = ei.criterion_id
+        WHERE (l.user_id = $1 OR s.user_id = $1)
+          AND COALESCE(ei.observation, '') <> ''
+          AND EXISTS (
+              SELECT 1 FROM grade_publications gp
+              WHERE gp.session_id = ei.session_id AND gp.competency_id = ei.competency_id
+          )
+          AND ($2::int IS NULL OR b.id = $2)
+          AND ($3::int IS NULL OR sess.id = $3)
+        ORDER BY b.id, sess.number, comp.number, abl.number, crt.number
+        "#,
+    )
+    .bind(user.id)
+    .bind(query.bimester_id)
+    .bind(query.session_id)
+    .fetch_all(&data.pool)
+    .await;
+
+    match rows {
+        Ok(r) => HttpResponse::Ok().json(r),
+        Err(e) => {
+            eprintln!("Error fetching observations: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al obtener observaciones")
+        }
+    }
+}
+
+#[get("/api/me/attendance")]
+pub async fn my_attendance(
+    req: HttpRequest,
+    query: web::Query<StudentGradesFilter>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user = match require_role(&data.pool, &req, &[UserRole::Alumno]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let rows = sqlx::query_as::<_, MyAttendance>(
+        r#"
+        SELECT
+            b.id AS bimester_id,
+            b.name AS bimester_name,
+            sess.id AS session_id,
+            sess.number AS session_number,
+            sess.title AS session_title,
+            sess.date AS session_date,
+            ar.status,
+            ar.note
+        FROM attendance_records ar
+        JOIN enrollments e ON e.student_id = ar.student_id
+        JOIN learners l ON l.id = e.learner_id
+        JOIN students s ON s.id = ar.student_id
+        JOIN sessions sess ON sess.id = ar.session_id
+        JOIN sections sec ON sec.id = sess.section_id
+        JOIN grades g ON g.id = sec.grade_id
+        JOIN bimesters b ON b.id = g.bimester_id
+        WHERE (l.user_id = $1 OR s.user_id = $1)
+          AND ($2::int IS NULL OR b.id = $2)
+          AND ($3::int IS NULL OR sess.id = $3)
+        ORDER BY b.id, sess.number
+        "#,
+    )
+    .bind(user.id)
+    .bind(query.bimester_id)
+    .bind(query.session_id)
+    .fetch_all(&data.pool)
+    .await;
+
+    match rows {
+        Ok(records) => {
+            let count = |status: &str| records.iter().filter(|r| r.status == status).count();
+            HttpResponse::Ok().json(MyAttendanceSummary {
+                present: count("present"),
+                absent: count("absent"),
+                late: count("late"),
+                justified: count("justified"),
+                records,
+            })
+        }
+        Err(e) => {
+            eprintln!("Error fetching attendance: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al obtener asistencia")
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(my_enrollments)
+        .service(my_grades)
+        .service(my_observations)
+        .service(my_attendance);
+}
@@ -1,6 +1,9 @@
+use crate::links::dni::DniLookup;
 use sqlx::PgPool;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
+    pub dni_lookup: Arc<dyn DniLookup>,
 }
@@ -0,0 +1,95 @@
+use crate::auth::guard::current_user;
+use crate::notifications::models::*;
+use crate::AppState;
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use sqlx::postgres::PgExecutor;
+
+pub async fn notify<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i32,
+    kind: &str,
+    title: &str,
+    body: Option<&str>,
+    payload: serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO notifications (user_id, kind, title, body, payload) VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(user_id)
+    .bind(kind)
+    .bind(title)
+    .bind(body)
+    .bind(payload)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+#[get("/api/notifications")]
+pub async fn list_notifications(
+    req: HttpRequest,
+    query: web::Query<NotificationFilter>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user = match current_user(&data.pool, &req).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let rows = sqlx::query_as::<_, Notification>(
+        r#"
+        SELECT id, user_id, kind, title, body, payload, created_at, read_at
+        FROM notifications
+        WHERE user_id = $1 AND (NOT $2 OR read_at IS NULL)
+        ORDER BY created_at DESC
+        LIMIT 100
+        "#,
+    )
+    .bind(user.id)
+    .bind(query.unread.unwrap_or(false))
+    .fetch_all(&data.pool)
+    .await;
+
+    match rows {
+        Ok(r) => HttpResponse::Ok().json(r),
+        Err(e) => {
+            eprintln!("Error fetching notifications: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al obtener notificaciones")
+        }
+    }
+}
+
+#[post("/api/notifications/{id}/read")]
+pub async fn mark_notification_read(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let user = match current_user(&data.pool, &req).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+
+    let result = sqlx::query(
+        "UPDATE notifications SET read_at = COALESCE(read_at, NOW()) WHERE id = $1 AND user_id = $2",
+    )
+    .bind(id)
+    .bind(user.id)
+    .execute(&data.pool)
+    .await;
+
+    match result {
+        Ok(r) if r.rows_affected() > 0 => HttpResponse::NoContent().finish(),
+        Ok(_) => HttpResponse::NotFound().body("Notificación no encontrada"),
+        Err(e) => {
+            eprintln!("Error marking notification: {:?}", e);
+            HttpResponse::InternalServerError().body("Error al actualizar notificación")
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_notifications)
+        .service(mark_notification_read);
+}
@@ -0,0 +1,153 @@
+use crate::reports::consolidation::level_description;
+use crate::reports::models::CompetencyResult;
+use std::collections::HashMap;
+
+// Plantilla por defecto. Variables: {{estudiante}}, {{competencia}}, {{nivel}},
+// {{nivel_descripcion}}, {{logros}}, {{criterios_bajos}}, {{observaciones}}.
+// Las secciones {{#var}}...{{/var}} solo se muestran si la variable no está vacía.
+pub const DEFAULT_TEMPLATE: &str = "{{estudiante}} se encuentra {{nivel_descripcion}} ({{nivel}}) en la competencia \"{{competencia}}\".\
+{{#logros}} Evidencia avances en: {{logros}}.{{/logros}}\
+{{#criterios_bajos}} Necesita reforzar: {{criterios_bajos}}.{{/criterios_bajos}}\
+{{#observaciones}} Observaciones del docente: {{observaciones}}.{{/observaciones}}";
+
+// Niveles que exigen conclusión descriptiva en la libreta
+pub const LEVELS_REQUIRING_CONCLUSION: [&str; 2] = ["B", "C"];
+
+pub fn render_template(template: &str, vars: &HashMap<&str, String>) -> String {
+    render_inner(template, vars).trim().to_string()
+}
+
+// Sin recortar: el espacio inicial de una sección separa su frase de la anterior
+fn render_inner(template: &str, vars: &HashMap<&str, String>) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        let tag = after[..end].trim();
+        rest = &after[end + 2..];
+
+        if let Some(name) = tag.strip_prefix('#') {
+            let close = format!("{{{{/{}}}}}", name);
+            let (inner, remaining) = match rest.find(&close) {
+                Some(pos) => (&rest[..pos], &rest[pos + close.len()..]),
+                None => (rest, ""),
+            };
+            let visible = vars.get(name).map(|v| !v.is_empty()).unwrap_or(false);
+            if visible {
+                out.push_str(&render_inner(inner, vars));
+            }
+            rest = remaining;
+        } else {
+            out.push_str(vars.get(tag).map(String::as_str).unwrap_or(""));
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+fn join_names(names: Vec<String>) -> String {
+    let mut unique: Vec<String> = Vec::new();
+    for n in names {
+        if !unique.contains(&n) {
+            unique.push(n);
+        }
+    }
+    unique.join("; ")
+}
+
+// Arma el borrador a partir de la rúbrica (criterios y capacidades), los criterios
+// con nivel bajo y las observaciones registradas en evaluation_items
+pub fn draft_conclusion(template: &str, student_name: &str, result: &CompetencyResult) -> String {
+    let logros = join_names(
+        result
+            .criteria
+            .iter()
+            .filter(|c| c.value == "AD" || c.value == "A")
+            .map(|c| c.criterion_name.clone())
+            .collect(),
+    );
+    let criterios_bajos = join_names(
+        result
+            .criteria
+            .iter()
+            .filter(|c| c.value == "B" || c.value == "C")
+            .map(|c| format!("{} ({})", c.criterion_name, c.ability_name))
+            .collect(),
+    );
+
+    let mut vars: HashMap<&str, String> = HashMap::new();
+    vars.insert("estudiante", student_name.to_string());
+    vars.insert("competencia", result.competency_name.clone());
+    vars.insert("nivel", result.level.clone());
+    vars.insert(
+        "nivel_descripcion",
+        level_description(&result.level).to_string(),
+    );
+    vars.insert("logros", logros);
+    vars.insert("criterios_bajos", criterios_bajos);
+    vars.insert("observaciones", result.observations.join(" "));
+
+    render_template(template, &vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&'static str, &str)]) -> HashMap<&'static str, String> {
+        pairs.iter().map(|(k, v)| (*k, v.to_string())).collect()
+    }
+
+    #[test]
+    fn sections_keep_the_space_between_sentences() {
+        let vars = vars(&[
+            ("estudiante", "Ana"),
+            ("nivel_descripcion", "en proceso"),
+            ("nivel", "B"),
+            ("competencia", "Resuelve problemas"),
+            ("logros", "Ordena datos"),
+            ("criterios_bajos", "Estima (Comunica)"),
+            ("observaciones", "Participa"),
+        ]);
+        assert_eq!(
+            render_template(DEFAULT_TEMPLATE, &vars),
+            "Ana se encuentra en proceso (B) en la competencia \"Resuelve problemas\". \
+             Evidencia avances en: Ordena datos. Necesita reforzar: Estima (Comunica). \
+             Observaciones del docente: Participa."
+        );
+    }
+
+    #[test]
+    fn missing_or_empty_section_variables_hide_the_section() {
+        let vars = vars(&[
+            ("estudiante", "Ana"),
+            ("nivel_descripcion", "en inicio"),
+            ("nivel", "C"),
+            ("competencia", "Lee textos"),
+            ("logros", ""),
+            ("criterios_bajos", "Infiere (Obtiene información)"),
+        ]);
+        assert_eq!(
+            render_template(DEFAULT_TEMPLATE, &vars),
+            "Ana se encuentra en inicio (C) en la competencia \"Lee textos\". \
+             Necesita reforzar: Infiere (Obtiene información)."
+        );
+    }
+
+    #[test]
+    fn only_the_whole_result_is_trimmed() {
+        let vars = vars(&[("a", "x")]);
+        assert_eq!(render_template("  {{#a}} uno {{a}}{{/a}} ", &vars), "uno x");
+        assert_eq!(
+            render_template("{{#b}}oculto{{/b}} {{sin_valor}}fin", &vars),
+            "fin"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use crate::reports::models::*;
+use sqlx::{PgPool, Row};
+use std::collections::BTreeMap;
+
+pub fn level_score(level: &str) -> Option<f64> {
+    match level {
+        "AD" => Some(4.0),
+        "A" => Some(3.0),
+        "B" => Some(2.0),
+        "C" => Some(1.0),
+        _ => None,
+    }
+}
+
+pub fn score_level(score: f64) -> &'static str {
+    if score >= 3.5 {
+        "AD"
+    } else if score >= 2.5 {
+        "A"
+    } else if score >= 1.5 {
+        "B"
+    } else {
+        "C"
+    }
+}
+
+pub fn level_description(level: &str) -> &'static str {
+    match level {
+        "AD" => "con logro destacado",
+        "A" => "en el nivel de logro esperado",
+        "B" => "en proceso",
+        _ => "en inicio",
+    }
+}
+
+pub async fn fetch_section_info(
+    pool: &PgPool,
+    section_id: i32,
+) -> Result<SectionInfo, sqlx::Error> {
+    sqlx::query_as::<_, SectionInfo>(
+        r#"
+        SELECT sec.id AS section_id, sec.letter AS section_letter, g.number AS grade_number,
+               b.id AS bimester_id, b.name AS bimester_name, b.year
+        FROM sections sec
+        JOIN grades g ON g.id = sec.grade_id
+        JOIN bimesters b ON b.id = g.bimester_id
+        WHERE sec.id = $1
+        "#,
+    )
+    .bind(section_id)
+    .fetch_one(pool)
+    .await
+}
+
+// Consolida el nivel de cada alumno por competencia en todo el bimestre de la sección.
+// Las competencias de distintas sesiones se agrupan por nombre visible y el nivel
+// es el promedio de los criterios (AD=4, A=3, B=2, C=1).
+pub async fn consolidate_section(
+    pool: &PgPool,
+    section_id: i32,
+) -> Result<SectionConsolidation, sqlx::Error> {
+    let section = fetch_section_info(pool, section_id).await?;
+
+    let students = sqlx::query(
+        "SELECT id, full_name, dni FROM students WHERE section_id = $1 ORDER BY full_name",
+    )
+    .bind(section_id)
+    .fetch_all(pool)
+    .await?;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT ei.student_id,
+               COALESCE(comp.name, 'Competencia '||comp.number::text) AS competency_name,
+               COALESCE(abl.name, 'Capacidad '||abl.number::text) AS ability_name,
+               COALESCE(crt.name, 'C'||crt.number::text) AS criterion_name,
+               ei.value::text AS value,
+               ei.observation
+        FROM evaluation_items ei
+        JOIN sessions sess ON sess.id = ei.session_id
+        JOIN competencies comp ON comp.id = ei.competency_id
+        JOIN abilities abl ON abl.id = ei.ability_id
+        JOIN criteria crt ON crt.id = ei.criterion_id
+        WHERE sess.section_id = $1
+        ORDER BY sess.number, comp.number, abl.number, crt.number
+        "#,
+    )
+    .bind(section_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut competency_order: Vec<String> = Vec::new();
+    let mut grouped: BTreeMap<(i32, String), Vec<CriterionScore>> = BTreeMap::new();
+    let mut observations: BTreeMap<(i32, String), Vec<String>> = BTreeMap::new();
+
+    for row in rows {
+        let student_id: i32 = row.try_get("student_id")?;
+        let competency_name: String = row.try_get("competency_name")?;
+        if !competency_order.contains(&competency_name) {
+            competency_order.push(competency_name.clone());
+        }
+
+        let key = (student_id, competency_name);
+        if let Some(obs) = row.try_get::<Option<String>, _>("observation")? {
+            let obs = obs.trim().to_string();
+            let list = observations.entry(key.clone()).or_default();
+            if !obs.is_empty() && !list.contains(&obs) {
+                list.push(obs);
+            }
+        }
+        grouped.entry(key).or_default().push(CriterionScore {
+            ability_name: row.try_get("ability_name")?,
+            criterion_name: row.try_get("criterion_name")?,
+            value: row.try_get("value")?,
+        });
+    }
+
+    let mut results = Vec::new();
+    for ((student_id, competency_name), criteria) in grouped {
+        let scores: Vec<f64> = criteria
+            .iter()
+            .filter_map(|c| level_score(&c.value))
+            .collect();
+        if scores.is_empty() {
+            continue;
+        }
+        let average = scores.iter().sum::<f64>() / scores.len() as f64;
+        let obs = observations
+            .remove(&(student_id, competency_name.clone()))
+            .unwrap_or_default();
+
+        results.push(CompetencyResult {
+            student_id,
+            competency_name,
+            level: score_level(average).to_string(),
+            average,
+            criteria,
+            observations: obs,
+        });
+    }
+
+    Ok(SectionConsolidation {
+        section,
+        students: students
+            .iter()
+            .map(|r| ConsolidatedStudent {
+                id: r.try_get("id").unwrap(),
+                full_name: r.try_get("full_name").unwrap(),
+                dni: r.try_get("dni").unwrap(),
+            })
+            .collect(),
+        competencies: competency_order,
+        results,
+    })
+}
@@ -0,0 +1,8 @@
+pub mod conclusions;
+pub mod consolidation;
+pub mod models;
+pub mod pdf;
+pub mod report_card;
+pub mod routes;
+pub mod siagie;
+pub mod xlsx;
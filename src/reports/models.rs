@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashMap;
+
+#[derive(Serialize, FromRow, Clone)]
+pub struct SectionInfo {
+    pub section_id: i32,
+    pub section_letter: String,
+    pub grade_number: i32,
+    pub bimester_id: i32,
+    pub bimester_name: String,
+    // Bimestres antiguos pueden no tener año
+    pub year: Option<i32>,
+}
+
+impl SectionInfo {
+    pub fn year_label(&self) -> String {
+        self.year.map(|y| y.to_string()).unwrap_or_default()
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct ConsolidatedStudent {
+    pub id: i32,
+    pub full_name: String,
+    pub dni: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct CriterionScore {
+    pub ability_name: String,
+    pub criterion_name: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct CompetencyResult {
+    pub student_id: i32,
+    pub competency_name: String,
+    pub level: String,
+    pub average: f64,
+    pub criteria: Vec<CriterionScore>,
+    pub observations: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct SectionConsolidation {
+    pub section: SectionInfo,
+    pub students: Vec<ConsolidatedStudent>,
+    pub competencies: Vec<String>,
+    pub results: Vec<CompetencyResult>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct DescriptiveConclusion {
+    pub id: i32,
+    pub student_id: i32,
+    pub student_name: String,
+    pub bimester_id: i32,
+    pub competency_name: String,
+    pub level: String,
+    pub draft_text: String,
+    pub text: String,
+    pub status: String,
+    pub generated_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+    pub approved_by_user_id: Option<i32>,
+    pub approved_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Deserialize)]
+pub struct GenerateConclusionsIn {
+    pub template: Option<String>,
+    pub overwrite_drafts: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct GenerateConclusionsOut {
+    pub created: usize,
+    pub updated: usize,
+    pub skipped_approved: usize,
+    pub conclusions: Vec<DescriptiveConclusion>,
+}
+
+#[derive(Deserialize)]
+pub struct ConclusionFilter {
+    pub status: Option<String>,
+    pub student_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateConclusionIn {
+    pub text: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct SchoolInfo {
+    pub name: String,
+    pub ugel: Option<String>,
+    pub modular_code: Option<String>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct ReportCardStudent {
+    pub student_id: i32,
+    pub full_name: String,
+    pub dni: Option<String>,
+    pub date_of_birth: Option<chrono::NaiveDate>,
+    pub gender: Option<String>,
+    pub enrollment_code: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ReportCardCompetency {
+    pub name: String,
+    pub level: String,
+    pub conclusion: Option<String>,
+}
+
+#[derive(Serialize, Default)]
+pub struct AttendanceSummary {
+    pub sessions: i64,
+    pub present: i64,
+    pub absent: i64,
+    pub late: i64,
+    pub justified: i64,
+}
+
+#[derive(Serialize)]
+pub struct ReportCard {
+    pub school: SchoolInfo,
+    pub section: SectionInfo,
+    pub student: ReportCardStudent,
+    pub competencies: Vec<ReportCardCompetency>,
+    pub attendance: AttendanceSummary,
+    pub tutor_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ReportCardBatchQuery {
+    pub format: Option<String>,
+}
+
+pub struct ConsolidadoAbility {
+    pub id: i32,
+    pub name: String,
+    pub criteria: Vec<(i32, String)>,
+}
+
+pub struct ConsolidadoCompetency {
+    pub id: i32,
+    pub name: String,
+    pub abilities: Vec<ConsolidadoAbility>,
+}
+
+pub struct ConsolidadoSheet {
+    pub session_id: i32,
+    pub number: i32,
+    pub title: Option<String>,
+    pub competencies: Vec<ConsolidadoCompetency>,
+}
+
+pub struct ConsolidadoExport {
+    pub section: SectionInfo,
+    pub students: Vec<(i32, String)>,
+    pub sessions: Vec<ConsolidadoSheet>,
+    pub values: HashMap<(i32, i32), String>,
+    pub observations: HashMap<(i32, i32), String>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct SiagieCompetencyMapping {
+    pub id: i32,
+    pub competency_name: String,
+    pub siagie_code: String,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct SiagieMappingIn {
+    pub competency_name: String,
+    pub siagie_code: String,
+}
+
+#[derive(Serialize)]
+pub struct SiagieStudentIssue {
+    pub student_id: i32,
+    pub full_name: String,
+    pub issue: String,
+}
+
+#[derive(Serialize)]
+pub struct SiagieValidationReport {
+    pub section: SectionInfo,
+    pub exportable_students: usize,
+    pub total_students: usize,
+    pub mapped_competencies: Vec<SiagieCompetencyMapping>,
+    pub unmapped_competencies: Vec<String>,
+    pub students_missing_dni: Vec<SiagieStudentIssue>,
+    pub students_without_identifier: Vec<SiagieStudentIssue>,
+    pub is_valid: bool,
+}
@@ -0,0 +1,256 @@
+use crate::reports::consolidation::level_description;
+use crate::reports::models::ReportCard;
+use printpdf::{
+    BuiltinFont, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference,
+    Point,
+};
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 15.0;
+const LEVEL_COLUMN: f32 = 150.0;
+const TOP: f32 = PAGE_HEIGHT - MARGIN - 5.0;
+
+// Escribe texto de arriba hacia abajo y agrega páginas cuando se acaba el espacio
+struct PageWriter<'a> {
+    doc: &'a PdfDocumentReference,
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    y: f32,
+}
+
+impl<'a> PageWriter<'a> {
+    fn new_page(&mut self) {
+        let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Capa 1");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.y = TOP;
+    }
+
+    fn ensure_space(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            self.new_page();
+        }
+    }
+
+    fn text_at(&self, text: &str, size: f32, x: f32, bold: bool) {
+        let font = if bold { &self.bold } else { &self.regular };
+        self.layer.use_text(text, size, Mm(x), Mm(self.y), font);
+    }
+
+    fn line(&mut self, text: &str, size: f32, bold: bool) {
+        self.ensure_space(size * 0.5);
+        self.text_at(text, size, MARGIN, bold);
+        self.y -= size * 0.5;
+    }
+
+    fn paragraph(&mut self, text: &str, size: f32, x: f32, width: f32) {
+        for chunk in wrap_text(text, max_chars(width, size)) {
+            self.ensure_space(size * 0.45);
+            self.text_at(&chunk, size, x, false);
+            self.y -= size * 0.45;
+        }
+    }
+
+    fn rule(&mut self, x1: f32, x2: f32) {
+        self.layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm(x1), Mm(self.y)), false),
+                (Point::new(Mm(x2), Mm(self.y)), false),
+            ],
+            is_closed: false,
+        });
+    }
+
+    fn gap(&mut self, mm: f32) {
+        self.y -= mm;
+    }
+}
+
+// Aproximación del ancho promedio de Helvetica para partir líneas
+fn max_chars(width_mm: f32, size: f32) -> usize {
+    (width_mm / (size * 0.55 * 0.3528)).max(10.0) as usize
+}
+
+fn wrap_text(text: &str, max: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        if !current.is_empty() && current.chars().count() + word.chars().count() + 1 > max {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+fn draw_report_card(w: &mut PageWriter, card: &ReportCard) {
+    let right = PAGE_WIDTH - MARGIN;
+
+    // Cabecera de la institución
+    w.line(&card.school.name, 14.0, true);
+    let mut school_line = Vec::new();
+    if let Some(ugel) = &card.school.ugel {
+        school_line.push(format!("UGEL: {}", ugel));
+    }
+    if let Some(code) = &card.school.modular_code {
+        school_line.push(format!("Código modular: {}", code));
+    }
+    if !school_line.is_empty() {
+        w.line(&school_line.join("    "), 9.0, false);
+    }
+    w.gap(2.0);
+    w.line(
+        &format!(
+            "INFORME DE PROGRESO DEL APRENDIZAJE - {} BIMESTRE {}",
+            card.section.bimester_name,
+            card.section.year_label()
+        ),
+        12.0,
+        true,
+    );
+    w.rule(MARGIN, right);
+    w.gap(5.0);
+
+    // Datos del estudiante
+    let student = &card.student;
+    w.line(&format!("Estudiante: {}", student.full_name), 10.0, false);
+    w.line(
+        &format!(
+            "DNI: {}    Código de matrícula: {}",
+            student.dni.as_deref().unwrap_or("-"),
+            student.enrollment_code.as_deref().unwrap_or("-")
+        ),
+        10.0,
+        false,
+    );
+    w.line(
+        &format!(
+            "Fecha de nacimiento: {}    Sexo: {}",
+            student
+                .date_of_birth
+                .map(|d| d.format("%d/%m/%Y").to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            student.gender.as_deref().unwrap_or("-")
+        ),
+        10.0,
+        false,
+    );
+    w.line(
+        &format!(
+            "Grado: {}°    Sección: {}    Bimestre: {}",
+            card.section.grade_number, card.section.section_letter, card.section.bimester_name
+        ),
+        10.0,
+        false,
+    );
+    w.gap(3.0);
+
+    // Tabla de competencias
+    w.ensure_space(12.0);
+    w.rule(MARGIN, right);
+    w.gap(4.5);
+    w.text_at("Competencia", 10.0, MARGIN, true);
+    w.text_at("Nivel de logro", 10.0, LEVEL_COLUMN, true);
+    w.gap(2.0);
+    w.rule(MARGIN, right);
+    w.gap(4.5);
+
+    if card.competencies.is_empty() {
+        w.line("Sin calificaciones registradas en el bimestre.", 9.0, false);
+    }
+    for comp in &card.competencies {
+        let name_lines = wrap_text(&comp.name, max_chars(LEVEL_COLUMN - MARGIN - 5.0, 9.5));
+        w.ensure_space(name_lines.len() as f32 * 4.5 + 2.0);
+        w.text_at(
+            &format!("{} ({})", comp.level, level_description(&comp.level)),
+            9.5,
+            LEVEL_COLUMN,
+            true,
+        );
+        for l in name_lines {
+            w.text_at(&l, 9.5, MARGIN, false);
+            w.gap(4.5);
+        }
+        if let Some(conclusion) = &comp.conclusion {
+            w.gap(0.5);
+            w.paragraph(
+                &format!("Conclusión descriptiva: {}", conclusion),
+                8.5,
+                MARGIN + 5.0,
+                right - MARGIN - 5.0,
+            );
+        }
+        w.gap(0.5);
+        w.rule(MARGIN, right);
+        w.gap(4.5);
+    }
+
+    // Asistencia
+    w.gap(2.0);
+    w.line("Resumen de asistencia", 10.0, true);
+    let a = &card.attendance;
+    w.paragraph(
+        &format!(
+            "Sesiones registradas: {}. Asistió: {}. Tardanzas: {}. Faltas justificadas: {}. Faltas injustificadas: {}.",
+            a.sessions, a.present, a.late, a.justified, a.absent
+        ),
+        9.0,
+        MARGIN,
+        right - MARGIN,
+    );
+
+    // Firmas
+    w.ensure_space(35.0);
+    w.gap(25.0);
+    let col = (PAGE_WIDTH - 2.0 * MARGIN) / 3.0;
+    for i in 0..3 {
+        let x = MARGIN + col * i as f32;
+        w.rule(x + 5.0, x + col - 5.0);
+    }
+    w.gap(4.0);
+    for (i, label) in ["Tutor(a)", "Dirección", "Padre, madre o apoderado"]
+        .iter()
+        .enumerate()
+    {
+        w.text_at(label, 9.0, MARGIN + col * i as f32 + 5.0, false);
+    }
+    if let Some(tutor) = &card.tutor_name {
+        w.gap(4.0);
+        w.text_at(tutor, 8.0, MARGIN + 5.0, false);
+    }
+}
+
+// Genera un solo PDF con una libreta por alumno, cada una empezando en página nueva
+pub fn render_report_cards(title: &str, cards: &[ReportCard]) -> Result<Vec<u8>, printpdf::Error> {
+    let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Capa 1");
+    let regular = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+    let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+
+    let mut writer = PageWriter {
+        layer: doc.get_page(page).get_layer(layer),
+        doc: &doc,
+        regular,
+        bold,
+        y: TOP,
+    };
+
+    if cards.is_empty() {
+        writer.line("La sección no tiene alumnos registrados.", 10.0, false);
+    }
+    for (i, card) in cards.iter().enumerate() {
+        if i > 0 {
+            writer.new_page();
+        }
+        draw_report_card(&mut writer, card);
+    }
+    drop(writer);
+
+    doc.save_to_bytes()
+}
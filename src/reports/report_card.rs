@@ -0,0 +1,136 @@
+use crate::reports::consolidation::consolidate_section;
+use crate::reports::models::*;
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::env;
+
+// Datos de la institución para la cabecera de la libreta
+pub fn school_info() -> SchoolInfo {
+    SchoolInfo {
+        name: env::var("SCHOOL_NAME").unwrap_or_else(|_| "Institución Educativa".to_string()),
+        ugel: env::var("SCHOOL_UGEL").ok(),
+        modular_code: env::var("SCHOOL_MODULAR_CODE").ok(),
+    }
+}
+
+// Arma las libretas de una sección (o de un solo alumno si se indica student_id).
+// Solo se imprimen las conclusiones descriptivas aprobadas.
+pub async fn build_report_cards(
+    pool: &PgPool,
+    section_id: i32,
+    student_id: Option<i32>,
+) -> Result<Vec<ReportCard>, sqlx::Error> {
+    let consolidation = consolidate_section(pool, section_id).await?;
+    let school = school_info();
+
+    let students = sqlx::query_as::<_, ReportCardStudent>(
+        r#"
+        SELECT s.id AS student_id,
+               COALESCE(sp.full_name, s.full_name) AS full_name,
+               COALESCE(sp.dni, s.dni) AS dni,
+               sp.date_of_birth, sp.gender, sp.enrollment_code
+        FROM students s
+        LEFT JOIN student_profiles sp ON sp.user_id = s.user_id
+        WHERE s.section_id = $1 AND ($2::int IS NULL OR s.id = $2)
+        ORDER BY s.full_name
+        "#,
+    )
+    .bind(section_id)
+    .bind(student_id)
+    .fetch_all(pool)
+    .await?;
+
+    let conclusions: HashMap<(i32, String), String> = sqlx::query(
+        r#"
+        SELECT dc.student_id, dc.competency_name, dc.text
+        FROM descriptive_conclusions dc
+        JOIN students s ON s.id = dc.student_id
+        WHERE s.section_id = $1 AND dc.bimester_id = $2 AND dc.status = 'approved'
+        "#,
+    )
+    .bind(section_id)
+    .bind(consolidation.section.bimester_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|r| {
+        (
+            (r.get("student_id"), r.get("competency_name")),
+            r.get("text"),
+        )
+    })
+    .collect();
+
+    let mut attendance: HashMap<i32, AttendanceSummary> = HashMap::new();
+    for row in sqlx::query(
+        r#"
+        SELECT ar.student_id, ar.status, COUNT(*) AS total
+        FROM attendance_records ar
+        JOIN sessions sess ON sess.id = ar.session_id
+        WHERE sess.section_id = $1
+        GROUP BY ar.student_id, ar.status
+        "#,
+    )
+    .bind(section_id)
+    .fetch_all(pool)
+    .await?
+    {
+        let summary = attendance.entry(row.get("student_id")).or_default();
+        let total: i64 = row.get("total");
+        summary.sessions += total;
+        match row.get::<String, _>("status").as_str() {
+            "present" => summary.present += total,
+            "absent" => summary.absent += total,
+            "late" => summary.late += total,
+            _ => summary.justified += total,
+        }
+    }
+
+    let tutor_name = sqlx::query_scalar::<_, Option<String>>(
+        r#"
+        SELECT tp.full_name
+        FROM section_teachers st
+        LEFT JOIN teacher_profiles tp ON tp.user_id = st.teacher_user_id
+        WHERE st.section_id = $1
+        ORDER BY st.is_tutor DESC, st.assigned_at
+        LIMIT 1
+        "#,
+    )
+    .bind(section_id)
+    .fetch_optional(pool)
+    .await?
+    .flatten();
+
+    let cards = students
+        .into_iter()
+        .map(|student| {
+            let competencies = consolidation
+                .competencies
+                .iter()
+                .filter_map(|name| {
+                    let result = consolidation.results.iter().find(|r| {
+                        r.student_id == student.student_id && &r.competency_name == name
+                    })?;
+                    Some(ReportCardCompetency {
+                        name: name.clone(),
+                        level: result.level.clone(),
+                        conclusion: conclusions
+                            .get(&(student.student_id, name.clone()))
+                            .cloned(),
+                    })
+                })
+                .collect();
+
+            ReportCard {
+                school: school.clone(),
+                section: consolidation.section.clone(),
+                attendance: attendance.remove(&student.student_id).unwrap_or_default(),
+                competencies,
+                tutor_name: tutor_name.clone(),
+                student,
+            }
+        })
+        .collect();
+
+    Ok(cards)
+}
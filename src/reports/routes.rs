@@ -0,0 +1,696 @@
+use crate::auth::guard::require_role;
+use crate::auth::models::UserRole;
+use crate::reports::conclusions::{
+    draft_conclusion, DEFAULT_TEMPLATE, LEVELS_REQUIRING_CONCLUSION,
+};
+use crate::reports::consolidation::consolidate_section;
+use crate::reports::models::*;
+use crate::reports::pdf::render_report_cards;
+use crate::reports::report_card::build_report_cards;
+use crate::reports::siagie::{build_siagie_workbook, prepare_siagie_export, MAPPING_SELECT};
+use crate::reports::xlsx::{build_consolidado_workbook, fetch_consolidado_sheets};
+use crate::AppState;
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use sqlx::PgPool;
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+const CONCLUSION_SELECT: &str = r#"
+    SELECT dc.id, dc.student_id, s.full_name AS student_name, dc.bimester_id,
+           dc.competency_name, dc.level, dc.draft_text, dc.text, dc.status,
+           dc.generated_at, dc.updated_at, dc.approved_by_user_id, dc.approved_at
+    FROM descriptive_conclusions dc
+    JOIN students s ON s.id = dc.student_id
+"#;
+
+async fn fetch_section_conclusions(
+    pool: &PgPool,
+    section_id: i32,
+    status: Option<&str>,
+    student_id: Option<i32>,
+) -> Result<Vec<DescriptiveConclusion>, sqlx::Error> {
+    let sql = format!(
+        r#"
+        {}
+        WHERE s.section_id = $1
+          AND dc.bimester_id = (
+              SELECT g.bimester_id FROM sections sec JOIN grades g ON g.id = sec.grade_id
+              WHERE sec.id = $1
+          )
+          AND ($2::text IS NULL OR dc.status = $2)
+          AND ($3::int IS NULL OR dc.student_id = $3)
+        ORDER BY s.full_name, dc.competency_name
+        "#,
+        CONCLUSION_SELECT
+    );
+
+    sqlx::query_as::<_, DescriptiveConclusion>(&sql)
+        .bind(section_id)
+        .bind(status)
+        .bind(student_id)
+        .fetch_all(pool)
+        .await
+}
+
+#[get("/sections/{sec_id}/competency-levels")]
+pub async fn get_competency_levels(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+
+    match consolidate_section(&data.pool, path.into_inner()).await {
+        Ok(c) => HttpResponse::Ok().json(c),
+        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("Sección no encontrada"),
+        Err(e) => {
+            eprintln!("Error consolidating section: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+// Genera borradores de conclusión para las competencias en nivel B o C.
+// Las conclusiones aprobadas no se tocan; los borradores solo se regeneran con overwrite_drafts.
+#[post("/sections/{sec_id}/conclusions/generate")]
+pub async fn generate_conclusions(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    body: web::Json<GenerateConclusionsIn>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+    let section_id = path.into_inner();
+
+    let consolidation = match consolidate_section(&data.pool, section_id).await {
+        Ok(c) => c,
+        Err(sqlx::Error::RowNotFound) => {
+            return HttpResponse::NotFound().body("Sección no encontrada")
+        }
+        Err(e) => {
+            eprintln!("Error consolidating section: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+
+    let template = body
+        .template
+        .as_deref()
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or(DEFAULT_TEMPLATE);
+    let overwrite = body.overwrite_drafts.unwrap_or(false);
+    let bimester_id = consolidation.section.bimester_id;
+
+    let mut tx = match data.pool.begin().await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+
+    let (mut created, mut updated, mut skipped_approved) = (0, 0, 0);
+    for result in consolidation
+        .results
+        .iter()
+        .filter(|r| LEVELS_REQUIRING_CONCLUSION.contains(&r.level.as_str()))
+    {
+        let Some(student) = consolidation
+            .students
+            .iter()
+            .find(|s| s.id == result.student_id)
+        else {
+            continue;
+        };
+        let draft = draft_conclusion(template, &student.full_name, result);
+
+        let existing = match sqlx::query_as::<_, (i32, String)>(
+            "SELECT id, status FROM descriptive_conclusions
+             WHERE student_id = $1 AND bimester_id = $2 AND competency_name = $3
+             FOR UPDATE",
+        )
+        .bind(result.student_id)
+        .bind(bimester_id)
+        .bind(&result.competency_name)
+        .fetch_optional(&mut *tx)
+        .await
+        {
+            Ok(row) => row,
+            Err(e) => {
+                eprintln!("Error fetching conclusion: {:?}", e);
+                return HttpResponse::InternalServerError().body("Error en la base de datos");
+            }
+        };
+
+        let outcome = match existing {
+            Some((_, status)) if status == "approved" => {
+                skipped_approved += 1;
+                continue;
+            }
+            Some(_) if !overwrite => continue,
+            Some((id, _)) => {
+                updated += 1;
+                sqlx::query(
+                    "UPDATE descriptive_conclusions
+                     SET level = $1, draft_text = $2, text = $2, generated_at = NOW(), updated_at = NOW()
+                     WHERE id = $3",
+                )
+                .bind(&result.level)
+                .bind(&draft)
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+            }
+            None => {
+                created += 1;
+                sqlx::query(
+                    "INSERT INTO descriptive_conclusions
+                     (student_id, bimester_id, competency_name, level, draft_text, text)
+                     VALUES ($1, $2, $3, $4, $5, $5)",
+                )
+                .bind(result.student_id)
+                .bind(bimester_id)
+                .bind(&result.competency_name)
+                .bind(&result.level)
+                .bind(&draft)
+                .execute(&mut *tx)
+                .await
+            }
+        };
+        if let Err(e) = outcome {
+            eprintln!("Error saving conclusion: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error guardando conclusiones");
+        }
+    }
+
+    if let Err(e) = tx.commit().await {
+        eprintln!("Error committing conclusions: {:?}", e);
+        return HttpResponse::InternalServerError().body("Error guardando conclusiones");
+    }
+
+    match fetch_section_conclusions(&data.pool, section_id, None, None).await {
+        Ok(conclusions) => HttpResponse::Ok().json(GenerateConclusionsOut {
+            created,
+            updated,
+            skipped_approved,
+            conclusions,
+        }),
+        Err(e) => {
+            eprintln!("Error fetching conclusions: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+#[get("/sections/{sec_id}/conclusions")]
+pub async fn list_conclusions(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    query: web::Query<ConclusionFilter>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+
+    match fetch_section_conclusions(
+        &data.pool,
+        path.into_inner(),
+        query.status.as_deref(),
+        query.student_id,
+    )
+    .await
+    {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => {
+            eprintln!("Error fetching conclusions: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+async fn fetch_conclusion(pool: &PgPool, id: i32) -> Result<DescriptiveConclusion, sqlx::Error> {
+    let sql = format!("{} WHERE dc.id = $1", CONCLUSION_SELECT);
+    sqlx::query_as::<_, DescriptiveConclusion>(&sql)
+        .bind(id)
+        .fetch_one(pool)
+        .await
+}
+
+// Editar el texto devuelve la conclusión a borrador: debe aprobarse de nuevo
+#[put("/conclusions/{id}")]
+pub async fn update_conclusion(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    body: web::Json<UpdateConclusionIn>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+    let id = path.into_inner();
+
+    let text = body.text.trim();
+    if text.is_empty() {
+        return HttpResponse::BadRequest().body("El texto no puede estar vacío");
+    }
+
+    match sqlx::query(
+        "UPDATE descriptive_conclusions
+         SET text = $1, status = 'draft', approved_by_user_id = NULL, approved_at = NULL,
+             updated_at = NOW()
+         WHERE id = $2",
+    )
+    .bind(text)
+    .bind(id)
+    .execute(&data.pool)
+    .await
+    {
+        Ok(r) if r.rows_affected() == 0 => {
+            return HttpResponse::NotFound().body("Conclusión no encontrada")
+        }
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("Error updating conclusion: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    }
+
+    match fetch_conclusion(&data.pool, id).await {
+        Ok(c) => HttpResponse::Ok().json(c),
+        Err(e) => {
+            eprintln!("Error fetching conclusion: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+#[post("/conclusions/{id}/approve")]
+pub async fn approve_conclusion(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user = match require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        Ok(u) => u,
+        Err(resp) => return resp,
+    };
+    let id = path.into_inner();
+
+    match sqlx::query(
+        "UPDATE descriptive_conclusions
+         SET status = 'approved', approved_by_user_id = $1, approved_at = NOW(), updated_at = NOW()
+         WHERE id = $2",
+    )
+    .bind(user.id)
+    .bind(id)
+    .execute(&data.pool)
+    .await
+    {
+        Ok(r) if r.rows_affected() == 0 => {
+            return HttpResponse::NotFound().body("Conclusión no encontrada")
+        }
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("Error approving conclusion: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    }
+
+    match fetch_conclusion(&data.pool, id).await {
+        Ok(c) => HttpResponse::Ok().json(c),
+        Err(e) => {
+            eprintln!("Error fetching conclusion: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+// Nombre de archivo seguro a partir del nombre del alumno
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    stem.trim_matches('_').to_string()
+}
+
+fn pdf_response(filename: &str, bytes: Vec<u8>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/pdf")
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", filename),
+        ))
+        .body(bytes)
+}
+
+#[get("/students/{student_id}/report-card")]
+pub async fn get_report_card(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+    let student_id = path.into_inner();
+
+    let section_id =
+        match sqlx::query_scalar::<_, i32>("SELECT section_id FROM students WHERE id = $1")
+            .bind(student_id)
+            .fetch_optional(&data.pool)
+            .await
+        {
+            Ok(Some(id)) => id,
+            Ok(None) => return HttpResponse::NotFound().body("Alumno no encontrado"),
+            Err(e) => {
+                eprintln!("Error fetching student: {:?}", e);
+                return HttpResponse::InternalServerError().body("Error en la base de datos");
+            }
+        };
+
+    let cards = match build_report_cards(&data.pool, section_id, Some(student_id)).await {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Error building report card: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+    let Some(card) = cards.first() else {
+        return HttpResponse::NotFound().body("Alumno no encontrado");
+    };
+
+    let title = format!("Libreta {}", card.student.full_name);
+    let filename = format!("libreta_{}.pdf", file_stem(&card.student.full_name));
+    match render_report_cards(&title, &cards) {
+        Ok(bytes) => pdf_response(&filename, bytes),
+        Err(e) => {
+            eprintln!("Error rendering report card: {:?}", e);
+            HttpResponse::InternalServerError().body("Error generando el PDF")
+        }
+    }
+}
+
+// Libretas de toda la sección: un PDF con todas (format=pdf, por defecto)
+// o un ZIP con un PDF por alumno (format=zip)
+#[get("/sections/{sec_id}/report-cards")]
+pub async fn get_section_report_cards(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    query: web::Query<ReportCardBatchQuery>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+    let section_id = path.into_inner();
+
+    let format = query.format.as_deref().unwrap_or("pdf");
+    if format != "pdf" && format != "zip" {
+        return HttpResponse::BadRequest().body("Formato inválido (pdf|zip)");
+    }
+
+    let cards = match build_report_cards(&data.pool, section_id, None).await {
+        Ok(c) => c,
+        Err(sqlx::Error::RowNotFound) => {
+            return HttpResponse::NotFound().body("Sección no encontrada")
+        }
+        Err(e) => {
+            eprintln!("Error building report cards: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+
+    let base_name = match cards.first() {
+        Some(c) => format!(
+            "libretas_{}{}_{}",
+            c.section.grade_number,
+            c.section.section_letter,
+            file_stem(&c.section.bimester_name)
+        ),
+        None => format!("libretas_seccion_{}", section_id),
+    };
+
+    if format == "pdf" {
+        return match render_report_cards(&base_name, &cards) {
+            Ok(bytes) => pdf_response(&format!("{}.pdf", base_name), bytes),
+            Err(e) => {
+                eprintln!("Error rendering report cards: {:?}", e);
+                HttpResponse::InternalServerError().body("Error generando el PDF")
+            }
+        };
+    }
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for card in cards {
+        let stem = format!(
+            "{}_{}",
+            card.student.student_id,
+            file_stem(&card.student.full_name)
+        );
+        let bytes = match render_report_cards(&card.student.full_name, std::slice::from_ref(&card))
+        {
+            Ok(b) => b,
+            Err(e) => {
+                eprintln!("Error rendering report card: {:?}", e);
+                return HttpResponse::InternalServerError().body("Error generando el PDF");
+            }
+        };
+        let written = zip
+            .start_file(format!("{}.pdf", stem), SimpleFileOptions::default())
+            .map_err(|e| e.to_string())
+            .and_then(|_| zip.write_all(&bytes).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            eprintln!("Error writing zip entry: {}", e);
+            return HttpResponse::InternalServerError().body("Error generando el ZIP");
+        }
+    }
+
+    match zip.finish() {
+        Ok(cursor) => HttpResponse::Ok()
+            .content_type("application/zip")
+            .insert_header((
+                "Content-Disposition",
+                format!("attachment; filename=\"{}.zip\"", base_name),
+            ))
+            .body(cursor.into_inner()),
+        Err(e) => {
+            eprintln!("Error finishing zip: {:?}", e);
+            HttpResponse::InternalServerError().body("Error generando el ZIP")
+        }
+    }
+}
+
+// Consolidado en Excel: una hoja por sesión con encabezados combinados
+// competencia → capacidad → criterio y colores por nivel
+#[get("/sections/{section_id}/consolidado/xlsx")]
+pub async fn export_consolidado_xlsx(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+
+    let export = match fetch_consolidado_sheets(&data.pool, path.into_inner()).await {
+        Ok(e) => e,
+        Err(sqlx::Error::RowNotFound) => {
+            return HttpResponse::NotFound().body("Sección no encontrada")
+        }
+        Err(e) => {
+            eprintln!("Error fetching consolidado: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+
+    let filename = format!(
+        "consolidado_{}{}_{}.xlsx",
+        export.section.grade_number,
+        export.section.section_letter,
+        file_stem(&export.section.bimester_name)
+    );
+    match build_consolidado_workbook(&export) {
+        Ok(bytes) => HttpResponse::Ok()
+            .content_type("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet")
+            .insert_header((
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", filename),
+            ))
+            .body(bytes),
+        Err(e) => {
+            eprintln!("Error building xlsx: {:?}", e);
+            HttpResponse::InternalServerError().body("Error generando el Excel")
+        }
+    }
+}
+
+#[get("/siagie/competency-mappings")]
+pub async fn list_siagie_mappings(req: HttpRequest, data: web::Data<AppState>) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+
+    let sql = format!("{} ORDER BY competency_name", MAPPING_SELECT);
+    match sqlx::query_as::<_, SiagieCompetencyMapping>(&sql)
+        .fetch_all(&data.pool)
+        .await
+    {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => {
+            eprintln!("Error fetching siagie mappings: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+// Crea o actualiza el código SIAGIE de una competencia (por nombre, sin distinguir mayúsculas)
+#[put("/siagie/competency-mappings")]
+pub async fn upsert_siagie_mapping(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    body: web::Json<SiagieMappingIn>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Admin]).await {
+        return resp;
+    }
+
+    let name = body.competency_name.trim();
+    let code = body.siagie_code.trim();
+    if name.is_empty() || code.is_empty() {
+        return HttpResponse::BadRequest().body("Competencia y código son obligatorios");
+    }
+
+    match sqlx::query_as::<_, SiagieCompetencyMapping>(
+        r#"
+        INSERT INTO siagie_competency_mappings (competency_name, siagie_code)
+        VALUES ($1, $2)
+        ON CONFLICT ((LOWER(TRIM(competency_name))))
+        DO UPDATE SET siagie_code = EXCLUDED.siagie_code, updated_at = NOW()
+        RETURNING id, competency_name, siagie_code, updated_at
+        "#,
+    )
+    .bind(name)
+    .bind(code)
+    .fetch_one(&data.pool)
+    .await
+    {
+        Ok(m) => HttpResponse::Ok().json(m),
+        Err(e) => {
+            eprintln!("Error saving siagie mapping: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+#[delete("/siagie/competency-mappings/{id}")]
+pub async fn delete_siagie_mapping(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Admin]).await {
+        return resp;
+    }
+
+    match sqlx::query("DELETE FROM siagie_competency_mappings WHERE id = $1")
+        .bind(path.into_inner())
+        .execute(&data.pool)
+        .await
+    {
+        Ok(r) if r.rows_affected() == 0 => HttpResponse::NotFound().body("Mapeo no encontrado"),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            eprintln!("Error deleting siagie mapping: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+#[get("/sections/{sec_id}/siagie/validation")]
+pub async fn validate_siagie_export(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+
+    match prepare_siagie_export(&data.pool, path.into_inner()).await {
+        Ok(export) => HttpResponse::Ok().json(export.report),
+        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().body("Sección no encontrada"),
+        Err(e) => {
+            eprintln!("Error preparing siagie export: {:?}", e);
+            HttpResponse::InternalServerError().body("Error en la base de datos")
+        }
+    }
+}
+
+// Planilla para la carga masiva en SIAGIE. Se genera aunque haya observaciones;
+// los alumnos sin identificador y las competencias sin código quedan fuera y
+// se listan en la hoja de validación.
+#[get("/sections/{sec_id}/siagie/export")]
+pub async fn export_siagie(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = require_role(&data.pool, &req, &[UserRole::Docente, UserRole::Admin]).await {
+        return resp;
+    }
+
+    let export = match prepare_siagie_export(&data.pool, path.into_inner()).await {
+        Ok(e) => e,
+        Err(sqlx::Error::RowNotFound) => {
+            return HttpResponse::NotFound().body("Sección no encontrada")
+        }
+        Err(e) => {
+            eprintln!("Error preparing siagie export: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error en la base de datos");
+        }
+    };
+
+    let section = &export.report.section;
+    let filename = format!(
+        "siagie_{}{}_{}.xlsx",
+        section.grade_number,
+        section.section_letter,
+        file_stem(&section.bimester_name)
+    );
+    match build_siagie_workbook(&export) {
+        Ok(bytes) => HttpResponse::Ok()
+            .content_type("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet")
+            .insert_header((
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", filename),
+            ))
+            .body(bytes),
+        Err(e) => {
+            eprintln!("Error building siagie xlsx: {:?}", e);
+            HttpResponse::InternalServerError().body("Error generando el Excel")
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_competency_levels)
+        .service(generate_conclusions)
+        .service(list_conclusions)
+        .service(update_conclusion)
+        .service(approve_conclusion)
+        .service(get_report_card)
+        .service(get_section_report_cards)
+        .service(export_consolidado_xlsx)
+        .service(list_siagie_mappings)
+        .service(upsert_siagie_mapping)
+        .service(delete_siagie_mapping)
+        .service(validate_siagie_export)
+        .service(export_siagie);
+}
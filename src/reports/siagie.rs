@@ -0,0 +1,273 @@
+use crate::reports::consolidation::consolidate_section;
+use crate::reports::models::*;
+use crate::reports::report_card::school_info;
+use rust_xlsxwriter::{Color, Format, FormatBorder, Workbook, XlsxError};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+
+pub const MAPPING_SELECT: &str =
+    "SELECT id, competency_name, siagie_code, updated_at FROM siagie_competency_mappings";
+
+struct SiagieStudent {
+    id: i32,
+    full_name: String,
+    dni: Option<String>,
+    enrollment_code: Option<String>,
+}
+
+pub struct SiagieExport {
+    consolidation: SectionConsolidation,
+    students: Vec<SiagieStudent>,
+    conclusions: HashMap<(i32, String), String>,
+    pub report: SiagieValidationReport,
+}
+
+fn competency_key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+// Reúne los niveles consolidados del bimestre y valida lo que SIAGIE necesita:
+// un identificador por alumno (DNI o código de matrícula) y un código por competencia
+pub async fn prepare_siagie_export(
+    pool: &PgPool,
+    section_id: i32,
+) -> Result<SiagieExport, sqlx::Error> {
+    let consolidation = consolidate_section(pool, section_id).await?;
+
+    let mut mappings: HashMap<String, SiagieCompetencyMapping> =
+        sqlx::query_as::<_, SiagieCompetencyMapping>(MAPPING_SELECT)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .map(|m| (competency_key(&m.competency_name), m))
+            .collect();
+
+    let students: Vec<SiagieStudent> = sqlx::query(
+        r#"
+        SELECT s.id, s.full_name,
+               NULLIF(TRIM(COALESCE(s.dni, sp.dni)), '') AS dni,
+               NULLIF(TRIM(sp.enrollment_code), '') AS enrollment_code
+        FROM students s
+        LEFT JOIN student_profiles sp ON sp.user_id = s.user_id
+        WHERE s.section_id = $1
+        ORDER BY s.full_name
+        "#,
+    )
+    .bind(section_id)
+    .fetch_all(pool)
+    .await?
+    .iter()
+    .map(|r| SiagieStudent {
+        id: r.get("id"),
+        full_name: r.get("full_name"),
+        dni: r.get("dni"),
+        enrollment_code: r.get("enrollment_code"),
+    })
+    .collect();
+
+    let conclusions = sqlx::query(
+        r#"
+        SELECT dc.student_id, dc.competency_name, dc.text
+        FROM descriptive_conclusions dc
+        JOIN students s ON s.id = dc.student_id
+        WHERE s.section_id = $1 AND dc.bimester_id = $2 AND dc.status = 'approved'
+        "#,
+    )
+    .bind(section_id)
+    .bind(consolidation.section.bimester_id)
+    .fetch_all(pool)
+    .await?
+    .iter()
+    .map(|r| {
+        (
+            (r.get("student_id"), r.get("competency_name")),
+            r.get("text"),
+        )
+    })
+    .collect();
+
+    let mut mapped_competencies = Vec::new();
+    let mut unmapped_competencies = Vec::new();
+    for name in &consolidation.competencies {
+        match mappings.remove(&competency_key(name)) {
+            Some(m) => mapped_competencies.push(SiagieCompetencyMapping {
+                competency_name: name.clone(),
+                ..m
+            }),
+            None => unmapped_competencies.push(name.clone()),
+        }
+    }
+
+    let issue = |s: &SiagieStudent, text: &str| SiagieStudentIssue {
+        student_id: s.id,
+        full_name: s.full_name.clone(),
+        issue: text.to_string(),
+    };
+    let students_missing_dni: Vec<SiagieStudentIssue> = students
+        .iter()
+        .filter(|s| s.dni.is_none())
+        .map(|s| issue(s, "Sin DNI registrado"))
+        .collect();
+    let students_without_identifier: Vec<SiagieStudentIssue> = students
+        .iter()
+        .filter(|s| s.dni.is_none() && s.enrollment_code.is_none())
+        .map(|s| issue(s, "Sin DNI ni código de matrícula: no se puede exportar"))
+        .collect();
+
+    let report = SiagieValidationReport {
+        section: consolidation.section.clone(),
+        exportable_students: students.len() - students_without_identifier.len(),
+        total_students: students.len(),
+        is_valid: unmapped_competencies.is_empty() && students_without_identifier.is_empty(),
+        mapped_competencies,
+        unmapped_competencies,
+        students_missing_dni,
+        students_without_identifier,
+    };
+
+    Ok(SiagieExport {
+        consolidation,
+        students,
+        conclusions,
+        report,
+    })
+}
+
+// Planilla de carga masiva: por cada competencia mapeada, una columna con el
+// nivel de logro y otra con la conclusión descriptiva. Incluye una hoja de validación.
+pub fn build_siagie_workbook(export: &SiagieExport) -> Result<Vec<u8>, XlsxError> {
+    let mut workbook = Workbook::new();
+    let bold = Format::new().set_bold();
+    let header = Format::new()
+        .set_bold()
+        .set_text_wrap()
+        .set_border(FormatBorder::Thin)
+        .set_background_color(Color::RGB(0xD9E1F2));
+    let cell = Format::new().set_border(FormatBorder::Thin);
+
+    let report = &export.report;
+    let section = &report.section;
+    let school = school_info();
+
+    let ws = workbook.add_worksheet();
+    ws.set_name("Notas")?;
+    ws.write_string_with_format(0, 0, "Código modular", &bold)?;
+    ws.write_string(0, 1, school.modular_code.as_deref().unwrap_or(""))?;
+    ws.write_string_with_format(1, 0, "Periodo", &bold)?;
+    ws.write_string(
+        1,
+        1,
+        format!(
+            "{} - Bimestre {}",
+            section.year_label(),
+            section.bimester_name
+        ),
+    )?;
+    ws.write_string_with_format(2, 0, "Grado", &bold)?;
+    ws.write_number(2, 1, section.grade_number)?;
+    ws.write_string_with_format(2, 2, "Sección", &bold)?;
+    ws.write_string(2, 3, &section.section_letter)?;
+
+    let header_row = 4;
+    for (col, title) in ["N°", "Código del estudiante", "DNI", "Apellidos y nombres"]
+        .iter()
+        .enumerate()
+    {
+        ws.write_string_with_format(header_row, col as u16, *title, &header)?;
+        ws.write_string_with_format(header_row + 1, col as u16, "", &header)?;
+    }
+    let mut col: u16 = 4;
+    for m in &report.mapped_competencies {
+        ws.write_string_with_format(header_row, col, &m.siagie_code, &header)?;
+        ws.write_string_with_format(header_row + 1, col, &m.competency_name, &header)?;
+        ws.write_string_with_format(
+            header_row,
+            col + 1,
+            format!("{} CD", m.siagie_code),
+            &header,
+        )?;
+        ws.write_string_with_format(header_row + 1, col + 1, "Conclusión descriptiva", &header)?;
+        ws.set_column_width(col, 12)?;
+        ws.set_column_width(col + 1, 40)?;
+        col += 2;
+    }
+
+    let levels: HashMap<(i32, &str), &str> = export
+        .consolidation
+        .results
+        .iter()
+        .map(|r| ((r.student_id, r.competency_name.as_str()), r.level.as_str()))
+        .collect();
+
+    let mut row = header_row + 2;
+    let mut n = 0;
+    for student in &export.students {
+        if student.dni.is_none() && student.enrollment_code.is_none() {
+            continue;
+        }
+        n += 1;
+        ws.write_number_with_format(row, 0, n, &cell)?;
+        ws.write_string_with_format(
+            row,
+            1,
+            student.enrollment_code.as_deref().unwrap_or(""),
+            &cell,
+        )?;
+        ws.write_string_with_format(row, 2, student.dni.as_deref().unwrap_or(""), &cell)?;
+        ws.write_string_with_format(row, 3, &student.full_name, &cell)?;
+
+        let mut col: u16 = 4;
+        for m in &report.mapped_competencies {
+            let level = levels
+                .get(&(student.id, m.competency_name.as_str()))
+                .copied()
+                .unwrap_or("");
+            let conclusion = export
+                .conclusions
+                .get(&(student.id, m.competency_name.clone()))
+                .map(String::as_str)
+                .unwrap_or("");
+            ws.write_string_with_format(row, col, level, &cell)?;
+            ws.write_string_with_format(row, col + 1, conclusion, &cell)?;
+            col += 2;
+        }
+        row += 1;
+    }
+    ws.set_column_width(1, 18)?;
+    ws.set_column_width(2, 12)?;
+    ws.set_column_width(3, 40)?;
+    ws.set_freeze_panes(header_row + 2, 4)?;
+
+    let ws = workbook.add_worksheet();
+    ws.set_name("Validación")?;
+    ws.write_string_with_format(0, 0, "Tipo", &header)?;
+    ws.write_string_with_format(0, 1, "Detalle", &header)?;
+    ws.write_string_with_format(0, 2, "Observación", &header)?;
+    let mut row = 1;
+    for name in &report.unmapped_competencies {
+        ws.write_string(row, 0, "Competencia sin código SIAGIE")?;
+        ws.write_string(row, 1, name)?;
+        ws.write_string(row, 2, "No se incluye en la planilla")?;
+        row += 1;
+    }
+    for missing in &report.students_missing_dni {
+        // Si además no tiene código de matrícula, se reporta el problema más grave
+        let issue = report
+            .students_without_identifier
+            .iter()
+            .find(|i| i.student_id == missing.student_id)
+            .unwrap_or(missing);
+        ws.write_string(row, 0, "Estudiante")?;
+        ws.write_string(row, 1, &issue.full_name)?;
+        ws.write_string(row, 2, &issue.issue)?;
+        row += 1;
+    }
+    if row == 1 {
+        ws.write_string(1, 0, "Sin observaciones")?;
+    }
+    ws.set_column_width(0, 30)?;
+    ws.set_column_width(1, 45)?;
+    ws.set_column_width(2, 55)?;
+
+    workbook.save_to_buffer()
+}
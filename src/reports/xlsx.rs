@@ -0,0 +1,325 @@
+use crate::reports::consolidation::fetch_section_info;
+use crate::reports::models::*;
+use rust_xlsxwriter::{
+    Color, ConditionalFormatCell, ConditionalFormatCellRule, Format, FormatAlign, FormatBorder,
+    Workbook, Worksheet, XlsxError,
+};
+use sqlx::{PgPool, Row};
+
+const HEADER_ROWS: u32 = 4;
+
+// Colores por nivel: AD verde, A azul, B amarillo, C rojo
+const LEVEL_COLORS: [(&str, u32); 4] = [
+    ("AD", 0x63BE7B),
+    ("A", 0x9BC2E6),
+    ("B", 0xFFE699),
+    ("C", 0xF4B084),
+];
+
+// Carga el consolidado de la sección con la misma información que
+// get_consolidado_section, pero ya agrupada por sesión para armar las hojas
+pub async fn fetch_consolidado_sheets(
+    pool: &PgPool,
+    section_id: i32,
+) -> Result<ConsolidadoExport, sqlx::Error> {
+    let section = fetch_section_info(pool, section_id).await?;
+
+    let students =
+        sqlx::query("SELECT id, full_name FROM students WHERE section_id = $1 ORDER BY full_name")
+            .bind(section_id)
+            .fetch_all(pool)
+            .await?
+            .iter()
+            .map(|r| (r.get::<i32, _>("id"), r.get::<String, _>("full_name")))
+            .collect();
+
+    let mut sessions: Vec<ConsolidadoSheet> =
+        sqlx::query("SELECT id, title, number FROM sessions WHERE section_id = $1 ORDER BY number")
+            .bind(section_id)
+            .fetch_all(pool)
+            .await?
+            .iter()
+            .map(|r| ConsolidadoSheet {
+                session_id: r.get("id"),
+                number: r.get("number"),
+                title: r.get("title"),
+                competencies: Vec::new(),
+            })
+            .collect();
+
+    let rows = sqlx::query(
+        r#"
+        SELECT comp.session_id,
+               comp.id AS competency_id,
+               COALESCE(comp.name, 'Competencia '||comp.number::text) AS competency_name,
+               abl.id AS ability_id,
+               COALESCE(abl.name, 'Capacidad '||abl.number::text) AS ability_name,
+               crt.id AS criterion_id,
+               COALESCE(crt.name, 'C'||crt.number::text) AS criterion_name
+        FROM competencies comp
+        JOIN abilities abl ON abl.competency_id = comp.id
+        JOIN criteria crt ON crt.ability_id = abl.id
+        WHERE comp.session_id IN (SELECT id FROM sessions WHERE section_id = $1)
+        ORDER BY comp.session_id, comp.number, abl.number, crt.number
+        "#,
+    )
+    .bind(section_id)
+    .fetch_all(pool)
+    .await?;
+
+    for row in rows {
+        let session_id: i32 = row.get("session_id");
+        let Some(sheet) = sessions.iter_mut().find(|s| s.session_id == session_id) else {
+            continue;
+        };
+
+        let competency_id: i32 = row.get("competency_id");
+        if sheet.competencies.last().map(|c| c.id) != Some(competency_id) {
+            sheet.competencies.push(ConsolidadoCompetency {
+                id: competency_id,
+                name: row.get("competency_name"),
+                abilities: Vec::new(),
+            });
+        }
+        let competency = sheet.competencies.last_mut().unwrap();
+
+        let ability_id: i32 = row.get("ability_id");
+        if competency.abilities.last().map(|a| a.id) != Some(ability_id) {
+            competency.abilities.push(ConsolidadoAbility {
+                id: ability_id,
+                name: row.get("ability_name"),
+                criteria: Vec::new(),
+            });
+        }
+        competency
+            .abilities
+            .last_mut()
+            .unwrap()
+            .criteria
+            .push((row.get("criterion_id"), row.get("criterion_name")));
+    }
+
+    // Si un criterio se evaluó en varios productos, se toma el último valor registrado
+    let values = sqlx::query(
+        r#"
+        SELECT DISTINCT ON (student_id, criterion_id) student_id, criterion_id, value::text AS value
+        FROM evaluation_items
+        WHERE session_id IN (SELECT id FROM sessions WHERE section_id = $1)
+        ORDER BY student_id, criterion_id, updated_at DESC
+        "#,
+    )
+    .bind(section_id)
+    .fetch_all(pool)
+    .await?
+    .iter()
+    .map(|r| ((r.get("student_id"), r.get("criterion_id")), r.get("value")))
+    .collect();
+
+    let observations = sqlx::query(
+        r#"
+        SELECT student_id, session_id, STRING_AGG(DISTINCT observation, ' || ') AS observation
+        FROM evaluation_items
+        WHERE session_id IN (SELECT id FROM sessions WHERE section_id = $1)
+          AND COALESCE(observation, '') <> ''
+        GROUP BY student_id, session_id
+        "#,
+    )
+    .bind(section_id)
+    .fetch_all(pool)
+    .await?
+    .iter()
+    .map(|r| {
+        (
+            (r.get("student_id"), r.get("session_id")),
+            r.get("observation"),
+        )
+    })
+    .collect();
+
+    Ok(ConsolidadoExport {
+        section,
+        students,
+        sessions,
+        values,
+        observations,
+    })
+}
+
+// Nombre de hoja válido para Excel: máximo 31 caracteres, sin []:*?/\ y sin repetir
+fn sheet_name(sheet: &ConsolidadoSheet, used: &mut Vec<String>) -> String {
+    let base = match &sheet.title {
+        Some(t) if !t.trim().is_empty() => format!("S{} {}", sheet.number, t.trim()),
+        _ => format!("Sesión {}", sheet.number),
+    };
+    let clean: String = base
+        .chars()
+        .map(|c| if "[]:*?/\\".contains(c) { '-' } else { c })
+        .take(31)
+        .collect();
+
+    let mut name = clean.clone();
+    let mut n = 2;
+    while used.iter().any(|u| u.eq_ignore_ascii_case(&name)) {
+        let suffix = format!(" ({})", n);
+        name = clean.chars().take(31 - suffix.len()).collect::<String>() + &suffix;
+        n += 1;
+    }
+    used.push(name.clone());
+    name
+}
+
+// merge_range no acepta una sola celda, así que en ese caso se escribe directamente
+fn merge_or_write(
+    ws: &mut Worksheet,
+    row: (u32, u32),
+    col: (u16, u16),
+    text: &str,
+    format: &Format,
+) -> Result<(), XlsxError> {
+    if row.0 == row.1 && col.0 == col.1 {
+        ws.write_string_with_format(row.0, col.0, text, format)?;
+    } else {
+        ws.merge_range(row.0, col.0, row.1, col.1, text, format)?;
+    }
+    Ok(())
+}
+
+fn write_sheet(
+    ws: &mut Worksheet,
+    export: &ConsolidadoExport,
+    sheet: &ConsolidadoSheet,
+) -> Result<(), XlsxError> {
+    let header = Format::new()
+        .set_bold()
+        .set_text_wrap()
+        .set_align(FormatAlign::Center)
+        .set_align(FormatAlign::VerticalCenter)
+        .set_border(FormatBorder::Thin)
+        .set_background_color(Color::RGB(0xD9E1F2));
+    let cell = Format::new()
+        .set_border(FormatBorder::Thin)
+        .set_align(FormatAlign::Center);
+    let name_cell = Format::new().set_border(FormatBorder::Thin);
+    let obs_cell = Format::new().set_border(FormatBorder::Thin).set_text_wrap();
+
+    let criteria_count: usize = sheet
+        .competencies
+        .iter()
+        .flat_map(|c| c.abilities.iter())
+        .map(|a| a.criteria.len())
+        .sum();
+    let obs_col = 2 + criteria_count as u16;
+
+    let section = &export.section;
+    let title = format!(
+        "{}° {} - {} - Sesión {}{}",
+        section.grade_number,
+        section.section_letter,
+        section.bimester_name,
+        sheet.number,
+        sheet
+            .title
+            .as_deref()
+            .map(|t| format!(": {}", t))
+            .unwrap_or_default()
+    );
+    ws.write_string_with_format(0, 0, &title, &Format::new().set_bold())?;
+
+    merge_or_write(ws, (1, 3), (0, 0), "N°", &header)?;
+    merge_or_write(ws, (1, 3), (1, 1), "Estudiante", &header)?;
+    merge_or_write(ws, (1, 3), (obs_col, obs_col), "Observaciones", &header)?;
+
+    let mut col: u16 = 2;
+    for comp in &sheet.competencies {
+        let comp_width: usize = comp.abilities.iter().map(|a| a.criteria.len()).sum();
+        if comp_width == 0 {
+            continue;
+        }
+        merge_or_write(
+            ws,
+            (1, 1),
+            (col, col + comp_width as u16 - 1),
+            &comp.name,
+            &header,
+        )?;
+        let mut ability_col = col;
+        for ability in &comp.abilities {
+            let width = ability.criteria.len() as u16;
+            merge_or_write(
+                ws,
+                (2, 2),
+                (ability_col, ability_col + width - 1),
+                &ability.name,
+                &header,
+            )?;
+            for (i, (_, criterion)) in ability.criteria.iter().enumerate() {
+                ws.write_string_with_format(3, ability_col + i as u16, criterion, &header)?;
+                ws.set_column_width(ability_col + i as u16, 10)?;
+            }
+            ability_col += width;
+        }
+        col += comp_width as u16;
+    }
+
+    for (i, (student_id, full_name)) in export.students.iter().enumerate() {
+        let row = HEADER_ROWS + i as u32;
+        ws.write_number_with_format(row, 0, (i + 1) as f64, &cell)?;
+        ws.write_string_with_format(row, 1, full_name, &name_cell)?;
+
+        let mut col: u16 = 2;
+        for comp in &sheet.competencies {
+            for ability in &comp.abilities {
+                for (criterion_id, _) in &ability.criteria {
+                    let value = export
+                        .values
+                        .get(&(*student_id, *criterion_id))
+                        .map(String::as_str)
+                        .unwrap_or("");
+                    ws.write_string_with_format(row, col, value, &cell)?;
+                    col += 1;
+                }
+            }
+        }
+
+        let obs = export
+            .observations
+            .get(&(*student_id, sheet.session_id))
+            .map(String::as_str)
+            .unwrap_or("");
+        ws.write_string_with_format(row, obs_col, obs, &obs_cell)?;
+    }
+
+    if criteria_count > 0 && !export.students.is_empty() {
+        let last_row = HEADER_ROWS + export.students.len() as u32 - 1;
+        for (level, color) in LEVEL_COLORS {
+            let rule = ConditionalFormatCell::new()
+                .set_rule(ConditionalFormatCellRule::EqualTo(level))
+                .set_format(Format::new().set_background_color(Color::RGB(color)));
+            ws.add_conditional_format(HEADER_ROWS, 2, last_row, obs_col - 1, &rule)?;
+        }
+    }
+
+    ws.set_column_width(0, 5)?;
+    ws.set_column_width(1, 40)?;
+    ws.set_column_width(obs_col, 50)?;
+    ws.set_row_height(3, 45)?;
+    ws.set_freeze_panes(HEADER_ROWS, 2)?;
+    Ok(())
+}
+
+pub fn build_consolidado_workbook(export: &ConsolidadoExport) -> Result<Vec<u8>, XlsxError> {
+    let mut workbook = Workbook::new();
+    let mut used_names = Vec::new();
+
+    if export.sessions.is_empty() {
+        let ws = workbook.add_worksheet();
+        ws.write_string(0, 0, "La sección no tiene sesiones registradas")?;
+    }
+    for sheet in &export.sessions {
+        let ws = workbook.add_worksheet();
+        ws.set_name(sheet_name(sheet, &mut used_names))?;
+        write_sheet(ws, export, sheet)?;
+    }
+
+    workbook.save_to_buffer()
+}